@@ -0,0 +1,278 @@
+use crate::{
+    database::{Database, StoredActivity},
+    screenpipe::Activity,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File formats activities can be imported from and exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Csv,
+    Json,
+}
+
+impl FileFormat {
+    /// Guess the format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// CSV header written on export, matching the `ActivityRecord` fields
+pub const COLUMNS: [&str; 9] = [
+    "timestamp",
+    "duration_secs",
+    "app_name",
+    "window_title",
+    "description",
+    "session_tags",
+    "attributed_issue",
+    "attribution_source",
+    "category",
+];
+
+/// One activity row; the column names double as the CSV header and JSON keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityRecord {
+    pub timestamp: DateTime<Utc>,
+    pub duration_secs: u64,
+    pub app_name: String,
+    pub window_title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Comma-separated tags of the activity's session; applied to new sessions on import
+    #[serde(default)]
+    pub session_tags: String,
+    /// Issue the time was logged to and how it was chosen (override, regex, llm, manual);
+    /// informational, ignored on import
+    #[serde(default)]
+    pub attributed_issue: String,
+    #[serde(default)]
+    pub attribution_source: String,
+    /// Category from `tracking.category_rules`; informational, re-derived on import
+    #[serde(default)]
+    pub category: String,
+}
+
+impl ActivityRecord {
+    fn validate(&self) -> Result<()> {
+        if self.duration_secs == 0 {
+            anyhow::bail!("duration_secs must be greater than 0");
+        }
+        if self.app_name.trim().is_empty() && self.window_title.trim().is_empty() {
+            anyhow::bail!("app_name and window_title are both empty");
+        }
+        Ok(())
+    }
+}
+
+impl From<&StoredActivity> for ActivityRecord {
+    fn from(activity: &StoredActivity) -> Self {
+        Self {
+            timestamp: activity.timestamp,
+            duration_secs: activity.duration_secs,
+            app_name: activity.app_name.clone(),
+            window_title: activity.window_title.clone(),
+            description: activity.description.clone(),
+            session_tags: String::new(),
+            attributed_issue: activity.attributed_issue.clone().unwrap_or_default(),
+            attribution_source: activity
+                .attribution_source
+                .map(|source| source.as_str().to_string())
+                .unwrap_or_default(),
+            category: activity.category.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl From<ActivityRecord> for Activity {
+    fn from(record: ActivityRecord) -> Self {
+        Activity {
+            timestamp: record.timestamp,
+            duration_secs: record.duration_secs,
+            window_title: record.window_title,
+            app_name: record.app_name,
+            description: record.description,
+            browser_url: None,
+        }
+    }
+}
+
+/// Outcome of an import: rows stored and per-row errors that were skipped
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub session_id: i64,
+    pub imported: usize,
+    /// (location, message) for each rejected row, e.g. ("line 3", "duration_secs ...")
+    pub errors: Vec<RowError>,
+}
+
+/// A record with its location in the source file, e.g. "line 3"
+type LocatedRecord = (String, ActivityRecord);
+
+/// An error message with the location of the offending row
+type RowError = (String, String);
+
+/// Parse `content` into located records, collecting a located error for each unparsable row
+fn parse_records(content: &str, format: FileFormat) -> Result<(Vec<LocatedRecord>, Vec<RowError>)> {
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+
+    match format {
+        FileFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(content.as_bytes());
+            let headers = reader
+                .headers()
+                .context("Failed to read CSV header")?
+                .clone();
+            for result in reader.records() {
+                let row = match result {
+                    Ok(row) => row,
+                    Err(e) => {
+                        let line = e.position().map(|p| p.line()).unwrap_or_default();
+                        errors.push((format!("line {}", line), e.to_string()));
+                        continue;
+                    }
+                };
+                let location = format!(
+                    "line {}",
+                    row.position().map(|p| p.line()).unwrap_or_default()
+                );
+                match row.deserialize::<ActivityRecord>(Some(&headers)) {
+                    Ok(record) => records.push((location, record)),
+                    Err(e) => errors.push((location, e.to_string())),
+                }
+            }
+        }
+        FileFormat::Json => {
+            let rows: Vec<serde_json::Value> =
+                serde_json::from_str(content).context("Expected a JSON array of activities")?;
+            for (index, row) in rows.into_iter().enumerate() {
+                let location = format!("entry {}", index + 1);
+                match serde_json::from_value::<ActivityRecord>(row) {
+                    Ok(record) => records.push((location, record)),
+                    Err(e) => errors.push((location, e.to_string())),
+                }
+            }
+        }
+    }
+
+    Ok((records, errors))
+}
+
+/// Import activities from `content` into `session_id`, or into a new session spanning the
+/// imported activities when `None`. Invalid rows are reported and skipped.
+pub fn import_activities(
+    database: &Database,
+    content: &str,
+    format: FileFormat,
+    session_id: Option<i64>,
+) -> Result<ImportReport> {
+    let (records, mut errors) = parse_records(content, format)?;
+
+    let mut valid = Vec::with_capacity(records.len());
+    for (location, record) in records {
+        match record.validate() {
+            Ok(()) => valid.push(record),
+            Err(e) => errors.push((location, e.to_string())),
+        }
+    }
+
+    let session_id = match session_id {
+        Some(id) => id,
+        None => {
+            let start = valid
+                .iter()
+                .map(|r| r.timestamp)
+                .min()
+                .unwrap_or_else(Utc::now);
+            let end = valid
+                .iter()
+                .map(|r| r.timestamp + chrono::Duration::seconds(r.duration_secs as i64))
+                .max()
+                .unwrap_or(start);
+            let session_id = database.create_closed_session(start, end)?;
+            let tags: Vec<String> = valid
+                .iter()
+                .flat_map(|r| r.session_tags.split(','))
+                .map(str::to_string)
+                .collect();
+            database.set_session_tags(session_id, &tags)?;
+            session_id
+        }
+    };
+
+    let mut imported = 0;
+    for record in valid {
+        database.store_activity(session_id, &Activity::from(record))?;
+        imported += 1;
+    }
+
+    Ok(ImportReport {
+        session_id,
+        imported,
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::ActivityTier;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_import_csv_skips_bad_rows() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db = Database::new(temp_file.path().to_path_buf()).unwrap();
+
+        let csv = "\
+timestamp,duration_secs,app_name,window_title,description
+2024-05-01T09:00:00Z,1800,Code,PROJ-1 main.rs,fn main()
+2024-05-01T09:30:00Z,120,Slack,#team,
+2024-05-01T09:32:00Z,not-a-number,Slack,#team,
+2024-05-01T09:40:00Z,0,Code,PROJ-2,
+";
+
+        let report = import_activities(&db, csv, FileFormat::Csv, None).unwrap();
+        assert_eq!(report.imported, 2);
+        let locations: Vec<&str> = report.errors.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(locations, vec!["line 4", "line 5"]);
+
+        let activities = db.get_session_activities(report.session_id, None).unwrap();
+        assert_eq!(activities.len(), 2);
+        let billable = db
+            .get_session_activities(report.session_id, Some(ActivityTier::Billable))
+            .unwrap();
+        assert_eq!(billable.len(), 1);
+        assert_eq!(billable[0].window_title, "PROJ-1 main.rs");
+
+        let stats = db.get_session_stats(report.session_id).unwrap();
+        assert_eq!(stats.start_time.to_rfc3339(), "2024-05-01T09:00:00+00:00");
+    }
+
+    #[test]
+    fn test_import_json_into_existing_session() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let session_id = db.create_session().unwrap();
+
+        let json = r#"[
+            {"timestamp": "2024-05-01T09:00:00Z", "duration_secs": 900, "app_name": "Code", "window_title": "PROJ-1"},
+            {"timestamp": "yesterday", "duration_secs": 60, "app_name": "Code", "window_title": "PROJ-2"}
+        ]"#;
+
+        let report = import_activities(&db, json, FileFormat::Json, Some(session_id)).unwrap();
+        assert_eq!(report.session_id, session_id);
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.errors[0].0, "entry 2");
+    }
+}
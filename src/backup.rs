@@ -0,0 +1,128 @@
+//! Timestamped snapshots of the analytics database, taken by the `backup` command and on a
+//! schedule while the daemon runs.
+
+use crate::database::Database;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SNAPSHOT_PREFIX: &str = "analytics-";
+const SNAPSHOT_EXTENSION: &str = ".db";
+
+/// Back up the database to a new timestamped file in `dir` and delete all but the newest
+/// `keep` snapshots there. Returns the new snapshot's path.
+pub fn snapshot(database: &Database, dir: &Path, keep: usize) -> Result<PathBuf> {
+    let path = dir.join(format!(
+        "{}{}{}",
+        SNAPSHOT_PREFIX,
+        Utc::now().format("%Y%m%d-%H%M%S"),
+        SNAPSHOT_EXTENSION
+    ));
+    database.backup_to(&path)?;
+
+    let removed = prune(dir, keep)?;
+    if removed > 0 {
+        log::debug!("Deleted {} old backups from {}", removed, dir.display());
+    }
+    Ok(path)
+}
+
+/// Delete the oldest snapshots in `dir` beyond `keep`; other files are left alone
+fn prune(dir: &Path, keep: usize) -> Result<usize> {
+    let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(SNAPSHOT_EXTENSION)
+                })
+        })
+        .collect();
+    // Timestamps in the names sort chronologically
+    snapshots.sort();
+
+    let excess = snapshots.len().saturating_sub(keep);
+    for path in &snapshots[..excess] {
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to delete old backup {}", path.display()))?;
+    }
+    Ok(excess)
+}
+
+/// Take a snapshot every `interval` until the task is aborted. Failures are logged and
+/// retried at the next interval.
+pub async fn run_scheduled(database: Database, dir: PathBuf, interval: Duration, keep: usize) {
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        ticker.tick().await;
+
+        let (database, dir) = (database.clone(), dir.clone());
+        match tokio::task::spawn_blocking(move || snapshot(&database, &dir, keep)).await {
+            Ok(Ok(path)) => log::info!("Backed up the database to {}", path.display()),
+            Ok(Err(e)) => log::error!("Scheduled database backup failed: {:#}", e),
+            Err(e) => log::error!("Scheduled database backup panicked: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screenpipe::Activity;
+
+    #[test]
+    fn test_backup_is_created_and_openable() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::new(dir.path().join("analytics.db")).unwrap();
+        let session_id = database.create_session().unwrap();
+        let activity = Activity {
+            timestamp: Utc::now(),
+            duration_secs: 900,
+            window_title: "PROJ-1 main.rs".to_string(),
+            app_name: "Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        };
+        database.store_activity(session_id, &activity).unwrap();
+
+        let path = snapshot(&database, &dir.path().join("backups"), 7).unwrap();
+        assert!(path.exists());
+
+        let restored = Database::new(path).unwrap();
+        let activities = restored.get_session_activities(session_id, None).unwrap();
+        assert_eq!(activities.len(), 1);
+        assert_eq!(activities[0].window_title, "PROJ-1 main.rs");
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "analytics-20240101-000000.db",
+            "analytics-20240102-000000.db",
+            "analytics-20240103-000000.db",
+            "notes.txt",
+        ] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+
+        assert_eq!(prune(dir.path(), 2).unwrap(), 1);
+
+        let mut left: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            vec![
+                "analytics-20240102-000000.db",
+                "analytics-20240103-000000.db",
+                "notes.txt"
+            ]
+        );
+    }
+}
@@ -10,6 +10,9 @@ use tauri::{
     WindowEvent,
 };
 
+#[path = "../format.rs"]
+mod format;
+
 const DAEMON_PORT: u16 = 8787;
 const DAEMON_URL: &str = "http://127.0.0.1:8787";
 
@@ -24,6 +27,13 @@ struct IssueRequest {
     issue_key: Option<String>,
 }
 
+/// Response of the daemon's `GET /summary/today`
+#[derive(Debug, Clone, Deserialize)]
+struct TodayTotals {
+    tracked_secs: u64,
+    current_issue: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ActivitySummary {
     current_issue: Option<String>,
@@ -111,15 +121,28 @@ async fn set_issue_override(
 async fn get_activity_summary(
     state: State<'_, Arc<Mutex<AppState>>>,
 ) -> Result<ActivitySummary, String> {
+    let daemon_url = {
+        let state = state.lock().unwrap();
+        state.daemon_url.clone()
+    };
+
     // Get current status
     let status = get_status(state).await?;
 
-    // TODO: In the future, query actual tracking data from daemon
-    // For now, return basic info
+    let today: TodayTotals = reqwest::Client::new()
+        .get(&format!("{}/summary/today", daemon_url))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get today's summary: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse today's summary: {}", e))?;
+
     let is_tracking = status.issue_override.is_some();
     Ok(ActivitySummary {
-        current_issue: status.issue_override,
-        total_tracked_today: "0h 0m".to_string(),
+        current_issue: status.issue_override.or(today.current_issue),
+        total_tracked_today: format::format_hm(today.tracked_secs),
         is_tracking,
     })
 }
@@ -132,19 +155,19 @@ async fn get_recent_issues() -> Result<Vec<RecentIssue>, String> {
         RecentIssue {
             key: "PROJ-123".to_string(),
             title: Some("Implement new feature".to_string()),
-            total_time: "2h 30m".to_string(),
+            total_time: format::format_hm(9000),
             last_used: "2025-11-19T10:30:00Z".to_string(),
         },
         RecentIssue {
             key: "PROJ-456".to_string(),
             title: Some("Fix critical bug".to_string()),
-            total_time: "1h 15m".to_string(),
+            total_time: format::format_hm(4500),
             last_used: "2025-11-19T09:00:00Z".to_string(),
         },
         RecentIssue {
             key: "PROJ-789".to_string(),
             title: Some("Code review".to_string()),
-            total_time: "45m".to_string(),
+            total_time: format::format_hm(2700),
             last_used: "2025-11-18T16:20:00Z".to_string(),
         },
     ])
@@ -155,21 +178,21 @@ async fn get_daily_summary() -> Result<DailySummary, String> {
     // TODO: Query actual daily summary from daemon/database
     // For now, return mock data
     Ok(DailySummary {
-        total_time: "4h 30m".to_string(),
+        total_time: format::format_hm(16200),
         issues: vec![
             IssueTime {
                 issue_key: "PROJ-123".to_string(),
-                duration: "2h 30m".to_string(),
+                duration: format::format_hm(9000),
                 percentage: 55.56,
             },
             IssueTime {
                 issue_key: "PROJ-456".to_string(),
-                duration: "1h 15m".to_string(),
+                duration: format::format_hm(4500),
                 percentage: 27.78,
             },
             IssueTime {
                 issue_key: "PROJ-789".to_string(),
-                duration: "45m".to_string(),
+                duration: format::format_hm(2700),
                 percentage: 16.67,
             },
         ],
@@ -177,33 +200,51 @@ async fn get_daily_summary() -> Result<DailySummary, String> {
 }
 
 #[tauri::command]
-async fn get_notification_prefs() -> Result<NotificationPrefs, String> {
-    // TODO: Load actual preferences from config file or database
-    // For now, return mock data
-    Ok(NotificationPrefs {
-        enabled: true,
-        frequency: "hourly".to_string(),
-    })
+async fn get_notification_prefs(
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<NotificationPrefs, String> {
+    let daemon_url = {
+        let state = state.lock().unwrap();
+        state.daemon_url.clone()
+    };
+
+    let client = reqwest::Client::new();
+
+    client
+        .get(&format!("{}/notifications", daemon_url))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get notification preferences: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse notification preferences: {}", e))
 }
 
 #[tauri::command]
-async fn set_notification_prefs(prefs: NotificationPrefs) -> Result<(), String> {
-    // TODO: Persist preferences to config file or database
-    // For now, just validate and return success
-
-    // Validate frequency
-    let valid_frequencies = ["immediate", "hourly", "daily"];
-    if !valid_frequencies.contains(&prefs.frequency.as_str()) {
-        return Err(format!(
-            "Invalid frequency '{}'. Must be one of: immediate, hourly, daily",
-            prefs.frequency
-        ));
-    }
+async fn set_notification_prefs(
+    prefs: NotificationPrefs,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<(), String> {
+    let daemon_url = {
+        let state = state.lock().unwrap();
+        state.daemon_url.clone()
+    };
+
+    let client = reqwest::Client::new();
 
-    println!(
-        "Notification preferences updated: enabled={}, frequency={}",
-        prefs.enabled, prefs.frequency
-    );
+    // The daemon validates the frequency and persists the preferences to the config file
+    let response = client
+        .post(&format!("{}/notifications", daemon_url))
+        .json(&prefs)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to set notification preferences: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(response.text().await.unwrap_or_default());
+    }
 
     Ok(())
 }
@@ -219,26 +260,21 @@ async fn open_dashboard(app: AppHandle) -> Result<(), String> {
     }
 
     // Create new dashboard window
-    let dashboard_window = WebviewWindowBuilder::new(
-        &app,
-        "dashboard",
-        WebviewUrl::App("dashboard.html".into()),
-    )
-    .title("WorkToJiraEffort - Dashboard")
-    .inner_size(800.0, 600.0)
-    .min_inner_size(600.0, 400.0)
-    .resizable(true)
-    .decorations(true)
-    .always_on_top(false)
-    .skip_taskbar(false)
-    .center()
-    .visible(true)
-    .build()
-    .map_err(|e| format!("Failed to create dashboard window: {}", e))?;
-
-    dashboard_window
-        .set_focus()
-        .map_err(|e| e.to_string())?;
+    let dashboard_window =
+        WebviewWindowBuilder::new(&app, "dashboard", WebviewUrl::App("dashboard.html".into()))
+            .title("WorkToJiraEffort - Dashboard")
+            .inner_size(800.0, 600.0)
+            .min_inner_size(600.0, 400.0)
+            .resizable(true)
+            .decorations(true)
+            .always_on_top(false)
+            .skip_taskbar(false)
+            .center()
+            .visible(true)
+            .build()
+            .map_err(|e| format!("Failed to create dashboard window: {}", e))?;
+
+    dashboard_window.set_focus().map_err(|e| e.to_string())?;
 
     Ok(())
 }
@@ -259,20 +295,17 @@ fn show_menubar_popup(app: &AppHandle) -> Result<(), String> {
     }
 
     // Create new popup window
-    let popup_window = WebviewWindowBuilder::new(
-        app,
-        "menubar",
-        WebviewUrl::App("menubar.html".into()),
-    )
-    .title("WorkToJiraEffort")
-    .inner_size(350.0, 450.0)
-    .resizable(false)
-    .decorations(false)
-    .always_on_top(true)
-    .skip_taskbar(true)
-    .visible(false) // Start hidden, will position then show
-    .build()
-    .map_err(|e| format!("Failed to create menubar popup: {}", e))?;
+    let popup_window =
+        WebviewWindowBuilder::new(app, "menubar", WebviewUrl::App("menubar.html".into()))
+            .title("WorkToJiraEffort")
+            .inner_size(350.0, 450.0)
+            .resizable(false)
+            .decorations(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .visible(false) // Start hidden, will position then show
+            .build()
+            .map_err(|e| format!("Failed to create menubar popup: {}", e))?;
 
     // Position near tray icon
     position_popup_near_tray(&popup_window)?;
@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::process::{Child, Command};
 use std::sync::{Arc, Mutex};
@@ -11,6 +12,16 @@ use tray_icon::{
     TrayIconBuilder, TrayIconEvent,
 };
 
+#[path = "../issue_key.rs"]
+mod issue_key;
+
+#[cfg(windows)]
+#[path = "../process_job.rs"]
+mod process_job;
+
+#[path = "../verbosity.rs"]
+mod verbosity;
+
 const DAEMON_PORT: u16 = 8787;
 const DAEMON_URL: &str = "http://127.0.0.1:8787";
 
@@ -18,6 +29,35 @@ const DAEMON_URL: &str = "http://127.0.0.1:8787";
 struct StatusResponse {
     version: String,
     issue_override: Option<String>,
+    // Missing from older daemons
+    #[serde(default)]
+    current_state: Option<String>,
+    #[serde(default)]
+    last_sync: Option<DateTime<Utc>>,
+    #[serde(default)]
+    last_activity_at: Option<DateTime<Utc>>,
+}
+
+/// Captures older than this while tracking mean Screenpipe has probably stopped recording
+const QUIET_AFTER_MINUTES: i64 = 15;
+
+/// Tracking state and how long ago the last capture was, e.g. "tracking (last activity 2m ago)"
+fn activity_text(status: &StatusResponse, now: DateTime<Utc>) -> Option<String> {
+    let state = status.current_state.as_deref()?;
+    let Some(last_activity) = status.last_activity_at else {
+        return Some(state.to_string());
+    };
+
+    let minutes = (now - last_activity).num_minutes().max(0);
+    let quiet = state == "tracking"
+        && minutes >= QUIET_AFTER_MINUTES
+        && status.last_sync.is_some_and(|sync| sync > last_activity);
+    Some(format!(
+        "{}{} (last activity {}m ago)",
+        if quiet { "⚠ " } else { "" },
+        state,
+        minutes
+    ))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +67,9 @@ struct IssueRequest {
 
 struct AppState {
     daemon_process: Option<Child>,
+    /// Ends the daemon and its Screenpipe if the tray exits or crashes
+    #[cfg(windows)]
+    daemon_job: Option<process_job::ProcessJob>,
 }
 
 // Menu item IDs to track which item was clicked
@@ -47,7 +90,15 @@ enum UserEvent {
 }
 
 fn main() -> Result<()> {
-    env_logger::init();
+    verbosity::init_env_logger(verbosity::level_from_args(std::env::args().skip(1)));
+
+    // tao drives the GTK main loop on Linux, which needs an X11 or Wayland session
+    #[cfg(target_os = "linux")]
+    if std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Err(anyhow::anyhow!(
+            "No graphical session found (neither DISPLAY nor WAYLAND_DISPLAY is set)"
+        ));
+    }
 
     // On macOS, activate NSApplication
     #[cfg(target_os = "macos")]
@@ -70,6 +121,8 @@ fn main() -> Result<()> {
 
     let state = Arc::new(Mutex::new(AppState {
         daemon_process: None,
+        #[cfg(windows)]
+        daemon_job: None,
     }));
 
     // Start daemon in background thread
@@ -97,27 +150,9 @@ fn main() -> Result<()> {
         let _ = proxy.send_event(UserEvent::MenuEvent(event));
     }));
 
-    println!("Creating tray icon...");
-
-    // Create tray icon
-    let icon = create_icon_image();
-    let tray_icon = TrayIconBuilder::new()
-        .with_tooltip("WorkToJiraEffort")
-        .with_icon(icon)
-        .build()
-        .context("Failed to create tray icon")?;
-
-    println!("Creating menu...");
-    let (menu, menu_ids) = create_menu()?;
-    let menu_ids = Arc::new(Mutex::new(menu_ids));
-
-    // Set menu on tray icon
-    tray_icon.set_menu(Some(Box::new(menu)));
-
-    println!("WorkToJiraEffort menubar app started!");
-    println!("Daemon running on port {}", DAEMON_PORT);
-    println!("Look for the blue icon in your menubar (top-right corner)");
-
+    // On Linux the tray icon and menu must be created on the thread running the GTK loop,
+    // once it is running, so they are built on the first event on every platform
+    let mut tray: Option<(tray_icon::TrayIcon, Arc<Mutex<MenuIds>>)> = None;
     let state_clone = Arc::clone(&state);
 
     // Run event loop
@@ -127,14 +162,30 @@ fn main() -> Result<()> {
         match event {
             Event::NewEvents(StartCause::Init) => {
                 println!("Event loop initialized");
+                match create_tray() {
+                    Ok(created) => {
+                        println!("WorkToJiraEffort menubar app started!");
+                        println!("Daemon running on port {}", DAEMON_PORT);
+                        println!("{}", TRAY_LOCATION_HINT);
+                        tray = Some(created);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to create tray icon: {:#}", e);
+                        #[cfg(target_os = "linux")]
+                        eprintln!("The tray needs libayatana-appindicator (or libappindicator) and a panel that shows StatusNotifier icons");
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
             }
             Event::UserEvent(UserEvent::TrayIconEvent(event)) => {
                 println!("Tray icon event: {:?}", event);
             }
             Event::UserEvent(UserEvent::MenuEvent(event)) => {
                 println!("Menu event received: {:?}", event.id);
-                if let Err(e) = handle_menu_event(event, &state_clone, &tray_icon, &menu_ids) {
-                    log::error!("Error handling menu event: {}", e);
+                if let Some((tray_icon, menu_ids)) = &tray {
+                    if let Err(e) = handle_menu_event(event, &state_clone, tray_icon, menu_ids) {
+                        log::error!("Error handling menu event: {}", e);
+                    }
                 }
             }
             _ => {}
@@ -142,6 +193,31 @@ fn main() -> Result<()> {
     });
 }
 
+/// Where users should look for the icon
+#[cfg(target_os = "macos")]
+const TRAY_LOCATION_HINT: &str = "Look for the blue icon in your menubar (top-right corner)";
+#[cfg(target_os = "linux")]
+const TRAY_LOCATION_HINT: &str =
+    "Look for the blue icon in your panel's status area (GNOME needs the AppIndicator extension)";
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+const TRAY_LOCATION_HINT: &str = "Look for the blue icon in the notification area";
+
+/// Build the tray icon with its menu; must run on the event loop thread
+fn create_tray() -> Result<(tray_icon::TrayIcon, Arc<Mutex<MenuIds>>)> {
+    println!("Creating tray icon...");
+    let tray_icon = TrayIconBuilder::new()
+        .with_tooltip("WorkToJiraEffort")
+        .with_icon(create_icon_image())
+        .build()
+        .context("Failed to create tray icon")?;
+
+    println!("Creating menu...");
+    let (menu, menu_ids) = create_menu()?;
+    tray_icon.set_menu(Some(Box::new(menu)));
+
+    Ok((tray_icon, Arc::new(Mutex::new(menu_ids))))
+}
+
 fn start_daemon(state: &Arc<Mutex<AppState>>) -> Result<()> {
     // Check if daemon is already running
     if check_daemon_health().is_ok() {
@@ -175,6 +251,13 @@ fn start_daemon(state: &Arc<Mutex<AppState>>) -> Result<()> {
         .context("Failed to start daemon process")?;
 
     let mut state = state.lock().unwrap();
+
+    #[cfg(windows)]
+    match process_job::ProcessJob::new().and_then(|job| job.assign(&child).map(|_| job)) {
+        Ok(job) => state.daemon_job = Some(job),
+        Err(e) => eprintln!("Daemon may outlive the tray: {:#}", e),
+    }
+
     state.daemon_process = Some(child);
 
     Ok(())
@@ -200,6 +283,9 @@ fn get_status() -> Result<StatusResponse> {
 }
 
 fn set_issue_override(issue_key: Option<String>) -> Result<StatusResponse> {
+    let issue_key = issue_key
+        .map(|key| issue_key::normalize(&key))
+        .transpose()?;
     let client = reqwest::blocking::Client::new();
     let response = client
         .post(&format!("{}/issue", DAEMON_URL))
@@ -210,16 +296,25 @@ fn set_issue_override(issue_key: Option<String>) -> Result<StatusResponse> {
     Ok(response)
 }
 
+/// Icon edge in pixels: 22 is the macOS menubar size, Linux panels scale a larger image down
+#[cfg(target_os = "linux")]
+const ICON_SIZE: usize = 32;
+#[cfg(not(target_os = "linux"))]
+const ICON_SIZE: usize = 22;
+
 fn create_icon_image() -> tray_icon::Icon {
-    // Create a 22x22 RGBA icon (blue square) - standard macOS menubar size
-    let size = 22;
+    // Blue square; on Linux with a light border so it stays visible on dark panel themes
+    let size = ICON_SIZE;
+    let border = if cfg!(target_os = "linux") { 2 } else { 0 };
     let mut rgba = Vec::with_capacity(size * size * 4);
-    for _y in 0..size {
-        for _x in 0..size {
-            rgba.push(0x41); // R
-            rgba.push(0x69); // G
-            rgba.push(0xE1); // B
-            rgba.push(0xFF); // A
+    for y in 0..size {
+        for x in 0..size {
+            let edge = x < border || y < border || x >= size - border || y >= size - border;
+            if edge {
+                rgba.extend_from_slice(&[0xF0, 0xF0, 0xF0, 0xFF]);
+            } else {
+                rgba.extend_from_slice(&[0x41, 0x69, 0xE1, 0xFF]);
+            }
         }
     }
 
@@ -230,19 +325,23 @@ fn create_menu() -> Result<(Menu, MenuIds)> {
     let menu = Menu::new();
 
     // Status item - get current status from daemon
-    let status_text = match get_status() {
-        Ok(status) => {
+    let status = get_status().ok();
+    let status_text = match &status {
+        Some(status) => {
             if let Some(ref issue) = status.issue_override {
                 format!("Current: {}", issue)
             } else {
                 "No override set".to_string()
             }
         }
-        Err(_) => "Status: Unknown".to_string(),
+        None => "Status: Unknown".to_string(),
     };
 
     let status_item = MenuItem::new(status_text, false, None);
     menu.append(&status_item)?;
+    if let Some(text) = status.and_then(|status| activity_text(&status, Utc::now())) {
+        menu.append(&MenuItem::new(text, false, None))?;
+    }
     menu.append(&PredefinedMenuItem::separator())?;
 
     // Open Dashboard
@@ -383,6 +482,10 @@ fn handle_menu_event(
         println!("Quitting...");
         // Kill daemon if we started it
         let mut state = state.lock().unwrap();
+        #[cfg(windows)]
+        if let Some(job) = state.daemon_job.take() {
+            job.terminate();
+        }
         if let Some(mut child) = state.daemon_process.take() {
             let _ = child.kill();
             let _ = child.wait();
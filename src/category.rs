@@ -0,0 +1,147 @@
+//! Cheap keyword rules that tag activities with a category (e.g. Slack → communication)
+//! when they are stored, before any LLM analysis.
+
+use crate::config::CategoryRule;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    /// Lowercased app name substring
+    app: Option<String>,
+    title: Option<Regex>,
+    category: String,
+}
+
+impl CompiledRule {
+    fn matches(&self, app_name: &str, window_title: &str) -> bool {
+        self.app
+            .as_ref()
+            .is_none_or(|app| app_name.to_lowercase().contains(app.as_str()))
+            && self
+                .title
+                .as_ref()
+                .is_none_or(|title| title.is_match(window_title))
+    }
+}
+
+/// Category rules compiled from `tracking.category_rules`. The first matching rule wins.
+#[derive(Debug, Clone, Default)]
+pub struct Categorizer {
+    rules: Vec<CompiledRule>,
+}
+
+impl Categorizer {
+    /// Compile `rules`, failing on an invalid regex or a rule that matches everything
+    pub fn new(rules: &[CategoryRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                let category = rule.category.trim();
+                if category.is_empty() {
+                    anyhow::bail!("tracking.category_rules[{}] has an empty category", index);
+                }
+                let app = rule
+                    .match_app
+                    .as_deref()
+                    .map(|app| app.trim().to_lowercase())
+                    .filter(|app| !app.is_empty());
+                let title = rule
+                    .match_title_regex
+                    .as_deref()
+                    .map(|pattern| {
+                        Regex::new(pattern).with_context(|| {
+                            format!(
+                                "Invalid tracking.category_rules[{}] title regex '{}'",
+                                index, pattern
+                            )
+                        })
+                    })
+                    .transpose()?;
+                if app.is_none() && title.is_none() {
+                    anyhow::bail!(
+                        "tracking.category_rules[{}] needs match_app or match_title_regex",
+                        index
+                    );
+                }
+
+                Ok(CompiledRule {
+                    app,
+                    title,
+                    category: category.to_string(),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Category of the first rule whose app substring (ignoring case) and title regex both
+    /// match; a rule only checks the parts it sets
+    pub fn categorize(&self, app_name: &str, window_title: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(app_name, window_title))
+            .map(|rule| rule.category.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(app: Option<&str>, title: Option<&str>, category: &str) -> CategoryRule {
+        CategoryRule {
+            match_app: app.map(str::to_string),
+            match_title_regex: title.map(str::to_string),
+            category: category.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rules_tag_by_app_and_title() {
+        let categorizer = Categorizer::new(&[
+            rule(Some("Slack"), None, "communication"),
+            rule(None, Some(r"\bPR\b|Pull Request"), "review"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            categorizer.categorize("slack", "#team"),
+            Some("communication")
+        );
+        assert_eq!(
+            categorizer.categorize("Firefox", "Fix parser - Pull Request #12"),
+            Some("review")
+        );
+        assert_eq!(categorizer.categorize("Code", "main.rs"), None);
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let categorizer = Categorizer::new(&[
+            rule(Some("Code"), Some("test"), "testing"),
+            rule(Some("Code"), None, "coding"),
+            rule(None, Some("test"), "qa"),
+        ])
+        .unwrap();
+
+        // Both Code rules match, and the app-and-title rule comes first
+        assert_eq!(
+            categorizer.categorize("Code", "parser_test.rs"),
+            Some("testing")
+        );
+        assert_eq!(categorizer.categorize("Code", "main.rs"), Some("coding"));
+        assert_eq!(categorizer.categorize("Firefox", "test plan"), Some("qa"));
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        let error = Categorizer::new(&[rule(None, Some("("), "broken")]).unwrap_err();
+        assert!(format!("{:#}", error).contains("category_rules[0]"));
+
+        assert!(Categorizer::new(&[rule(None, None, "everything")]).is_err());
+        assert!(Categorizer::new(&[rule(Some("Slack"), None, " ")]).is_err());
+    }
+}
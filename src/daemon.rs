@@ -1,18 +1,50 @@
-use crate::{config::Config, screenpipe_manager::ScreenpipeManager, tracker::WorkTracker};
+use crate::{
+    activity_file::FileFormat,
+    backup,
+    config::{Config, NotificationFrequency, NotificationsConfig, TrackingConfig},
+    database::{
+        self, ActivityUpdate, AnalysisFlag, AnalysisResult, Database, DayTotals, Granularity,
+        PeriodStats, SessionStats, TimelineSegment,
+    },
+    events::{self, TrackerEvent},
+    export, format, heartbeat, issue_key,
+    jira::JiraClient,
+    llm::AssignedIssue,
+    metrics::{self, Metrics},
+    screenpipe_manager::ScreenpipeManager,
+    state::TrackerStatus,
+    tracker::WorkTracker,
+};
 use anyhow::{Context, Result};
 use axum::{
-    extract::State,
-    routing::{get, post},
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{get, patch, post},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream};
 use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, sync::Arc};
-use tokio::{net::TcpListener, signal, sync::RwLock};
+use std::{convert::Infallible, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+use tokio::{
+    net::TcpListener,
+    signal,
+    sync::{broadcast, watch, RwLock},
+    task::JoinHandle,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// How long shutdown waits for the tracker to finish its final sync and analysis
+const TRACKER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(120);
+
 /// Run the long-lived daemon that can be controlled by external clients (e.g., menubar app)
-pub async fn run_daemon(port: u16, mut screenpipe: ScreenpipeManager) -> Result<()> {
+pub async fn run_daemon(
+    mut config: Config,
+    port: u16,
+    mut screenpipe: ScreenpipeManager,
+) -> Result<()> {
     // On macOS, if launched from tray app, don't show in dock
     #[cfg(target_os = "macos")]
     if std::env::var("WORK_TO_JIRA_NO_DOCK").is_ok() {
@@ -29,20 +61,49 @@ pub async fn run_daemon(port: u16, mut screenpipe: ScreenpipeManager) -> Result<
         }
     }
 
-    let config = Config::load().context("Failed to load configuration")?;
+    // Screenpipe may have fallen back to another port if the configured one was taken
+    config.screenpipe.port = screenpipe.port();
     let issue_override = Arc::new(RwLock::new(None));
+    let notification_prefs = Arc::new(RwLock::new(config.notifications.clone()));
+    let tracking_settings = Arc::new(RwLock::new(config.tracking.clone()));
+    let events = events::channel();
+    let tracker_status = Arc::new(RwLock::new(TrackerStatus::default()));
+    let heartbeat = crate::paths::database_path(&config)?.with_file_name(heartbeat::FILE_NAME);
+    let metrics = Arc::new(Metrics::default());
+
+    // Shared pool for the tracker loop and API handlers
+    let database = WorkTracker::open_database(&config)?;
+    // Client for issue searches from the API; the tracker loop builds its own
+    let jira =
+        WorkTracker::open_jira(&config, crate::http::build_client(&config.network)?)?.map(Arc::new);
 
     // Start tracker loop in the background
-    {
+    let (tracker_shutdown, tracker_shutdown_rx) = watch::channel(false);
+    let tracker_handle = {
         let tracker_issue_override = Arc::clone(&issue_override);
+        let tracker_database = database.clone();
+        let tracker_notification_prefs = Arc::clone(&notification_prefs);
+        let tracker_tracking_settings = Arc::clone(&tracking_settings);
+        let tracker_events = events.clone();
+        let tracker_status_handle = Arc::clone(&tracker_status);
+        let tracker_heartbeat = heartbeat.clone();
+        let tracker_metrics = Arc::clone(&metrics);
         let config_clone = config.clone();
 
         tokio::spawn(async move {
             let interval = config_clone.tracking.screenpipe_poll_interval_secs;
 
-            match WorkTracker::new(config_clone, tracker_issue_override) {
-                Ok(mut tracker) => {
-                    if let Err(err) = tracker.run(interval).await {
+            match WorkTracker::with_database(config_clone, tracker_issue_override, tracker_database)
+            {
+                Ok(tracker) => {
+                    let mut tracker = tracker
+                        .with_notification_prefs(tracker_notification_prefs)
+                        .with_tracking_settings(tracker_tracking_settings)
+                        .with_events(tracker_events)
+                        .with_status(tracker_status_handle)
+                        .with_heartbeat(tracker_heartbeat)
+                        .with_metrics(tracker_metrics);
+                    if let Err(err) = tracker.run(interval, tracker_shutdown_rx).await {
                         log::error!("Tracker daemon exited with error: {}", err);
                     }
                 }
@@ -50,15 +111,33 @@ pub async fn run_daemon(port: u16, mut screenpipe: ScreenpipeManager) -> Result<
                     log::error!("Failed to create tracker: {}", err);
                 }
             }
-        });
-    }
+        })
+    };
 
-    let state = Arc::new(DaemonState { issue_override });
+    // Scheduled backups only run when a backup directory is configured
+    let backup_handle = match config.analytics.backup_dir {
+        Some(_) => Some(tokio::spawn(backup::run_scheduled(
+            database.clone(),
+            crate::paths::backup_dir(&config)?,
+            Duration::from_secs(config.analytics.backup_interval_hours * 3600),
+            config.analytics.backup_keep,
+        ))),
+        None => None,
+    };
 
-    let app = Router::new()
-        .route("/status", get(status_handler))
-        .route("/issue", post(issue_override_handler))
-        .with_state(state);
+    let state = Arc::new(DaemonState {
+        issue_override,
+        jira,
+        database,
+        notification_prefs,
+        tracking_settings,
+        events,
+        tracker_status,
+        heartbeat,
+        metrics,
+    });
+
+    let app = router(state);
 
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
     log::info!("WorkToJiraEffort daemon listening on http://{}", addr);
@@ -71,6 +150,11 @@ pub async fn run_daemon(port: u16, mut screenpipe: ScreenpipeManager) -> Result<
         .await
         .context("Daemon HTTP server error")?;
 
+    stop_tracker(tracker_shutdown, tracker_handle, TRACKER_SHUTDOWN_TIMEOUT).await;
+    if let Some(handle) = backup_handle {
+        handle.abort();
+    }
+
     // Stop Screenpipe server when daemon shuts down
     log::info!("Daemon shutting down, stopping Screenpipe...");
     screenpipe.stop().await?;
@@ -78,49 +162,604 @@ pub async fn run_daemon(port: u16, mut screenpipe: ScreenpipeManager) -> Result<
     Ok(())
 }
 
+/// Routes of the daemon's HTTP API
+fn router(state: Arc<DaemonState>) -> Router {
+    Router::new()
+        .route("/status", get(status_handler))
+        .route("/health", get(health_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/events", get(events_handler))
+        .route("/issue", post(issue_override_handler))
+        .route("/issues/search", get(issue_search_handler))
+        .route("/flags", get(flags_handler))
+        .route("/analysis", get(analysis_handler))
+        .route("/analytics", get(analytics_handler))
+        .route("/summary/today", get(today_summary_handler))
+        .route("/activities/manual", post(manual_activity_handler))
+        .route(
+            "/activities/:id",
+            patch(update_activity_handler).delete(delete_activity_handler),
+        )
+        .route("/sessions/:id", get(session_handler))
+        .route("/sessions/:id/tags", post(session_tags_handler))
+        .route("/sessions/:id/timeline", get(timeline_handler))
+        .route("/sessions/:id/export", get(export_handler))
+        .route("/config", get(config_handler).patch(update_config_handler))
+        .route(
+            "/notifications",
+            get(notification_prefs_handler).post(set_notification_prefs_handler),
+        )
+        .with_state(state)
+}
+
 #[derive(Clone)]
 struct DaemonState {
     issue_override: Arc<RwLock<Option<String>>>,
+    jira: Option<Arc<JiraClient>>,
+    database: Database,
+    notification_prefs: Arc<RwLock<NotificationsConfig>>,
+    tracking_settings: Arc<RwLock<TrackingConfig>>,
+    events: broadcast::Sender<TrackerEvent>,
+    tracker_status: Arc<RwLock<TrackerStatus>>,
+    /// Written by the tracker loop on every iteration
+    heartbeat: PathBuf,
+    /// Counted by the tracker loop
+    metrics: Arc<Metrics>,
 }
 
 #[derive(Serialize)]
 struct StatusResponse {
     version: &'static str,
     issue_override: Option<String>,
+    /// `stopped`, `tracking` or `paused`
+    current_state: &'static str,
+    /// When the tracker last fetched from Screenpipe
+    last_sync: Option<DateTime<Utc>>,
+    /// End of the latest capture; falling behind `last_sync` means nothing is being recorded
+    last_activity_at: Option<DateTime<Utc>>,
 }
 
 async fn status_handler(State(state): State<Arc<DaemonState>>) -> Json<StatusResponse> {
     let issue_override = state.issue_override.read().await.clone();
+    let tracker = *state.tracker_status.read().await;
     Json(StatusResponse {
         version: VERSION,
         issue_override,
+        current_state: tracker.state.as_str(),
+        last_sync: tracker.last_sync,
+        last_activity_at: tracker.last_activity_at,
     })
 }
 
+#[derive(Serialize)]
+struct HealthResponse {
+    healthy: bool,
+    last_heartbeat: Option<DateTime<Utc>>,
+    max_age_secs: i64,
+}
+
+/// 200 while the tracker loop keeps beating, 503 once its heartbeat is older than two poll
+/// intervals (or it never wrote one), e.g. because a request hung
+async fn health_handler(
+    State(state): State<Arc<DaemonState>>,
+) -> Result<(StatusCode, Json<HealthResponse>), (StatusCode, String)> {
+    let poll_interval_secs = state
+        .tracking_settings
+        .read()
+        .await
+        .screenpipe_poll_interval_secs;
+    let last_heartbeat = heartbeat::read(&state.heartbeat)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+
+    let healthy = last_heartbeat
+        .is_some_and(|last| !heartbeat::is_stale(last, Utc::now(), poll_interval_secs));
+    let code = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok((
+        code,
+        Json(HealthResponse {
+            healthy,
+            last_heartbeat,
+            max_age_secs: heartbeat::max_age(poll_interval_secs).num_seconds(),
+        }),
+    ))
+}
+
+/// The tracker's counters and today's tracked time for Prometheus to scrape
+async fn metrics_handler(
+    State(state): State<Arc<DaemonState>>,
+) -> Result<([(header::HeaderName, &'static str); 1], String), (StatusCode, String)> {
+    let today = state
+        .database
+        .today_totals()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    Ok((
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        state.metrics.render(today.tracked_secs),
+    ))
+}
+
 #[derive(Deserialize)]
 struct IssueRequest {
     issue_key: Option<String>,
 }
 
+/// Set the override, or clear it with a missing or blank key; 400 for anything that isn't
+/// shaped like an issue key
 async fn issue_override_handler(
     State(state): State<Arc<DaemonState>>,
     Json(payload): Json<IssueRequest>,
-) -> Json<StatusResponse> {
-    let cleaned = payload.issue_key.and_then(|value| {
-        let trimmed = value.trim().to_uppercase();
-        if trimmed.is_empty() {
-            None
-        } else {
-            Some(trimmed)
-        }
-    });
+) -> Result<Json<StatusResponse>, (StatusCode, String)> {
+    let cleaned = match payload.issue_key.filter(|value| !value.trim().is_empty()) {
+        Some(value) => Some(
+            issue_key::normalize(&value).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+        ),
+        None => None,
+    };
 
     {
         let mut guard = state.issue_override.write().await;
         *guard = cleaned;
     }
 
-    status_handler(State(state)).await
+    Ok(status_handler(State(state)).await)
+}
+
+#[derive(Deserialize)]
+struct IssueSearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+/// Issues matching a typed key or summary prefix, for override typeahead
+async fn issue_search_handler(
+    State(state): State<Arc<DaemonState>>,
+    Query(query): Query<IssueSearchQuery>,
+) -> Result<Json<Vec<AssignedIssue>>, (StatusCode, String)> {
+    let jira = state.jira.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Jira integration is disabled".to_string(),
+        )
+    })?;
+
+    jira.search_issues(&query.q)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("{:#}", e)))
+}
+
+#[derive(Deserialize)]
+struct SessionQuery {
+    session_id: i64,
+}
+
+/// Unmatched time and red flags raised by analyses of a session
+async fn flags_handler(
+    State(state): State<Arc<DaemonState>>,
+    Query(query): Query<SessionQuery>,
+) -> Result<Json<Vec<AnalysisFlag>>, (StatusCode, String)> {
+    state
+        .database
+        .get_flags(query.session_id)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+/// LLM analyses of a session with the requests that produced them
+async fn analysis_handler(
+    State(state): State<Arc<DaemonState>>,
+    Query(query): Query<SessionQuery>,
+) -> Result<Json<Vec<AnalysisResult>>, (StatusCode, String)> {
+    state
+        .database
+        .get_analyses(query.session_id)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+#[derive(Deserialize)]
+struct AnalyticsQuery {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    granularity: Granularity,
+}
+
+/// Tracked time rolled up per day, week or month
+async fn analytics_handler(
+    State(state): State<Arc<DaemonState>>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<Json<Vec<PeriodStats>>, (StatusCode, String)> {
+    state
+        .database
+        .aggregate_by_period(query.from, query.to, query.granularity)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+/// Tracked and break time since local midnight, and the current issue
+async fn today_summary_handler(
+    State(state): State<Arc<DaemonState>>,
+) -> Result<Json<DayTotals>, (StatusCode, String)> {
+    state
+        .database
+        .today_totals()
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+/// Fail with 404 unless the session exists
+fn require_session(state: &DaemonState, session_id: i64) -> Result<(), (StatusCode, String)> {
+    match state.database.session_exists(session_id) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            format!("Session {} not found", session_id),
+        )),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))),
+    }
+}
+
+/// Totals and tags of a session
+async fn session_handler(
+    State(state): State<Arc<DaemonState>>,
+    Path(session_id): Path<i64>,
+) -> Result<Json<SessionStats>, (StatusCode, String)> {
+    require_session(&state, session_id)?;
+
+    state
+        .database
+        .get_session_stats(session_id)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+/// The session's activities merged into contiguous segments, with its breaks, for a
+/// Gantt-style view
+async fn timeline_handler(
+    State(state): State<Arc<DaemonState>>,
+    Path(session_id): Path<i64>,
+) -> Result<Json<Vec<TimelineSegment>>, (StatusCode, String)> {
+    require_session(&state, session_id)?;
+
+    state
+        .database
+        .get_session_timeline(session_id)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+#[derive(Deserialize)]
+struct SessionTagsRequest {
+    tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SessionTagsResponse {
+    session_id: i64,
+    tags: Vec<String>,
+}
+
+/// Replace the labels of a session, e.g. "client onsite"
+async fn session_tags_handler(
+    State(state): State<Arc<DaemonState>>,
+    Path(session_id): Path<i64>,
+    Json(payload): Json<SessionTagsRequest>,
+) -> Result<Json<SessionTagsResponse>, (StatusCode, String)> {
+    require_session(&state, session_id)?;
+    let tags = database::normalize_tags(&payload.tags)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+
+    state
+        .database
+        .set_session_tags(session_id, &tags)
+        .map(|tags| Json(SessionTagsResponse { session_id, tags }))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+#[derive(Deserialize)]
+struct ManualActivityRequest {
+    /// Defaults to the active session, or a new one covering the entry. Entries outside the
+    /// active session are logged by the tracker's next scheduled analysis.
+    session_id: Option<i64>,
+    /// Log to this issue as-is; without it the entry is matched like captured activity
+    issue_key: Option<String>,
+    start: DateTime<Utc>,
+    duration_secs: Option<u64>,
+    /// Alternative to `duration_secs` as typed by a person, e.g. "1h 30m"
+    duration: Option<String>,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Serialize)]
+struct ManualActivityResponse {
+    id: i64,
+    session_id: i64,
+}
+
+/// Record off-screen work (meetings, calls) that Screenpipe could not capture
+async fn manual_activity_handler(
+    State(state): State<Arc<DaemonState>>,
+    Json(payload): Json<ManualActivityRequest>,
+) -> Result<Json<ManualActivityResponse>, (StatusCode, String)> {
+    let internal = |e: anyhow::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e));
+
+    let duration_secs = match (payload.duration_secs, &payload.duration) {
+        (Some(secs), _) => secs,
+        (None, Some(duration)) => {
+            format::parse_hm(duration).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        }
+        (None, None) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "duration_secs or duration is required".to_string(),
+            ))
+        }
+    };
+    if duration_secs == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "duration_secs must be greater than 0".to_string(),
+        ));
+    }
+    let issue_key = match payload.issue_key.filter(|key| !key.trim().is_empty()) {
+        Some(key) => {
+            Some(issue_key::normalize(&key).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?)
+        }
+        None => None,
+    };
+    let description = payload.description.trim();
+    if issue_key.is_none() && description.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Either issue_key or description is required".to_string(),
+        ));
+    }
+
+    let session_id = match payload.session_id {
+        Some(session_id) => {
+            require_session(&state, session_id)?;
+            session_id
+        }
+        None => match state.database.get_active_session().map_err(internal)? {
+            Some(session) => session.id,
+            None => {
+                let end = payload.start + chrono::Duration::seconds(duration_secs as i64);
+                state
+                    .database
+                    .create_closed_session(payload.start, end)
+                    .map_err(internal)?
+            }
+        },
+    };
+
+    let id = state
+        .database
+        .add_manual_activity(
+            session_id,
+            issue_key.as_deref(),
+            payload.start,
+            duration_secs,
+            description,
+        )
+        .map_err(internal)?;
+
+    Ok(Json(ManualActivityResponse { id, session_id }))
+}
+
+/// Fail with 404 unless the activity exists, or 409 if it's already logged to Jira
+fn require_unlogged_activity(
+    state: &DaemonState,
+    activity_id: i64,
+) -> Result<(), (StatusCode, String)> {
+    match state.database.get_activity(activity_id) {
+        Ok(Some(activity)) if activity.logged_to_jira => Err((
+            StatusCode::CONFLICT,
+            format!("Activity {} is already logged to Jira", activity_id),
+        )),
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            format!("Activity {} not found", activity_id),
+        )),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))),
+    }
+}
+
+/// Correct an activity's duration, title, description or issue before it is logged
+async fn update_activity_handler(
+    State(state): State<Arc<DaemonState>>,
+    Path(activity_id): Path<i64>,
+    Json(mut payload): Json<ActivityUpdate>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if payload.duration_secs == Some(0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "duration_secs must be greater than 0".to_string(),
+        ));
+    }
+    // A blank key stays empty and clears the assignment
+    if let Some(key) = payload
+        .issue_key
+        .as_mut()
+        .filter(|key| !key.trim().is_empty())
+    {
+        *key = issue_key::normalize(key).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    }
+    require_unlogged_activity(&state, activity_id)?;
+
+    state
+        .database
+        .update_activity(activity_id, &payload)
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+/// Remove an activity that shouldn't be logged at all
+async fn delete_activity_handler(
+    State(state): State<Arc<DaemonState>>,
+    Path(activity_id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_unlogged_activity(&state, activity_id)?;
+
+    state
+        .database
+        .delete_activity(activity_id)
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default = "default_export_format")]
+    format: FileFormat,
+}
+
+fn default_export_format() -> FileFormat {
+    FileFormat::Csv
+}
+
+/// A session's activities as CSV or JSON, in the same layout as `work-to-jira-effort export`
+async fn export_handler(
+    State(state): State<Arc<DaemonState>>,
+    Path(session_id): Path<i64>,
+    Query(query): Query<ExportQuery>,
+) -> Result<([(header::HeaderName, &'static str); 1], String), (StatusCode, String)> {
+    require_session(&state, session_id)?;
+
+    export::export_session(&state.database, session_id, query.format)
+        .map(|body| {
+            (
+                [(header::CONTENT_TYPE, export::content_type(query.format))],
+                body,
+            )
+        })
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+async fn notification_prefs_handler(
+    State(state): State<Arc<DaemonState>>,
+) -> Json<NotificationsConfig> {
+    Json(state.notification_prefs.read().await.clone())
+}
+
+#[derive(Deserialize)]
+struct NotificationPrefsRequest {
+    enabled: bool,
+    frequency: String,
+}
+
+/// Update notification preferences for the running tracker and persist them to the config file
+async fn set_notification_prefs_handler(
+    State(state): State<Arc<DaemonState>>,
+    Json(payload): Json<NotificationPrefsRequest>,
+) -> Result<Json<NotificationsConfig>, (StatusCode, String)> {
+    let frequency: NotificationFrequency = payload
+        .frequency
+        .parse()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let prefs = NotificationsConfig {
+        enabled: payload.enabled,
+        frequency,
+    };
+
+    Config::load_strict()
+        .and_then(|mut config| {
+            config.notifications = prefs.clone();
+            config.save()
+        })
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+
+    *state.notification_prefs.write().await = prefs.clone();
+    Ok(Json(prefs))
+}
+
+/// Server-sent stream of tracker events, so clients don't have to poll `/status`
+async fn events_handler(
+    State(state): State<Arc<DaemonState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.events.subscribe();
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse = Event::default()
+                        .event(event.name())
+                        .json_data(&event)
+                        .unwrap_or_else(|_| Event::default().event(event.name()));
+                    return Some((Ok(sse), receiver));
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::debug!("Event subscriber lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Current config file contents, with secrets masked
+async fn config_handler() -> Result<Json<Config>, (StatusCode, String)> {
+    let config = Config::load_strict()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    Ok(Json(config.redacted()))
+}
+
+/// Apply a partial config update, persist it and hand tracking and notification
+/// settings to the running tracker. Other changes apply after a restart.
+async fn update_config_handler(
+    State(state): State<Arc<DaemonState>>,
+    Json(patch): Json<serde_json::Value>,
+) -> Result<Json<Config>, (StatusCode, String)> {
+    let config = Config::load_strict()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    let updated = config
+        .merge_patch(&patch)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    updated
+        .save()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+
+    *state.tracking_settings.write().await = updated.tracking.clone();
+    *state.notification_prefs.write().await = updated.notifications.clone();
+    Ok(Json(updated.redacted()))
+}
+
+/// Signal the tracker loop to stop and wait for it to finish.
+/// The tracker only checks the signal between polls, so an in-flight sync or
+/// worklog completes first; after `timeout` the task is aborted instead.
+/// Returns whether the tracker stopped on its own.
+async fn stop_tracker(
+    shutdown: watch::Sender<bool>,
+    mut handle: JoinHandle<()>,
+    timeout: Duration,
+) -> bool {
+    let _ = shutdown.send(true);
+
+    match tokio::time::timeout(timeout, &mut handle).await {
+        Ok(Ok(())) => {
+            log::info!("Tracker stopped");
+            true
+        }
+        Ok(Err(err)) => {
+            log::error!("Tracker task failed: {}", err);
+            false
+        }
+        Err(_) => {
+            log::warn!(
+                "Tracker did not stop within {}s, abandoning it",
+                timeout.as_secs()
+            );
+            handle.abort();
+            false
+        }
+    }
 }
 
 async fn shutdown_signal() {
@@ -128,3 +767,293 @@ async fn shutdown_signal() {
         log::warn!("Failed to listen for shutdown signal: {}", err);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TrackingState;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// State for a daemon without Jira or a tracker loop; override fields as needed
+    fn test_state(database: Database) -> DaemonState {
+        DaemonState {
+            issue_override: Arc::new(RwLock::new(None)),
+            jira: None,
+            database,
+            notification_prefs: Arc::new(RwLock::new(NotificationsConfig::default())),
+            tracking_settings: Arc::new(RwLock::new(Config::default().tracking)),
+            events: events::channel(),
+            tracker_status: Arc::new(RwLock::new(TrackerStatus::default())),
+            // No tracker loop writes one
+            heartbeat: PathBuf::new(),
+            metrics: Arc::new(Metrics::default()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_stream_delivers_tracker_events() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let events = events::channel();
+        let state = Arc::new(DaemonState {
+            events: events.clone(),
+            ..test_state(Database::new(temp_file.path().to_path_buf()).unwrap())
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+
+        let mut response = reqwest::get(format!("http://{}/events", addr))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        // The subscription exists once the response headers arrive
+        events
+            .send(TrackerEvent::ActivitiesStored {
+                session_id: 3,
+                count: 2,
+            })
+            .unwrap();
+
+        let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let body = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(body.contains("event: activities_stored"), "{}", body);
+        assert!(
+            body.contains(r#"data: {"type":"activities_stored","session_id":3,"count":2}"#),
+            "{}",
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let metrics = Arc::new(Metrics::default());
+        let state = Arc::new(DaemonState {
+            metrics: Arc::clone(&metrics),
+            ..test_state(Database::new(temp_file.path().to_path_buf()).unwrap())
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        metrics.record_worklog_posted();
+
+        let response = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            metrics::CONTENT_TYPE
+        );
+        let body = response.text().await.unwrap();
+        assert!(
+            body.contains("\nwtje_worklogs_posted_total 1\n"),
+            "{}",
+            body
+        );
+        assert!(
+            body.contains("\nwtje_tracked_seconds_today 0\n"),
+            "{}",
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn test_issue_override_is_normalized() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let issue_override = Arc::new(RwLock::new(None));
+        let state = Arc::new(DaemonState {
+            issue_override: Arc::clone(&issue_override),
+            ..test_state(Database::new(temp_file.path().to_path_buf()).unwrap())
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/issue", addr);
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({ "issue_key": "proj-42 " }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(issue_override.read().await.as_deref(), Some("PROJ-42"));
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({ "issue_key": "not an issue" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert_eq!(issue_override.read().await.as_deref(), Some("PROJ-42"));
+    }
+
+    #[tokio::test]
+    async fn test_manual_activity_rejects_malformed_issue_key() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let state = Arc::new(test_state(database.clone()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/activities/manual", addr);
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({
+                "issue_key": "not an issue",
+                "start": "2024-05-01T09:00:00Z",
+                "duration": "30m"
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("'not an issue' is not a Jira issue key"));
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({
+                "issue_key": " proj-42",
+                "start": "2024-05-01T09:00:00Z",
+                "duration": "30m"
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let created: serde_json::Value = response.json().await.unwrap();
+        let activity = database
+            .get_activity(created["id"].as_i64().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(activity.issue_key.as_deref(), Some("PROJ-42"));
+    }
+
+    #[tokio::test]
+    async fn test_activity_update_rejects_malformed_issue_key() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let session_id = database.create_session().unwrap();
+        let activity_id = database
+            .add_manual_activity(session_id, Some("PROJ-1"), Utc::now(), 900, "Design review")
+            .unwrap();
+        let state = Arc::new(test_state(database.clone()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/activities/{}", addr, activity_id);
+        let issue_key = || {
+            database
+                .get_activity(activity_id)
+                .unwrap()
+                .unwrap()
+                .issue_key
+        };
+
+        let response = client
+            .patch(&url)
+            .json(&serde_json::json!({ "issue_key": "not an issue" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert_eq!(issue_key().as_deref(), Some("PROJ-1"));
+
+        let response = client
+            .patch(&url)
+            .json(&serde_json::json!({ "issue_key": "proj-7 " }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        assert_eq!(issue_key().as_deref(), Some("PROJ-7"));
+
+        let response = client
+            .patch(&url)
+            .json(&serde_json::json!({ "issue_key": " " }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        assert_eq!(issue_key(), None);
+    }
+
+    #[test]
+    fn test_status_reports_tracker_activity() {
+        let last_sync = "2024-05-01T10:05:00Z".parse::<DateTime<Utc>>().unwrap();
+        let status = StatusResponse {
+            version: VERSION,
+            issue_override: Some("PROJ-42".to_string()),
+            current_state: TrackingState::Tracking.as_str(),
+            last_sync: Some(last_sync),
+            last_activity_at: Some(last_sync - chrono::Duration::minutes(2)),
+        };
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["version"], VERSION);
+        assert_eq!(json["issue_override"], "PROJ-42");
+        assert_eq!(json["current_state"], "tracking");
+        assert_eq!(json["last_sync"], "2024-05-01T10:05:00Z");
+        assert_eq!(json["last_activity_at"], "2024-05-01T10:03:00Z");
+
+        let idle = serde_json::to_value(StatusResponse {
+            version: VERSION,
+            issue_override: None,
+            current_state: TrackingState::Stopped.as_str(),
+            last_sync: None,
+            last_activity_at: None,
+        })
+        .unwrap();
+        assert!(idle["last_activity_at"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_stop_tracker_waits_for_loop_to_finish() {
+        let (shutdown, mut shutdown_rx) = watch::channel(false);
+        let finished = Arc::new(AtomicBool::new(false));
+        let task_finished = Arc::clone(&finished);
+
+        let handle = tokio::spawn(async move {
+            let _ = shutdown_rx.changed().await;
+            // Simulates the final flush running after the signal
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            task_finished.store(true, Ordering::SeqCst);
+        });
+
+        assert!(stop_tracker(shutdown, handle, Duration::from_secs(5)).await);
+        assert!(finished.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_stop_tracker_aborts_after_timeout() {
+        let (shutdown, _shutdown_rx) = watch::channel(false);
+        let handle = tokio::spawn(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        assert!(!stop_tracker(shutdown, handle, Duration::from_millis(50)).await);
+    }
+}
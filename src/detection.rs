@@ -0,0 +1,346 @@
+use crate::{
+    config::{Config, DetectionStage},
+    database::{AttributionSource, StoredActivity},
+    jira::{build_issue_key_regex, detect_issue_key},
+    llm::{AssignedIssue, LLMAnalyzer},
+};
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// An issue key found for an activity and the stage that found it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub issue_key: String,
+    pub source: AttributionSource,
+}
+
+/// One way of finding the Jira issue an activity belongs to
+#[async_trait]
+pub trait IssueDetector: Send + Sync {
+    /// Short name used in logs
+    fn name(&self) -> &'static str;
+
+    async fn detect(
+        &self,
+        activity: &StoredActivity,
+        assigned: &[AssignedIssue],
+    ) -> Result<Option<Detection>>;
+}
+
+/// Finds issue keys in the window title and app name
+pub struct RegexDetector {
+    regex: regex::Regex,
+    use_denylist: bool,
+}
+
+impl RegexDetector {
+    pub fn new(project_keys: &[String]) -> Self {
+        Self {
+            regex: build_issue_key_regex(project_keys),
+            use_denylist: project_keys.is_empty(),
+        }
+    }
+}
+
+#[async_trait]
+impl IssueDetector for RegexDetector {
+    fn name(&self) -> &'static str {
+        "regex"
+    }
+
+    async fn detect(
+        &self,
+        activity: &StoredActivity,
+        _: &[AssignedIssue],
+    ) -> Result<Option<Detection>> {
+        let text = format!("{} {}", activity.window_title, activity.app_name);
+        Ok(
+            detect_issue_key(&self.regex, &text, self.use_denylist).map(|issue_key| Detection {
+                issue_key,
+                source: AttributionSource::Regex,
+            }),
+        )
+    }
+}
+
+/// Finds issue keys in the browser URL, e.g. `/browse/PROJ-1` or a `PROJ-1-fix` branch
+pub struct UrlDetector {
+    regex: regex::Regex,
+    use_denylist: bool,
+}
+
+impl UrlDetector {
+    pub fn new(project_keys: &[String]) -> Self {
+        Self {
+            regex: build_issue_key_regex(project_keys),
+            use_denylist: project_keys.is_empty(),
+        }
+    }
+}
+
+#[async_trait]
+impl IssueDetector for UrlDetector {
+    fn name(&self) -> &'static str {
+        "url"
+    }
+
+    async fn detect(
+        &self,
+        activity: &StoredActivity,
+        _: &[AssignedIssue],
+    ) -> Result<Option<Detection>> {
+        let Some(url) = activity.browser_url.as_deref() else {
+            return Ok(None);
+        };
+        Ok(
+            detect_issue_key(&self.regex, url, self.use_denylist).map(|issue_key| Detection {
+                issue_key,
+                source: AttributionSource::Regex,
+            }),
+        )
+    }
+}
+
+/// Asks the LLM which assigned issue a single activity belongs to
+pub struct LlmDetector {
+    llm: LLMAnalyzer,
+}
+
+impl LlmDetector {
+    pub fn new(llm: LLMAnalyzer) -> Self {
+        Self { llm }
+    }
+}
+
+#[async_trait]
+impl IssueDetector for LlmDetector {
+    fn name(&self) -> &'static str {
+        "llm"
+    }
+
+    async fn detect(
+        &self,
+        activity: &StoredActivity,
+        assigned: &[AssignedIssue],
+    ) -> Result<Option<Detection>> {
+        if assigned.is_empty() {
+            return Ok(None);
+        }
+        Ok(self
+            .llm
+            .suggest_issue(activity, assigned)
+            .await?
+            .map(|suggestion| Detection {
+                issue_key: suggestion.key,
+                source: AttributionSource::Llm,
+            }))
+    }
+}
+
+/// Tries detectors in order and returns the first key that is assigned to the user.
+/// A stage that errors or finds an unassigned key hands over to the next one.
+pub struct ChainDetector {
+    stages: Vec<Box<dyn IssueDetector>>,
+}
+
+impl ChainDetector {
+    pub fn new(stages: Vec<Box<dyn IssueDetector>>) -> Self {
+        Self { stages }
+    }
+
+    /// Build the stages listed in `detection.stages`; the LLM stage uses the `[llm]` endpoint
+    pub fn from_config(config: &Config, http: reqwest::Client) -> Result<Self> {
+        let project_keys = &config.jira.project_keys;
+        let mut stages: Vec<Box<dyn IssueDetector>> = Vec::new();
+
+        for stage in &config.detection.stages {
+            match stage {
+                DetectionStage::Regex => stages.push(Box::new(RegexDetector::new(project_keys))),
+                DetectionStage::Url => stages.push(Box::new(UrlDetector::new(project_keys))),
+                DetectionStage::Llm => {
+                    if config.llm.endpoint.trim().is_empty() {
+                        anyhow::bail!("The 'llm' detection stage needs llm.endpoint to be set");
+                    }
+                    let llm = LLMAnalyzer::new(
+                        config.llm.endpoint.clone(),
+                        config.llm.api_key.clone(),
+                        config.llm.timeout_secs,
+                    )?
+                    .with_client(http.clone())
+                    .with_redaction_patterns(&config.analytics.redaction_patterns)?;
+                    stages.push(Box::new(LlmDetector::new(llm)));
+                }
+            }
+        }
+
+        Ok(Self::new(stages))
+    }
+}
+
+#[async_trait]
+impl IssueDetector for ChainDetector {
+    fn name(&self) -> &'static str {
+        "chain"
+    }
+
+    async fn detect(
+        &self,
+        activity: &StoredActivity,
+        assigned: &[AssignedIssue],
+    ) -> Result<Option<Detection>> {
+        for stage in &self.stages {
+            match stage.detect(activity, assigned).await {
+                Ok(Some(detection)) if assigned.iter().any(|i| i.key == detection.issue_key) => {
+                    log::debug!("{} detected {}", stage.name(), detection.issue_key);
+                    return Ok(Some(detection));
+                }
+                Ok(Some(detection)) => {
+                    log::info!(
+                        "Skipping {} from {} detection - not assigned to you",
+                        detection.issue_key,
+                        stage.name()
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("{} issue detection failed: {:#}", stage.name(), e),
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{ActivitySource, ActivityTier};
+    use chrono::Utc;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Detector returning a fixed answer and counting its calls
+    struct Fixed {
+        answer: Option<&'static str>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl IssueDetector for Fixed {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        async fn detect(
+            &self,
+            _: &StoredActivity,
+            _: &[AssignedIssue],
+        ) -> Result<Option<Detection>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.answer.map(|key| Detection {
+                issue_key: key.to_string(),
+                source: AttributionSource::Llm,
+            }))
+        }
+    }
+
+    fn fixed(answer: Option<&'static str>) -> (Box<dyn IssueDetector>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        (
+            Box::new(Fixed {
+                answer,
+                calls: calls.clone(),
+            }),
+            calls,
+        )
+    }
+
+    fn stored(window_title: &str, browser_url: Option<&str>) -> StoredActivity {
+        StoredActivity {
+            id: 1,
+            session_id: 1,
+            timestamp: Utc::now(),
+            duration_secs: 900,
+            window_title: window_title.to_string(),
+            app_name: "Firefox".to_string(),
+            description: String::new(),
+            tier: ActivityTier::Billable,
+            logged_to_jira: false,
+            browser_url: browser_url.map(str::to_string),
+            source: ActivitySource::Auto,
+            issue_key: None,
+            attributed_issue: None,
+            attribution_source: None,
+            billable: true,
+            category: None,
+        }
+    }
+
+    fn assigned(keys: &[&str]) -> Vec<AssignedIssue> {
+        keys.iter()
+            .map(|key| AssignedIssue {
+                key: key.to_string(),
+                summary: String::new(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_chain_short_circuits_on_assigned_match() {
+        let (first, first_calls) = fixed(None);
+        let (second, second_calls) = fixed(Some("PROJ-1"));
+        let (third, third_calls) = fixed(Some("PROJ-2"));
+        let chain = ChainDetector::new(vec![first, second, third]);
+
+        let detection = chain
+            .detect(&stored("", None), &assigned(&["PROJ-1", "PROJ-2"]))
+            .await
+            .unwrap();
+        assert_eq!(detection.unwrap().issue_key, "PROJ-1");
+        assert_eq!(first_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(second_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(third_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_chain_skips_unassigned_keys() {
+        let (unassigned, _) = fixed(Some("OTHER-9"));
+        let (fallback, _) = fixed(Some("PROJ-2"));
+        let chain = ChainDetector::new(vec![unassigned, fallback]);
+
+        let detection = chain
+            .detect(&stored("", None), &assigned(&["PROJ-2"]))
+            .await
+            .unwrap();
+        assert_eq!(detection.unwrap().issue_key, "PROJ-2");
+
+        let (only_unassigned, _) = fixed(Some("OTHER-9"));
+        let chain = ChainDetector::new(vec![only_unassigned]);
+        assert!(chain
+            .detect(&stored("", None), &assigned(&["PROJ-2"]))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_url_stage_finds_key_missing_from_title() {
+        let chain = ChainDetector::new(vec![
+            Box::new(RegexDetector::new(&[])),
+            Box::new(UrlDetector::new(&[])),
+        ]);
+        let activity = stored(
+            "Sprint board - Jira",
+            Some("https://example.atlassian.net/browse/PROJ-7"),
+        );
+
+        let detection = chain
+            .detect(&activity, &assigned(&["PROJ-7"]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(detection.issue_key, "PROJ-7");
+        assert_eq!(detection.source, AttributionSource::Regex);
+    }
+}
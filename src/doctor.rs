@@ -0,0 +1,543 @@
+use crate::{
+    config::Config,
+    database::Database,
+    jira::JiraClient,
+    llm::LLMAnalyzer,
+    salesforce::SalesforceClient,
+    screenpipe::ScreenpipeClient,
+    screenpipe_manager::{find_installed_binary, SCREENPIPE_INSTALL_URL},
+};
+use std::path::Path;
+
+/// Outcome of a single diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    /// Worth fixing, but tracking can still work
+    Warn,
+    /// Nothing (or nothing useful) gets logged until this is fixed
+    Fail,
+    /// Not applicable, e.g. a disabled integration or a check that needs a valid config
+    Skip,
+}
+
+impl Status {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Status::Pass => "✓",
+            Status::Warn => "!",
+            Status::Fail => "✗",
+            Status::Skip => "-",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Skip,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+}
+
+/// Results of `run`, in the order the checks ran
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|c| c.status == Status::Fail)
+            .count()
+    }
+
+    /// Print the checklist with remediation hints under failed and warned checks
+    pub fn print(&self) {
+        for check in &self.checks {
+            println!(
+                "{} {:<22} {}",
+                check.status.symbol(),
+                check.name,
+                check.detail
+            );
+            if let Some(hint) = &check.hint {
+                println!("  → {}", hint);
+            }
+        }
+    }
+}
+
+/// Diagnose the config, Screenpipe, keychain, integrations and database without changing
+/// anything (the config file and database are not created or migrated)
+pub async fn run() -> Report {
+    let mut report = Report::default();
+
+    let config = match Config::config_path() {
+        Ok(path) => {
+            let (check, config) = check_config_file(&path);
+            report.checks.push(check);
+            #[cfg(unix)]
+            if config.is_some() {
+                report.checks.push(check_config_permissions(&path));
+            }
+            config
+        }
+        Err(e) => {
+            report.checks.push(Check::fail(
+                "Config file",
+                format!("{:#}", e),
+                "Make sure the HOME directory is set",
+            ));
+            None
+        }
+    };
+
+    report.checks.push(check_screenpipe_binary());
+    report.checks.push(check_keychain());
+
+    let Some(config) = config else {
+        for name in [
+            "Credentials",
+            "Screenpipe server",
+            "Jira",
+            "Salesforce",
+            "LLM",
+            "Database",
+        ] {
+            report
+                .checks
+                .push(Check::skip(name, "needs a valid config"));
+        }
+        return report;
+    };
+
+    report.checks.push(check_credentials(&config));
+    let http = match crate::http::build_client(&config.network) {
+        Ok(http) => http,
+        Err(e) => {
+            report.checks.push(Check::fail(
+                "Network",
+                format!("{:#}", e),
+                "Check the [network] section",
+            ));
+            return report;
+        }
+    };
+    report
+        .checks
+        .push(check_screenpipe_server(&config, &http).await);
+    report.checks.push(check_jira(&config, &http).await);
+    report.checks.push(check_salesforce(&config, &http).await);
+    report.checks.push(check_llm(&config, &http).await);
+    report.checks.push(check_database(&config));
+
+    report
+}
+
+fn check_config_file(path: &Path) -> (Check, Option<Config>) {
+    const NAME: &str = "Config file";
+
+    if !path.exists() {
+        let check = Check::fail(
+            NAME,
+            format!("{} not found", path.display()),
+            "Run `work-to-jira-effort init` and fill in your credentials",
+        );
+        return (check, None);
+    }
+
+    let config = std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| toml::from_str::<Config>(&content).map_err(anyhow::Error::from))
+        .and_then(|config| config.validate().map(|_| config));
+
+    match config {
+        Ok(config) => (Check::pass(NAME, path.display().to_string()), Some(config)),
+        Err(e) => (
+            Check::fail(
+                NAME,
+                format!("{:#}", e),
+                format!("Fix {} (see config.example.toml)", path.display()),
+            ),
+            None,
+        ),
+    }
+}
+
+/// Credentials live in the config file, so it should only be readable by its owner
+#[cfg(unix)]
+fn check_config_permissions(path: &Path) -> Check {
+    use std::os::unix::fs::PermissionsExt;
+    const NAME: &str = "Config permissions";
+
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().mode() & 0o077 != 0 => Check::warn(
+            NAME,
+            format!(
+                "readable by others ({:o})",
+                metadata.permissions().mode() & 0o777
+            ),
+            format!("Run `chmod 600 {}`", path.display()),
+        ),
+        Ok(_) => Check::pass(NAME, "only readable by you"),
+        Err(e) => Check::warn(
+            NAME,
+            e.to_string(),
+            "Check that the config file is accessible",
+        ),
+    }
+}
+
+/// Enabled integrations still using the placeholder values written by `init`
+fn check_credentials(config: &Config) -> Check {
+    const NAME: &str = "Credentials";
+    let placeholders = config.placeholder_fields();
+
+    if placeholders.is_empty() {
+        Check::pass(NAME, "no placeholder values")
+    } else {
+        Check::fail(
+            NAME,
+            format!("still set to the defaults: {}", placeholders.join(", ")),
+            "Replace them with real values, or disable the integration",
+        )
+    }
+}
+
+fn check_screenpipe_binary() -> Check {
+    const NAME: &str = "Screenpipe binary";
+    match find_installed_binary() {
+        Some(path) => Check::pass(NAME, path.display().to_string()),
+        None => Check::fail(
+            NAME,
+            "not found",
+            format!(
+                "Install it from {}, or run `work-to-jira-effort start --yes` to install it automatically",
+                SCREENPIPE_INSTALL_URL
+            ),
+        ),
+    }
+}
+
+/// Not critical: `start` and `daemon` launch the server themselves
+async fn check_screenpipe_server(config: &Config, http: &reqwest::Client) -> Check {
+    const NAME: &str = "Screenpipe server";
+    let url = config.screenpipe.url();
+    let screenpipe = ScreenpipeClient::new(url.clone()).with_client(http.clone());
+    match screenpipe.health_check().await {
+        Ok(true) => Check::pass(NAME, format!("reachable at {}", url)),
+        _ => Check::warn(
+            NAME,
+            format!("not running at {}", url),
+            "It is started by `start` and `daemon`; see `work-to-jira-effort logs` if it fails to come up",
+        ),
+    }
+}
+
+async fn check_jira(config: &Config, http: &reqwest::Client) -> Check {
+    const NAME: &str = "Jira";
+    if !config.jira.enabled {
+        return Check::skip(NAME, "disabled");
+    }
+
+    let jira = JiraClient::new(
+        config.jira.url.clone(),
+        config.jira.email.clone(),
+        config.jira.api_token.clone(),
+    )
+    .with_client(http.clone());
+    match jira.health_check().await {
+        Ok(true) => Check::pass(NAME, format!("authenticated as {}", config.jira.email)),
+        _ => Check::fail(
+            NAME,
+            format!("could not authenticate to {}", config.jira.url),
+            "Check jira.url, jira.email and jira.api_token (create a token at https://id.atlassian.com/manage-profile/security/api-tokens)",
+        ),
+    }
+}
+
+async fn check_salesforce(config: &Config, http: &reqwest::Client) -> Check {
+    const NAME: &str = "Salesforce";
+    if !config.salesforce.enabled {
+        return Check::skip(NAME, "disabled");
+    }
+
+    let mut salesforce = SalesforceClient::new(
+        config.salesforce.instance_url.clone(),
+        config.salesforce.username.clone(),
+        config.salesforce.password.clone(),
+        config.salesforce.security_token.clone(),
+        config.salesforce.client_id.clone(),
+        config.salesforce.client_secret.clone(),
+    )
+    .with_client(http.clone())
+    .with_login_url(config.salesforce.login_url_or_default());
+    match salesforce.health_check().await {
+        Ok(true) => Check::pass(
+            NAME,
+            format!("authenticated as {}", config.salesforce.username),
+        ),
+        _ => Check::fail(
+            NAME,
+            format!(
+                "could not authenticate to {}",
+                config.salesforce.instance_url
+            ),
+            "Check the [salesforce] credentials; the password may need the security token reset",
+        ),
+    }
+}
+
+async fn check_llm(config: &Config, http: &reqwest::Client) -> Check {
+    const NAME: &str = "LLM";
+    if !config.llm.enabled {
+        return Check::skip(NAME, "disabled");
+    }
+
+    let llm = match LLMAnalyzer::new(
+        config.llm.endpoint.clone(),
+        config.llm.api_key.clone(),
+        config.llm.timeout_secs,
+    ) {
+        Ok(llm) => llm.with_client(http.clone()),
+        Err(e) => return Check::fail(NAME, format!("{:#}", e), "Check the [llm] section"),
+    };
+    match llm.health_check().await {
+        Ok(true) => Check::pass(NAME, format!("reachable at {}", config.llm.endpoint)),
+        _ => Check::fail(
+            NAME,
+            format!(
+                "{} is unreachable or rejected the API key",
+                config.llm.endpoint
+            ),
+            "Check llm.endpoint and llm.api_key, or set llm.enabled = false to use regex matching",
+        ),
+    }
+}
+
+/// Inspects the file read-only: pending migrations are reported, not applied
+fn check_database(config: &Config) -> Check {
+    const NAME: &str = "Database";
+    let path = match crate::paths::database_path(config) {
+        Ok(path) => path,
+        Err(e) => return Check::fail(NAME, format!("{:#}", e), "Check analytics.database_path"),
+    };
+
+    if !path.exists() {
+        return Check::pass(
+            NAME,
+            format!("{} (created on the first start)", path.display()),
+        );
+    }
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.permissions().readonly()) {
+        return Check::fail(
+            NAME,
+            format!("{} is read-only", path.display()),
+            "Make the file writable, or point analytics.database_path to a writable location",
+        );
+    }
+
+    match Database::schema_version_of(&path) {
+        Ok(version) if version < Database::SCHEMA_VERSION => Check::pass(
+            NAME,
+            format!(
+                "{} ({} schema updates are applied on the next start)",
+                path.display(),
+                Database::SCHEMA_VERSION - version
+            ),
+        ),
+        Ok(version) if version > Database::SCHEMA_VERSION => Check::warn(
+            NAME,
+            format!(
+                "{} was written by a newer version (schema {})",
+                path.display(),
+                version
+            ),
+            "Update work-to-jira-effort, or restore a backup made by this version",
+        ),
+        Ok(_) => Check::pass(NAME, path.display().to_string()),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{:#}", e),
+            "Run `work-to-jira-effort maintenance`, or restore a backup",
+        ),
+    }
+}
+
+/// Whether the system keychain (Secret Service on Linux) can be reached. Credentials are
+/// read from the config file, so a problem here is only a warning.
+fn check_keychain() -> Check {
+    const NAME: &str = "Keychain";
+
+    if cfg!(target_os = "macos") {
+        return match std::process::Command::new("security")
+            .arg("default-keychain")
+            .output()
+        {
+            Ok(output) if output.status.success() => Check::pass(
+                NAME,
+                String::from_utf8_lossy(&output.stdout)
+                    .trim()
+                    .trim_matches('"')
+                    .to_string(),
+            ),
+            Ok(output) => Check::warn(
+                NAME,
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                "Unlock or reset the login keychain in Keychain Access",
+            ),
+            Err(e) => Check::warn(
+                NAME,
+                format!("could not run `security`: {}", e),
+                "Check that /usr/bin/security is available",
+            ),
+        };
+    }
+
+    if cfg!(target_os = "linux") {
+        return match which::which("secret-tool") {
+            Ok(path) => Check::pass(NAME, format!("Secret Service via {}", path.display())),
+            Err(_) => Check::skip(NAME, "no Secret Service client (secret-tool) installed"),
+        };
+    }
+
+    Check::skip(NAME, "credentials are read from the config file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_file_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let (check, config) = check_config_file(&path);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.hint.unwrap().contains("init"));
+        assert!(config.is_none());
+        assert!(!path.exists(), "doctor must not create the config");
+
+        let mut invalid = Config::default();
+        invalid.tracking.screenpipe_poll_interval_secs = 0;
+        std::fs::write(&path, toml::to_string(&invalid).unwrap()).unwrap();
+        let (check, config) = check_config_file(&path);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.detail.contains("screenpipe_poll_interval_secs"));
+        assert!(config.is_none());
+
+        std::fs::write(&path, toml::to_string(&Config::default()).unwrap()).unwrap();
+        let (check, config) = check_config_file(&path);
+        assert_eq!(check.status, Status::Pass);
+        assert!(config.is_some());
+    }
+
+    #[test]
+    fn test_placeholder_credentials_fail() {
+        let mut config = Config::default();
+        let check = check_credentials(&config);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.detail.contains("jira.api_token"));
+        assert!(
+            !check.detail.contains("salesforce"),
+            "disabled integrations are ignored"
+        );
+
+        config.jira.url = "https://acme.atlassian.net".to_string();
+        config.jira.email = "dev@acme.com".to_string();
+        config.jira.api_token = "real-token".to_string();
+        assert_eq!(check_credentials(&config).status, Status::Pass);
+    }
+
+    #[test]
+    fn test_database_check_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("analytics.db");
+        let mut config = Config::default();
+        config.analytics.database_path = path.to_string_lossy().into_owned();
+
+        let check = check_database(&config);
+        assert_eq!(check.status, Status::Pass);
+        assert!(check.detail.contains("created on the first start"));
+        assert!(!path.exists(), "doctor must not create the database");
+
+        // An older schema is reported but not migrated
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE schema_version (version INTEGER NOT NULL);
+             INSERT INTO schema_version (version) VALUES (1);",
+        )
+        .unwrap();
+        drop(conn);
+        let check = check_database(&config);
+        assert_eq!(check.status, Status::Pass);
+        assert!(
+            check.detail.contains("applied on the next start"),
+            "{}",
+            check.detail
+        );
+        assert_eq!(Database::schema_version_of(&path).unwrap(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        Database::new(path.clone()).unwrap();
+        let check = check_database(&config);
+        assert_eq!(check.status, Status::Pass);
+        assert_eq!(check.detail, path.display().to_string());
+    }
+
+    #[test]
+    fn test_report_counts_failures() {
+        let report = Report {
+            checks: vec![
+                Check::pass("a", ""),
+                Check::warn("b", "", ""),
+                Check::fail("c", "", ""),
+                Check::skip("d", ""),
+            ],
+        };
+        assert_eq!(report.failures(), 1);
+    }
+}
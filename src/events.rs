@@ -0,0 +1,36 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber; slower clients skip the oldest ones
+pub const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Live tracker updates pushed to daemon clients over `GET /events`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TrackerEvent {
+    /// Tracking started, paused, resumed or stopped
+    StateChanged {
+        state: &'static str,
+        session_id: Option<i64>,
+    },
+    /// A sync stored new activities for the session
+    ActivitiesStored { session_id: i64, count: usize },
+    /// A batch analysis finished; `logged` counts the entries posted to Jira
+    AnalysisComplete { session_id: i64, logged: usize },
+}
+
+impl TrackerEvent {
+    /// SSE event name, matching the `type` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrackerEvent::StateChanged { .. } => "state_changed",
+            TrackerEvent::ActivitiesStored { .. } => "activities_stored",
+            TrackerEvent::AnalysisComplete { .. } => "analysis_complete",
+        }
+    }
+}
+
+/// Channel the tracker publishes to and daemon clients subscribe to
+pub fn channel() -> broadcast::Sender<TrackerEvent> {
+    broadcast::channel(EVENT_CHANNEL_CAPACITY).0
+}
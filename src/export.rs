@@ -0,0 +1,124 @@
+use crate::{
+    activity_file::{ActivityRecord, FileFormat, COLUMNS},
+    database::Database,
+};
+use anyhow::{Context, Result};
+
+/// MIME type for an export in `format`
+pub fn content_type(format: FileFormat) -> &'static str {
+    match format {
+        FileFormat::Csv => "text/csv",
+        FileFormat::Json => "application/json",
+    }
+}
+
+/// Serialize records in the same layout `import` reads, so exports can be re-imported
+pub fn format_records(records: &[ActivityRecord], format: FileFormat) -> Result<String> {
+    match format {
+        FileFormat::Csv => {
+            // Write the header ourselves so an empty session still gets one
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Vec::new());
+            writer.write_record(COLUMNS)?;
+            for record in records {
+                writer.serialize(record)?;
+            }
+            let bytes = writer.into_inner().context("Failed to flush CSV export")?;
+            String::from_utf8(bytes).context("CSV export is not valid UTF-8")
+        }
+        FileFormat::Json => {
+            serde_json::to_string_pretty(records).context("Failed to serialize JSON export")
+        }
+    }
+}
+
+/// Export all activities of a session, shared by the `export` command and the daemon API
+pub fn export_session(database: &Database, session_id: i64, format: FileFormat) -> Result<String> {
+    if !database.session_exists(session_id)? {
+        anyhow::bail!("Session {} not found", session_id);
+    }
+
+    let session_tags = database.get_session_tags(session_id)?.join(", ");
+    let records: Vec<ActivityRecord> = database
+        .get_session_activities(session_id, None)?
+        .iter()
+        .map(|activity| ActivityRecord {
+            session_tags: session_tags.clone(),
+            ..ActivityRecord::from(activity)
+        })
+        .collect();
+
+    format_records(&records, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<ActivityRecord> {
+        vec![ActivityRecord {
+            timestamp: "2024-05-01T09:00:00Z".parse().unwrap(),
+            duration_secs: 1800,
+            app_name: "Code".to_string(),
+            window_title: "PROJ-1, main.rs".to_string(),
+            description: "fn main()".to_string(),
+            session_tags: "client onsite, bugfix".to_string(),
+            attributed_issue: String::new(),
+            attribution_source: String::new(),
+            category: "coding".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_format_csv() {
+        let csv = format_records(&records(), FileFormat::Csv).unwrap();
+        assert_eq!(
+            csv,
+            "timestamp,duration_secs,app_name,window_title,description,session_tags,attributed_issue,attribution_source,category\n\
+             2024-05-01T09:00:00Z,1800,Code,\"PROJ-1, main.rs\",fn main(),\"client onsite, bugfix\",,,coding\n"
+        );
+
+        let empty = format_records(&[], FileFormat::Csv).unwrap();
+        assert_eq!(
+            empty,
+            "timestamp,duration_secs,app_name,window_title,description,session_tags,attributed_issue,attribution_source,category\n"
+        );
+    }
+
+    #[test]
+    fn test_format_json() {
+        let json = format_records(&records(), FileFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["window_title"], "PROJ-1, main.rs");
+        assert_eq!(value[0]["duration_secs"], 1800);
+    }
+
+    #[test]
+    fn test_export_round_trips_through_import() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        // Categories aren't imported but re-derived from the rules
+        let db = Database::new(temp_file.path().to_path_buf())
+            .unwrap()
+            .with_category_rules(&[crate::config::CategoryRule {
+                match_app: Some("code".to_string()),
+                match_title_regex: None,
+                category: "coding".to_string(),
+            }])
+            .unwrap();
+
+        let csv = format_records(&records(), FileFormat::Csv).unwrap();
+        let report =
+            crate::activity_file::import_activities(&db, &csv, FileFormat::Csv, None).unwrap();
+
+        assert_eq!(
+            db.get_session_tags(report.session_id).unwrap(),
+            vec!["client onsite", "bugfix"]
+        );
+        assert_eq!(
+            export_session(&db, report.session_id, FileFormat::Csv).unwrap(),
+            csv
+        );
+        assert!(export_session(&db, report.session_id + 1, FileFormat::Csv).is_err());
+    }
+}
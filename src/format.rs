@@ -0,0 +1,116 @@
+//! Human-readable durations shared by the CLI, daemon and desktop apps
+
+use anyhow::{Context, Result};
+
+/// Whole hours and minutes, e.g. 5400 → "1h 30m"; leftover seconds are dropped
+pub fn format_hm(secs: u64) -> String {
+    format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+}
+
+/// Like `format_hm`, but leaving out a zero part: "2h 15m", "45m" or "3h", for short
+/// notification text
+pub fn format_hm_compact(secs: u64) -> String {
+    match (secs / 3600, secs % 3600 / 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+/// Hours, minutes and seconds, e.g. 5405 → "1h 30m 5s", for durations under a minute
+/// or where seconds matter
+pub fn format_hms(secs: u64) -> String {
+    format!("{}h {}m {}s", secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+/// Parse "1h 30m", "1h30m", "2h" or "45m" (case-insensitive) into seconds; the
+/// inverse of `format_hm`
+pub fn parse_hm(text: &str) -> Result<u64> {
+    let invalid = || format!("Invalid duration '{}', expected e.g. \"1h 30m\"", text);
+    let compact: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    if compact.is_empty() {
+        anyhow::bail!(invalid());
+    }
+
+    let mut secs = 0;
+    let mut number = String::new();
+    let (mut seen_hours, mut seen_minutes) = (false, false);
+    for c in compact.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: u64 = number.parse().with_context(invalid)?;
+        number.clear();
+        match c {
+            'h' if !seen_hours && !seen_minutes => {
+                seen_hours = true;
+                secs += value * 3600;
+            }
+            'm' if !seen_minutes => {
+                seen_minutes = true;
+                secs += value * 60;
+            }
+            _ => anyhow::bail!(invalid()),
+        }
+    }
+    if !number.is_empty() {
+        anyhow::bail!(invalid());
+    }
+    Ok(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_hm() {
+        assert_eq!(format_hm(5400), "1h 30m");
+        assert_eq!(format_hm(0), "0h 0m");
+        assert_eq!(format_hm(59), "0h 0m");
+        assert_eq!(format_hm(3600), "1h 0m");
+        assert_eq!(format_hm(26 * 3600 + 61), "26h 1m");
+    }
+
+    #[test]
+    fn test_format_hm_compact() {
+        assert_eq!(format_hm_compact(8100), "2h 15m");
+        assert_eq!(format_hm_compact(1200), "20m");
+        assert_eq!(format_hm_compact(3600), "1h");
+        assert_eq!(format_hm_compact(0), "0m");
+        assert_eq!(parse_hm(&format_hm_compact(8100)).unwrap(), 8100);
+    }
+
+    #[test]
+    fn test_format_hms() {
+        assert_eq!(format_hms(0), "0h 0m 0s");
+        assert_eq!(format_hms(59), "0h 0m 59s");
+        assert_eq!(format_hms(5405), "1h 30m 5s");
+    }
+
+    #[test]
+    fn test_parse_hm() {
+        assert_eq!(parse_hm("1h 30m").unwrap(), 5400);
+        assert_eq!(parse_hm("1H30M").unwrap(), 5400);
+        assert_eq!(parse_hm(" 2h ").unwrap(), 7200);
+        assert_eq!(parse_hm("90m").unwrap(), 5400);
+        assert_eq!(parse_hm("0h 0m").unwrap(), 0);
+
+        for invalid in ["", "90", "1.5h", "30m 1h", "1h 1h", "h", "1d"] {
+            let error = parse_hm(invalid).unwrap_err().to_string();
+            assert!(error.contains("Invalid duration"), "{}: {}", invalid, error);
+        }
+    }
+
+    #[test]
+    fn test_parse_hm_round_trips_format_hm() {
+        for secs in [0, 59, 60, 3599, 3600, 5400, 86_399, 90_061] {
+            assert_eq!(parse_hm(&format_hm(secs)).unwrap(), secs - secs % 60);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! A file the tracker loop rewrites with the current time on every iteration, so a loop
+//! stuck on a hung request can be told apart from one that is just idle. The daemon's
+//! `/health` endpoint and outside supervisors read it back.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::path::Path;
+
+/// Name of the heartbeat file, kept next to the analytics database
+pub const FILE_NAME: &str = "heartbeat";
+
+/// Record that the loop is alive at `now`
+pub fn beat(path: &Path, now: DateTime<Utc>) -> Result<()> {
+    std::fs::write(path, now.to_rfc3339())
+        .with_context(|| format!("Failed to write heartbeat {}", path.display()))
+}
+
+/// The last recorded beat, or `None` if the loop hasn't written one yet
+pub fn read(path: &Path) -> Result<Option<DateTime<Utc>>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read heartbeat {}", path.display()))
+        }
+    };
+
+    let at = DateTime::parse_from_rfc3339(contents.trim())
+        .with_context(|| format!("Heartbeat {} is not a timestamp", path.display()))?;
+    Ok(Some(at.with_timezone(&Utc)))
+}
+
+/// How old a beat may get before the loop counts as stuck: two poll intervals, so one slow
+/// iteration isn't reported
+pub fn max_age(poll_interval_secs: u64) -> Duration {
+    Duration::seconds(poll_interval_secs.saturating_mul(2) as i64)
+}
+
+/// Whether a loop polling every `poll_interval_secs` has missed its beats
+pub fn is_stale(last_beat: DateTime<Utc>, now: DateTime<Utc>, poll_interval_secs: u64) -> bool {
+    now - last_beat > max_age(poll_interval_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn test_staleness_is_two_poll_intervals() {
+        let last_beat = at("2024-05-01T10:00:00Z");
+
+        assert!(!is_stale(last_beat, at("2024-05-01T10:04:00Z"), 300));
+        assert!(!is_stale(last_beat, at("2024-05-01T10:10:00Z"), 300));
+        assert!(is_stale(last_beat, at("2024-05-01T10:10:01Z"), 300));
+        assert!(is_stale(last_beat, at("2024-05-01T10:03:00Z"), 60));
+    }
+
+    #[test]
+    fn test_beat_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("heartbeat");
+        assert_eq!(read(&path).unwrap(), None);
+
+        let now = at("2024-05-01T10:00:00Z");
+        beat(&path, now).unwrap();
+        assert_eq!(read(&path).unwrap(), Some(now));
+
+        std::fs::write(&path, "garbage").unwrap();
+        assert!(read(&path).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! The HTTP client shared by the Jira, Salesforce, Screenpipe and LLM integrations, so
+//! they reuse one connection pool and the same timeout and user agent.
+
+use crate::config::NetworkConfig;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::time::Duration;
+
+/// Sent with every request so server admins can tell the tracker's traffic apart
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Connecting gives up after this even when the request timeout is longer, so a host that
+/// is down fails fast
+const MAX_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Hosts that never go through the proxy, so the embedded Screenpipe server stays reachable
+const LOCAL_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+/// Build the client from the `[network]` section. Clone the result to share its pool.
+pub fn build_client(config: &NetworkConfig) -> Result<reqwest::Client> {
+    let mut builder = with_timeouts(
+        reqwest::Client::builder().user_agent(USER_AGENT),
+        Duration::from_secs(config.request_timeout_secs),
+    );
+
+    match resolve_proxy(config, |name| std::env::var(name).ok()) {
+        Some((proxy_url, no_proxy)) => {
+            let proxy = reqwest::Proxy::all(&proxy_url)
+                .with_context(|| format!("Invalid proxy URL '{}'", proxy_url))?
+                .no_proxy(reqwest::NoProxy::from_string(&no_proxy));
+            builder = builder.proxy(proxy);
+        }
+        None => builder = builder.no_proxy(),
+    }
+
+    if let Some(path) = &config.ca_cert_path {
+        for certificate in load_certificates(Path::new(path))? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if config.danger_accept_invalid_certs {
+        log::warn!(
+            "network.danger_accept_invalid_certs is on: TLS certificates are NOT verified, \
+             so credentials and worklogs can be intercepted. Use network.ca_cert_path instead."
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    builder.build().context("Failed to create HTTP client")
+}
+
+/// Client for integrations used without a shared one: the default `[network]` timeouts
+/// and user agent, with proxies from the environment
+pub fn default_client() -> reqwest::Client {
+    with_timeouts(
+        reqwest::Client::builder().user_agent(USER_AGENT),
+        Duration::from_secs(NetworkConfig::default().request_timeout_secs),
+    )
+    .build()
+    .unwrap_or_default()
+}
+
+fn with_timeouts(builder: reqwest::ClientBuilder, timeout: Duration) -> reqwest::ClientBuilder {
+    builder
+        .timeout(timeout)
+        .connect_timeout(timeout.min(MAX_CONNECT_TIMEOUT))
+}
+
+/// Read every certificate in the PEM bundle at `path`
+fn load_certificates(path: &Path) -> Result<Vec<reqwest::Certificate>> {
+    let pem = std::fs::read(path)
+        .with_context(|| format!("Failed to read CA certificate {}", path.display()))?;
+    let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("Invalid PEM in CA certificate {}", path.display()))?;
+    if certificates.is_empty() {
+        anyhow::bail!("No certificates found in {}", path.display());
+    }
+    Ok(certificates)
+}
+
+/// The proxy URL and comma-separated bypass list to use: `network.proxy_url` and
+/// `network.no_proxy` first, then the usual environment variables
+fn resolve_proxy(
+    config: &NetworkConfig,
+    env: impl Fn(&str) -> Option<String>,
+) -> Option<(String, String)> {
+    let first_env = |names: &[&str]| {
+        names
+            .iter()
+            .filter_map(|name| env(name))
+            .find(|value| !value.trim().is_empty())
+    };
+
+    let proxy_url = config
+        .proxy_url
+        .clone()
+        .filter(|url| !url.trim().is_empty())
+        .or_else(|| first_env(&["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"]))?;
+
+    let mut no_proxy: Vec<String> = if config.no_proxy.is_empty() {
+        first_env(&["NO_PROXY", "no_proxy"])
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|host| host.trim().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    } else {
+        config.no_proxy.clone()
+    };
+    no_proxy.extend(LOCAL_HOSTS.iter().map(|host| host.to_string()));
+    no_proxy.retain(|host| !host.is_empty());
+
+    Some((proxy_url, no_proxy.join(",")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Request, http::HeaderMap, routing::get, Router};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_user_agent_is_set() {
+        let app = Router::new().route(
+            "/",
+            get(|headers: HeaderMap| async move {
+                headers
+                    .get("user-agent")
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = build_client(&NetworkConfig::default()).unwrap();
+        let user_agent = client.get(&url).send().await.unwrap().text().await.unwrap();
+
+        assert_eq!(user_agent, USER_AGENT);
+        assert!(user_agent.starts_with("work-to-jira-effort/"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_endpoint_times_out() {
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                "too late"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = NetworkConfig {
+            request_timeout_secs: 1,
+            ..NetworkConfig::default()
+        };
+        let client = build_client(&config).unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), client.get(&url).send())
+            .await
+            .expect("request should time out instead of hanging");
+
+        assert!(result.unwrap_err().is_timeout());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_url_is_applied() {
+        // Stands in for the proxy: answers every request with the URI it was asked for
+        let proxy =
+            Router::new().fallback(|request: Request| async move { request.uri().to_string() });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, proxy).await.unwrap() });
+
+        let config = NetworkConfig {
+            proxy_url: Some(proxy_url),
+            ..NetworkConfig::default()
+        };
+        let client = build_client(&config).unwrap();
+        let body = client
+            .get("http://jira.corp.example/rest/api/3/myself")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert_eq!(body, "http://jira.corp.example/rest/api/3/myself");
+    }
+
+    #[test]
+    fn test_proxy_falls_back_to_env() {
+        let env: HashMap<&str, &str> = [
+            ("HTTPS_PROXY", "http://env-proxy:3128"),
+            ("NO_PROXY", "corp.example, .internal"),
+        ]
+        .into();
+        let lookup = |name: &str| env.get(name).map(|value| value.to_string());
+
+        let (url, no_proxy) = resolve_proxy(&NetworkConfig::default(), lookup).unwrap();
+        assert_eq!(url, "http://env-proxy:3128");
+        assert_eq!(no_proxy, "corp.example,.internal,localhost,127.0.0.1,::1");
+
+        let configured = NetworkConfig {
+            proxy_url: Some("http://config-proxy:8080".to_string()),
+            no_proxy: vec!["jira.corp.example".to_string()],
+            ..NetworkConfig::default()
+        };
+        let (url, no_proxy) = resolve_proxy(&configured, lookup).unwrap();
+        assert_eq!(url, "http://config-proxy:8080");
+        assert_eq!(no_proxy, "jira.corp.example,localhost,127.0.0.1,::1");
+
+        assert_eq!(resolve_proxy(&NetworkConfig::default(), |_| None), None);
+    }
+
+    #[test]
+    fn test_bad_ca_cert_path_is_a_clear_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("corp-ca.pem");
+        let config = NetworkConfig {
+            ca_cert_path: Some(missing.display().to_string()),
+            ..NetworkConfig::default()
+        };
+        let error = format!("{:#}", build_client(&config).unwrap_err());
+        assert!(error.contains("Failed to read CA certificate"));
+        assert!(error.contains(&missing.display().to_string()));
+
+        let not_pem = dir.path().join("not-a-cert.pem");
+        std::fs::write(&not_pem, "hello").unwrap();
+        let config = NetworkConfig {
+            ca_cert_path: Some(not_pem.display().to_string()),
+            ..NetworkConfig::default()
+        };
+        let error = format!("{:#}", build_client(&config).unwrap_err());
+        assert!(error.contains(&not_pem.display().to_string()));
+    }
+}
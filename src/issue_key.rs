@@ -0,0 +1,52 @@
+//! Issue key normalization for the manual override, manual entries and activity corrections,
+//! shared by the daemon, the tray and the tracker so a key typed as `proj-42 ` ends up as
+//! `PROJ-42` everywhere.
+
+use anyhow::{bail, Result};
+
+/// Whether `key` has the `PROJECT-NUMBER` shape Jira uses, e.g. `PROJ-42` or `AB_2-7`
+pub fn is_issue_key(key: &str) -> bool {
+    key.split_once('-').is_some_and(|(project, number)| {
+        project.starts_with(|c: char| c.is_ascii_uppercase())
+            && project
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+/// Trim and uppercase `input`, rejecting anything that isn't shaped like an issue key
+pub fn normalize(input: &str) -> Result<String> {
+    let key = input.trim().to_uppercase();
+    if !is_issue_key(&key) {
+        bail!(
+            "'{}' is not a Jira issue key (expected PROJECT-NUMBER, e.g. PROJ-42)",
+            input.trim()
+        );
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_trims_and_uppercases() {
+        assert_eq!(normalize("proj-42 ").unwrap(), "PROJ-42");
+        assert_eq!(normalize("  AB_2-7").unwrap(), "AB_2-7");
+    }
+
+    #[test]
+    fn test_normalize_rejects_non_keys() {
+        let error = normalize("not an issue").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("'not an issue' is not a Jira issue key"));
+
+        for input in ["", "PROJ", "PROJ-", "-42", "42-PROJ", "PROJ-4a"] {
+            assert!(normalize(input).is_err(), "{:?} should be rejected", input);
+        }
+    }
+}
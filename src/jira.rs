@@ -1,25 +1,92 @@
+use crate::config::{default_worklog_comment_template, JiraSearchApi};
+use crate::http;
+use crate::issue_key;
 use crate::llm::AssignedIssue;
+use crate::rate_limit::RateLimiter;
 use crate::screenpipe::Activity;
+use crate::work_type::WorkType;
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 use tokio::sync::RwLock;
 
+/// Times a worklog write is retried after Jira answers 429 Too Many Requests
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+/// Back-off when a 429 response has no usable `Retry-After` header
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// Cap on issues returned by `search_issues`, enough for a typeahead list
+const MAX_SEARCH_RESULTS: u32 = 20;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WorklogEntry {
     pub comment: String,
     #[serde(rename = "timeSpentSeconds")]
     pub time_spent_seconds: u64,
     pub started: String,
+    /// Worklog attributes, e.g. the work type
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<WorklogProperty>,
+}
+
+/// Worklog body for Tempo Timesheets (`POST /rest/tempo-timesheets/4/worklogs`)
+#[derive(Debug, Serialize)]
+pub struct TempoWorklogEntry {
+    #[serde(rename = "authorAccountId")]
+    pub author_account_id: String,
+    /// Issue key the time is logged against
+    #[serde(rename = "originTaskId")]
+    pub origin_task_id: String,
+    pub comment: String,
+    /// Local date and time, without a zone
+    pub started: String,
+    #[serde(rename = "timeSpentSeconds")]
+    pub time_spent_seconds: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<WorklogProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TempoWorklogResponse {
+    #[serde(rename = "tempoWorklogId")]
+    tempo_worklog_id: i64,
+    /// Id of the core Jira worklog Tempo created alongside its own
+    #[serde(rename = "originId")]
+    origin_id: Option<i64>,
+}
+
+/// Jira entity property attached to a worklog
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorklogProperty {
+    pub key: String,
+    pub value: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
 pub struct JiraWorklogResponse {
     pub id: String,
 }
 
+/// Returned when logging work to an issue that was deleted, moved to another key or
+/// hidden from you; find it with `err.downcast_ref::<IssueNotFound>()`
+#[derive(Debug, thiserror::Error)]
+#[error("Jira issue {0} was not found (deleted, moved or not visible to you)")]
+pub struct IssueNotFound(pub String);
+
+/// Body of a Jira error response, e.g. for an issue that doesn't exist
+#[derive(Debug, Deserialize)]
+struct JiraErrorResponse {
+    #[serde(rename = "errorMessages", default)]
+    error_messages: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 pub struct JiraUser {
@@ -49,15 +116,133 @@ pub struct JiraAssignee {
     pub account_id: String,
 }
 
+/// A worklog as listed by `GET /issue/{key}/worklog`
+#[derive(Debug, Deserialize)]
+pub struct JiraWorklog {
+    pub id: String,
+    pub author: Option<JiraWorklogAuthor>,
+    pub started: String,
+    #[serde(rename = "timeSpentSeconds")]
+    pub time_spent_seconds: u64,
+}
+
+impl JiraWorklog {
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_str(&self.started, "%Y-%m-%dT%H:%M:%S%.f%z")
+            .ok()
+            .map(|started| started.with_timezone(&Utc))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JiraWorklogAuthor {
+    #[serde(rename = "accountId")]
+    pub account_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraWorklogPage {
+    total: usize,
+    worklogs: Vec<JiraWorklog>,
+}
+
+/// A project as listed by `GET /project/search`
+#[derive(Debug, Clone, Deserialize)]
+pub struct JiraProject {
+    pub key: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraProjectPage {
+    values: Vec<JiraProject>,
+    #[serde(rename = "isLast", default)]
+    is_last: bool,
+}
+
+/// Project keys picked from `projects` by a comma- or space-separated `input`, in any case.
+/// Keys that aren't in `projects` are an error.
+pub fn select_projects(input: &str, projects: &[JiraProject]) -> Result<Vec<String>> {
+    let mut selected: Vec<String> = Vec::new();
+    for key in input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|key| key.trim().to_uppercase())
+        .filter(|key| !key.is_empty())
+    {
+        if !projects.iter().any(|project| project.key == key) {
+            anyhow::bail!("Unknown project key '{}'", key);
+        }
+        if !selected.contains(&key) {
+            selected.push(key);
+        }
+    }
+    Ok(selected)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct JiraSearchResponse {
     pub issues: Vec<JiraIssue>,
     pub total: usize,
 }
 
+/// A page from `/rest/api/3/search/jql`, which pages with a cursor instead of `startAt`
+#[derive(Debug, Deserialize)]
+struct JiraJqlSearchPage {
+    issues: Vec<JiraIssue>,
+    #[serde(rename = "nextPageToken")]
+    next_page_token: Option<String>,
+    #[serde(rename = "isLast", default)]
+    is_last: bool,
+}
+
+/// Prefixes that look like issue keys but are almost always something else
+/// (encodings, protocols, hashes, ...). Only applied when no project allowlist is set.
+const ISSUE_KEY_DENYLIST: &[&str] = &[
+    "UTF", "HTTP", "COVID", "ISO", "SHA", "MD", "AES", "RSA", "TLS", "SSL", "IPV", "WIN", "CVE",
+    "RFC", "GPT", "MP", "H", "X", "PDF", "USB", "HDMI",
+];
+
+/// Build the regex used to detect issue keys, restricted to `project_keys` when non-empty
+pub(crate) fn build_issue_key_regex(project_keys: &[String]) -> regex::Regex {
+    let pattern = if project_keys.is_empty() {
+        r"([A-Z]+-\d+)".to_string()
+    } else {
+        let prefixes: Vec<String> = project_keys
+            .iter()
+            .map(|k| regex::escape(&k.trim().to_uppercase()))
+            .collect();
+        format!(r"\b((?:{})-\d+)\b", prefixes.join("|"))
+    };
+
+    regex::Regex::new(&pattern).expect("Invalid issue key regex")
+}
+
+/// Find the first issue key in `text` that isn't a known false positive
+pub(crate) fn detect_issue_key(
+    regex: &regex::Regex,
+    text: &str,
+    use_denylist: bool,
+) -> Option<String> {
+    regex
+        .captures_iter(text)
+        .filter_map(|captures| captures.get(1))
+        .map(|m| m.as_str())
+        .find(|key| {
+            if !use_denylist {
+                return true;
+            }
+            let prefix = key.split('-').next().unwrap_or_default();
+            !ISSUE_KEY_DENYLIST.contains(&prefix)
+        })
+        .map(|key| key.to_string())
+}
+
 /// Cached assigned issues with timestamp
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct AssignedIssuesCache {
+    /// Jira site and account the issues were fetched for, so a changed config isn't served stale data
+    base_url: String,
+    email: String,
     issues: Vec<AssignedIssue>,
     cached_at: DateTime<Utc>,
 }
@@ -69,6 +254,24 @@ pub struct JiraClient {
     client: reqwest::Client,
     assigned_issues_cache: Arc<RwLock<Option<AssignedIssuesCache>>>,
     cache_duration_secs: u64,
+    /// Where the assigned-issues cache is persisted across restarts; in memory only when `None`
+    cache_file: Option<PathBuf>,
+    issue_key_regex: regex::Regex,
+    has_project_keys: bool,
+    dry_run: bool,
+    comment_template: String,
+    /// Zone worklog start times are expressed in; the system zone when `None`
+    timezone: Option<Tz>,
+    /// Paces worklog writes so batch analyses don't trip Jira's rate limits
+    rate_limiter: RateLimiter,
+    /// Worklog property key and the value sent under it for each work type
+    worklog_attribute_key: String,
+    worklog_attributes: BTreeMap<WorkType, String>,
+    /// Post worklogs through Tempo Timesheets instead of the core worklog API
+    use_tempo: bool,
+    /// Account id of the API user, looked up once for Tempo's `authorAccountId`
+    account_id: tokio::sync::OnceCell<String>,
+    search_api: JiraSearchApi,
 }
 
 impl JiraClient {
@@ -77,48 +280,238 @@ impl JiraClient {
             base_url,
             email,
             api_token,
-            client: reqwest::Client::new(),
+            client: http::default_client(),
             assigned_issues_cache: Arc::new(RwLock::new(None)),
             cache_duration_secs: 7200, // 2 hours default
+            cache_file: None,
+            issue_key_regex: build_issue_key_regex(&[]),
+            has_project_keys: false,
+            dry_run: false,
+            comment_template: default_worklog_comment_template(),
+            timezone: None,
+            rate_limiter: RateLimiter::new(0.0),
+            worklog_attribute_key: String::new(),
+            worklog_attributes: BTreeMap::new(),
+            use_tempo: false,
+            account_id: tokio::sync::OnceCell::new(),
+            search_api: JiraSearchApi::Auto,
         }
     }
 
+    /// Use a shared client (see `http::build_client`) instead of a private one
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Choose the issue search endpoint; `Auto` picks by whether the site is on Atlassian Cloud
+    pub fn with_search_api(mut self, search_api: JiraSearchApi) -> Self {
+        self.search_api = search_api;
+        self
+    }
+
+    /// Log time through Tempo Timesheets, for sites where Tempo owns worklogs
+    pub fn with_tempo(mut self, use_tempo: bool) -> Self {
+        self.use_tempo = use_tempo;
+        self
+    }
+
+    /// Tag worklogs with a `key` property holding the value mapped from their work type
+    pub fn with_worklog_attributes(
+        mut self,
+        key: String,
+        attributes: BTreeMap<WorkType, String>,
+    ) -> Self {
+        self.worklog_attribute_key = key;
+        self.worklog_attributes = attributes;
+        self
+    }
+
+    /// Properties for a worklog of `work_type`; empty when the type has no mapped value
+    fn worklog_properties(&self, work_type: Option<WorkType>) -> Vec<WorklogProperty> {
+        work_type
+            .and_then(|work_type| self.worklog_attributes.get(&work_type))
+            .map(|value| WorklogProperty {
+                key: self.worklog_attribute_key.clone(),
+                value: serde_json::Value::String(value.clone()),
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// Send at most `requests_per_second` worklog writes; 0 sends them back-to-back
+    pub fn with_rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.rate_limiter = RateLimiter::new(requests_per_second);
+        self
+    }
+
+    /// Express worklog start times in `timezone` instead of the system zone
+    pub fn with_timezone(mut self, timezone: Option<Tz>) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Template for worklog comments, see `render_comment`
+    pub fn with_comment_template(mut self, comment_template: String) -> Self {
+        self.comment_template = comment_template;
+        self
+    }
+
+    /// Render the worklog comment template for an activity. `work_type` fills `{work_type}`
+    /// and is empty when unknown.
+    pub fn render_comment(
+        &self,
+        issue_key: &str,
+        activity: &Activity,
+        work_type: Option<&str>,
+    ) -> String {
+        let date = activity.timestamp.format("%Y-%m-%d").to_string();
+        render_template(
+            &self.comment_template,
+            &[
+                ("app", activity.app_name.as_str()),
+                ("window", activity.window_title.as_str()),
+                ("issue", issue_key),
+                ("date", date.as_str()),
+                ("work_type", work_type.unwrap_or_default()),
+            ],
+        )
+    }
+
+    /// Log would-be worklogs instead of posting them
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Restrict issue detection to the given project key prefixes
+    pub fn with_project_keys(mut self, project_keys: &[String]) -> Self {
+        self.issue_key_regex = build_issue_key_regex(project_keys);
+        self.has_project_keys = !project_keys.is_empty();
+        self
+    }
+
     pub fn with_cache_duration(mut self, cache_duration_secs: u64) -> Self {
         self.cache_duration_secs = cache_duration_secs;
         self
     }
 
-    pub async fn log_work(&self, issue_key: &str, activity: &Activity) -> Result<()> {
+    /// Persist the assigned-issues cache to `path`, starting from its contents when they are
+    /// still fresh and belong to this site and account. Set after `with_cache_duration`.
+    pub fn with_cache_file(mut self, path: PathBuf) -> Self {
+        let cached = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<AssignedIssuesCache>(&content).ok())
+            .filter(|cache| cache.base_url == self.base_url && cache.email == self.email)
+            .filter(|cache| {
+                (Utc::now() - cache.cached_at).num_seconds() < self.cache_duration_secs as i64
+            });
+
+        if let Some(cache) = &cached {
+            log::debug!(
+                "Loaded {} assigned issues cached at {} from {}",
+                cache.issues.len(),
+                cache.cached_at,
+                path.display()
+            );
+        }
+
+        self.assigned_issues_cache = Arc::new(RwLock::new(cached));
+        self.cache_file = Some(path);
+        self
+    }
+
+    /// Write the cache to `cache_file`; failures only cost a refetch after restart
+    fn save_cache_file(&self, cache: &AssignedIssuesCache) {
+        let Some(path) = &self.cache_file else {
+            return;
+        };
+
+        let result = serde_json::to_string(cache)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, content)?;
+                Ok(())
+            });
+        if let Err(e) = result {
+            log::warn!(
+                "Failed to persist assigned issues cache to {}: {:#}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    /// Log work against an issue with the templated comment, returning the id of the created worklog.
+    /// In dry-run mode nothing is sent and an empty id is returned.
+    pub async fn log_work(&self, issue_key: &str, activity: &Activity) -> Result<String> {
+        let comment = self.render_comment(issue_key, activity, None);
+        self.log_work_with_comment(issue_key, activity, comment, None)
+            .await
+    }
+
+    /// Log work against an issue with an explicit comment (e.g. an LLM-written summary),
+    /// tagged with the attribute configured for `work_type`
+    pub async fn log_work_with_comment(
+        &self,
+        issue_key: &str,
+        activity: &Activity,
+        comment: String,
+        work_type: Option<WorkType>,
+    ) -> Result<String> {
+        if self.use_tempo {
+            return self
+                .log_work_tempo(issue_key, activity, comment, work_type)
+                .await;
+        }
+
         let url = format!("{}/rest/api/3/issue/{}/worklog", self.base_url, issue_key);
 
         let worklog = WorklogEntry {
-            comment: format!(
-                "Auto-tracked: {} - {}",
-                activity.app_name, activity.window_title
-            ),
+            comment,
             time_spent_seconds: activity.duration_secs,
-            started: activity
-                .timestamp
-                .format("%Y-%m-%dT%H:%M:%S%.3f%z")
-                .to_string(),
+            started: format_started(activity.timestamp, self.timezone),
+            properties: self.worklog_properties(work_type),
         };
 
-        let response = self
+        if self.dry_run {
+            log::info!(
+                "[dry-run] Would log {} seconds to Jira issue {} starting {}: {}",
+                worklog.time_spent_seconds,
+                issue_key,
+                worklog.started,
+                worklog.comment
+            );
+            return Ok(String::new());
+        }
+
+        let request = self
             .client
             .post(&url)
             .basic_auth(&self.email, Some(&self.api_token))
-            .json(&worklog)
-            .send()
+            .json(&worklog);
+        let response = self
+            .send_paced(request)
             .await
             .context("Failed to log work to Jira")?;
 
+        // A wrong base URL also answers 404, so only a confirmed missing issue is reported
+        // as one; its activities are never retried
+        if response.status() == reqwest::StatusCode::NOT_FOUND
+            && !self.issue_exists(issue_key).await?
+        {
+            return Err(IssueNotFound(issue_key.to_string()).into());
+        }
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             anyhow::bail!("Jira API error ({}): {}", status, text);
         }
 
-        let _result: JiraWorklogResponse = response
+        let result: JiraWorklogResponse = response
             .json()
             .await
             .context("Failed to parse Jira response")?;
@@ -128,23 +521,181 @@ impl JiraClient {
             activity.duration_secs,
             issue_key
         );
-        Ok(())
+        Ok(result.id)
     }
 
-    pub async fn find_issue_from_activity(&self, activity: &Activity) -> Result<Option<String>> {
-        // Simple heuristic: look for Jira issue keys (e.g., PROJ-123) in window title or app name
-        let text = format!("{} {}", activity.window_title, activity.app_name);
+    /// Build the Tempo Timesheets worklog for an activity
+    fn tempo_worklog(
+        &self,
+        author_account_id: String,
+        issue_key: &str,
+        activity: &Activity,
+        comment: String,
+        work_type: Option<WorkType>,
+    ) -> TempoWorklogEntry {
+        TempoWorklogEntry {
+            author_account_id,
+            origin_task_id: issue_key.to_string(),
+            comment,
+            started: format_started_local(activity.timestamp, self.timezone),
+            time_spent_seconds: activity.duration_secs,
+            attributes: self.worklog_properties(work_type),
+        }
+    }
+
+    /// Log work through Tempo Timesheets. Returns the id of the core Jira worklog Tempo
+    /// creates alongside its own, so undo and duplicate checks work as without Tempo.
+    pub async fn log_work_tempo(
+        &self,
+        issue_key: &str,
+        activity: &Activity,
+        comment: String,
+        work_type: Option<WorkType>,
+    ) -> Result<String> {
+        if self.dry_run {
+            let worklog =
+                self.tempo_worklog(String::new(), issue_key, activity, comment, work_type);
+            log::info!(
+                "[dry-run] Would log {} seconds to {} in Tempo starting {}: {}",
+                worklog.time_spent_seconds,
+                issue_key,
+                worklog.started,
+                worklog.comment
+            );
+            return Ok(String::new());
+        }
+
+        let account_id = self
+            .account_id
+            .get_or_try_init(|| async { self.get_current_user().await.map(|user| user.account_id) })
+            .await?
+            .clone();
+        let worklog = self.tempo_worklog(account_id, issue_key, activity, comment, work_type);
+
+        let url = format!("{}/rest/tempo-timesheets/4/worklogs", self.base_url);
+        let request = self
+            .client
+            .post(&url)
+            .basic_auth(&self.email, Some(&self.api_token))
+            .json(&worklog);
+        let response = self
+            .send_paced(request)
+            .await
+            .context("Failed to log work to Tempo")?;
 
-        // Regex pattern for Jira issue keys
-        let issue_key_regex = regex::Regex::new(r"([A-Z]+-\d+)").unwrap();
+        // Tempo answers 404 when it isn't installed, so it says nothing about the issue
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            anyhow::bail!(
+                "Tempo API not found at {}; check that Tempo Timesheets is installed or turn off jira.use_tempo",
+                url
+            );
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Tempo API error ({}): {}", status, text);
+        }
+
+        // Tempo answers with the list of worklogs it created
+        let created: Vec<TempoWorklogResponse> = response
+            .json()
+            .await
+            .context("Failed to parse Tempo response")?;
+        let created = created
+            .first()
+            .context("Tempo response contained no worklog")?;
+
+        log::info!(
+            "Logged {} seconds to {} in Tempo (worklog {})",
+            activity.duration_secs,
+            issue_key,
+            created.tempo_worklog_id
+        );
+        Ok(created
+            .origin_id
+            .unwrap_or(created.tempo_worklog_id)
+            .to_string())
+    }
+
+    /// Send a write through the rate limiter. On 429 the limiter is paused for the
+    /// `Retry-After` period, so other writes wait too, and the request is retried.
+    async fn send_paced(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut retries = 0;
+        loop {
+            let attempt = request.try_clone().context("Request cannot be retried")?;
+            self.rate_limiter.acquire().await;
+            let response = attempt.send().await?;
 
-        if let Some(captures) = issue_key_regex.captures(&text) {
-            if let Some(issue_key) = captures.get(1) {
-                return Ok(Some(issue_key.as_str().to_string()));
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS
+                || retries == MAX_RATE_LIMIT_RETRIES
+            {
+                return Ok(response);
             }
+            retries += 1;
+
+            let wait = retry_after(&response).unwrap_or(DEFAULT_RETRY_AFTER);
+            log::warn!(
+                "Jira rate limit reached; pausing worklog writes for {}s",
+                wait.as_secs()
+            );
+            self.rate_limiter.pause(wait).await;
+        }
+    }
+
+    fn worklog_url(&self, issue_key: &str, worklog_id: &str) -> String {
+        format!(
+            "{}/rest/api/3/issue/{}/worklog/{}",
+            self.base_url, issue_key, worklog_id
+        )
+    }
+
+    /// Delete a previously logged worklog. One that is already gone counts as deleted.
+    pub async fn delete_worklog(&self, issue_key: &str, worklog_id: &str) -> Result<()> {
+        let url = self.worklog_url(issue_key, worklog_id);
+
+        let request = self
+            .client
+            .delete(&url)
+            .basic_auth(&self.email, Some(&self.api_token));
+        let response = self
+            .send_paced(request)
+            .await
+            .context("Failed to delete Jira worklog")?;
+
+        // Deleted by hand (or with its issue) already; a 404 from a wrong base URL fails the
+        // issue lookup instead
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            self.issue_exists(issue_key).await?;
+            log::info!(
+                "Worklog {} on {} was already deleted from Jira",
+                worklog_id,
+                issue_key
+            );
+            return Ok(());
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Jira API error ({}): {}", status, text);
         }
 
-        Ok(None)
+        log::info!(
+            "Deleted worklog {} from Jira issue {}",
+            worklog_id,
+            issue_key
+        );
+        Ok(())
+    }
+
+    pub async fn find_issue_from_activity(&self, activity: &Activity) -> Result<Option<String>> {
+        // Simple heuristic: look for Jira issue keys (e.g., PROJ-123) in window title or app name
+        let text = format!("{} {}", activity.window_title, activity.app_name);
+
+        Ok(detect_issue_key(
+            &self.issue_key_regex,
+            &text,
+            !self.has_project_keys,
+        ))
     }
 
     pub async fn health_check(&self) -> Result<bool> {
@@ -162,6 +713,58 @@ impl JiraClient {
         }
     }
 
+    /// Whether `issue_key` exists and is visible to you. Only Jira's own "issue does not
+    /// exist" answer counts as missing; any other 404 (e.g. a wrong base URL) is an error.
+    pub async fn issue_exists(&self, issue_key: &str) -> Result<bool> {
+        Ok(self.fetch_issue(issue_key).await?.is_some())
+    }
+
+    /// Key and summary of `issue_key`, or `None` when Jira says it doesn't exist
+    pub async fn get_issue(&self, issue_key: &str) -> Result<Option<AssignedIssue>> {
+        let Some(body) = self.fetch_issue(issue_key).await? else {
+            return Ok(None);
+        };
+        let issue: JiraIssue =
+            serde_json::from_str(&body).context("Failed to parse Jira issue response")?;
+
+        Ok(Some(AssignedIssue {
+            key: issue.key,
+            summary: issue.fields.summary,
+        }))
+    }
+
+    /// Body of `GET /issue/{key}` with just the summary, or `None` on a confirmed missing issue
+    async fn fetch_issue(&self, issue_key: &str) -> Result<Option<String>> {
+        let url = format!("{}/rest/api/3/issue/{}", self.base_url, issue_key);
+
+        let response = self
+            .client
+            .get(&url)
+            .basic_auth(&self.email, Some(&self.api_token))
+            .query(&[("fields", "summary")])
+            .send()
+            .await
+            .context("Failed to look up Jira issue")?;
+
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        if status.is_success() {
+            return Ok(Some(text));
+        }
+        if status == reqwest::StatusCode::NOT_FOUND
+            && serde_json::from_str::<JiraErrorResponse>(&text)
+                .is_ok_and(|error| !error.error_messages.is_empty())
+        {
+            return Ok(None);
+        }
+        anyhow::bail!(
+            "Jira API error ({}) looking up {}: {}",
+            status,
+            issue_key,
+            text
+        );
+    }
+
     /// Get current user information
     pub async fn get_current_user(&self) -> Result<JiraUser> {
         let url = format!("{}/rest/api/3/myself", self.base_url);
@@ -188,26 +791,229 @@ impl JiraClient {
         Ok(user)
     }
 
+    /// All projects visible to the user, ordered by key
+    pub async fn list_projects(&self) -> Result<Vec<JiraProject>> {
+        let url = format!("{}/rest/api/3/project/search", self.base_url);
+        let mut projects = Vec::new();
+
+        loop {
+            let response = self
+                .client
+                .get(&url)
+                .basic_auth(&self.email, Some(&self.api_token))
+                .query(&[
+                    ("orderBy", "key".to_string()),
+                    ("startAt", projects.len().to_string()),
+                    ("maxResults", "50".to_string()),
+                ])
+                .send()
+                .await
+                .context("Failed to list Jira projects")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                anyhow::bail!("Jira API error ({}): {}", status, text);
+            }
+
+            let page: JiraProjectPage = response
+                .json()
+                .await
+                .context("Failed to parse Jira project search response")?;
+
+            let fetched = page.values.len();
+            projects.extend(page.values);
+            if fetched == 0 || page.is_last {
+                return Ok(projects);
+            }
+        }
+    }
+
+    /// Worklogs on an issue started at or after `since`, by anyone
+    pub async fn get_worklogs(
+        &self,
+        issue_key: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<JiraWorklog>> {
+        let url = format!("{}/rest/api/3/issue/{}/worklog", self.base_url, issue_key);
+        let mut worklogs = Vec::new();
+
+        loop {
+            let response = self
+                .client
+                .get(&url)
+                .basic_auth(&self.email, Some(&self.api_token))
+                .query(&[
+                    ("startedAfter", since.timestamp_millis().to_string()),
+                    ("startAt", worklogs.len().to_string()),
+                ])
+                .send()
+                .await
+                .context("Failed to fetch Jira worklogs")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                anyhow::bail!("Jira API error ({}): {}", status, text);
+            }
+
+            let page: JiraWorklogPage = response
+                .json()
+                .await
+                .context("Failed to parse Jira worklogs response")?;
+
+            let fetched = page.worklogs.len();
+            worklogs.extend(page.worklogs);
+            if fetched == 0 || worklogs.len() >= page.total {
+                return Ok(worklogs);
+            }
+        }
+    }
+
     /// Fetch issues assigned to the current user
     async fn fetch_assigned_issues_from_api(&self) -> Result<Vec<AssignedIssue>> {
         // Get current user first
         let user = self.get_current_user().await?;
 
         // JQL query to get issues assigned to current user
-        let jql = format!("assignee = \"{}\" AND resolution = Unresolved ORDER BY updated DESC", user.account_id);
-
-        let url = format!("{}/rest/api/3/search", self.base_url);
+        let jql = format!(
+            "assignee = \"{}\" AND resolution = Unresolved ORDER BY updated DESC",
+            user.account_id
+        );
 
         log::debug!("Fetching assigned issues with JQL: {}", jql);
 
+        let assigned_issues = self
+            .search(&jql, 100)
+            .await
+            .context("Failed to search for assigned issues")?;
+
+        log::info!(
+            "Fetched {} assigned issues from Jira",
+            assigned_issues.len()
+        );
+
+        Ok(assigned_issues)
+    }
+
+    /// Issues whose summary starts a word with `query`, or whose key is `query`, for
+    /// typeahead; at most `MAX_SEARCH_RESULTS`. The key is looked up on its own, since JQL
+    /// rejects a `key =` clause naming an issue or project that doesn't exist.
+    pub async fn search_issues(&self, query: &str) -> Result<Vec<AssignedIssue>> {
+        if query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut issues = self
+            .search(&search_jql(query), MAX_SEARCH_RESULTS)
+            .await
+            .context("Failed to search Jira issues")?;
+
+        if let Ok(key) = issue_key::normalize(query.trim()) {
+            match self.get_issue(&key).await {
+                Ok(Some(issue)) => {
+                    issues.retain(|i| i.key != issue.key);
+                    issues.insert(0, issue);
+                    issues.truncate(MAX_SEARCH_RESULTS as usize);
+                }
+                Ok(None) => {}
+                Err(e) => log::debug!("Failed to look up {} for search: {:#}", key, e),
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Run a JQL search, returning key and summary of up to `max_results` issues
+    async fn search(&self, jql: &str, max_results: u32) -> Result<Vec<AssignedIssue>> {
+        let use_jql_endpoint = match self.search_api {
+            JiraSearchApi::Jql => true,
+            JiraSearchApi::Legacy => false,
+            JiraSearchApi::Auto => self.base_url.contains(".atlassian.net"),
+        };
+        let issues = if use_jql_endpoint {
+            self.search_jql_endpoint(jql, max_results).await?
+        } else {
+            self.search_legacy(jql, max_results).await?
+        };
+
+        Ok(issues
+            .into_iter()
+            .map(|issue| AssignedIssue {
+                key: issue.key,
+                summary: issue.fields.summary,
+            })
+            .collect())
+    }
+
+    /// `/rest/api/3/search/jql`, following `nextPageToken` until `max_results` issues or the
+    /// last page
+    async fn search_jql_endpoint(&self, jql: &str, max_results: u32) -> Result<Vec<JiraIssue>> {
+        let url = format!("{}/rest/api/3/search/jql", self.base_url);
+        let mut issues = Vec::new();
+        let mut next_page_token: Option<String> = None;
+
+        while issues.len() < max_results as usize {
+            let mut query = vec![
+                ("jql", jql.to_string()),
+                (
+                    "maxResults",
+                    (max_results as usize - issues.len()).to_string(),
+                ),
+                ("fields", "summary,assignee".to_string()),
+            ];
+            if let Some(token) = next_page_token.take() {
+                query.push(("nextPageToken", token));
+            }
+
+            let response = self
+                .client
+                .get(&url)
+                .basic_auth(&self.email, Some(&self.api_token))
+                .query(&query)
+                .send()
+                .await
+                .context("Failed to send Jira search request")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                anyhow::bail!("Jira search API error ({}): {}", status, text);
+            }
+
+            let page: JiraJqlSearchPage = response
+                .json()
+                .await
+                .context("Failed to parse Jira search response")?;
+
+            let fetched = page.issues.len();
+            issues.extend(page.issues);
+            match page.next_page_token {
+                Some(token) if !page.is_last && fetched > 0 => next_page_token = Some(token),
+                _ => break,
+            }
+        }
+
+        issues.truncate(max_results as usize);
+        Ok(issues)
+    }
+
+    /// `/rest/api/3/search`, for Jira Server and Data Center
+    async fn search_legacy(&self, jql: &str, max_results: u32) -> Result<Vec<JiraIssue>> {
+        let url = format!("{}/rest/api/3/search", self.base_url);
+
         let response = self
             .client
             .get(&url)
             .basic_auth(&self.email, Some(&self.api_token))
-            .query(&[("jql", jql), ("maxResults", "100".to_string()), ("fields", "summary,assignee".to_string())])
+            .query(&[
+                ("jql", jql.to_string()),
+                ("maxResults", max_results.to_string()),
+                ("fields", "summary,assignee".to_string()),
+            ])
             .send()
             .await
-            .context("Failed to search for assigned issues")?;
+            .context("Failed to send Jira search request")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -220,18 +1026,7 @@ impl JiraClient {
             .await
             .context("Failed to parse Jira search response")?;
 
-        let assigned_issues: Vec<AssignedIssue> = search_response
-            .issues
-            .into_iter()
-            .map(|issue| AssignedIssue {
-                key: issue.key,
-                summary: issue.fields.summary,
-            })
-            .collect();
-
-        log::info!("Fetched {} assigned issues from Jira", assigned_issues.len());
-
-        Ok(assigned_issues)
+        Ok(search_response.issues)
     }
 
     /// Get assigned issues with caching
@@ -259,26 +1054,857 @@ impl JiraClient {
 
         // Update cache
         {
-            let mut cache = self.assigned_issues_cache.write().await;
-            *cache = Some(AssignedIssuesCache {
+            let cached = AssignedIssuesCache {
+                base_url: self.base_url.clone(),
+                email: self.email.clone(),
                 issues: issues.clone(),
                 cached_at: Utc::now(),
-            });
+            };
+            self.save_cache_file(&cached);
+            *self.assigned_issues_cache.write().await = Some(cached);
         }
 
         Ok(issues)
     }
 
-    /// Check if a specific issue is assigned to the current user
-    pub async fn is_assigned_to_me(&self, issue_key: &str) -> Result<bool> {
-        let assigned_issues = self.get_assigned_issues().await?;
-        Ok(assigned_issues.iter().any(|i| i.key == issue_key))
+    /// The subset of `keys` assigned to the current user, checked against one read of the
+    /// assigned issues (cached or fetched) however many keys there are
+    pub async fn filter_assigned(&self, keys: &[String]) -> Result<HashSet<String>> {
+        let assigned: HashSet<String> = self
+            .get_assigned_issues()
+            .await?
+            .into_iter()
+            .map(|issue| issue.key)
+            .collect();
+        Ok(keys
+            .iter()
+            .filter(|key| assigned.contains(*key))
+            .cloned()
+            .collect())
     }
 
     /// Clear the assigned issues cache (useful for testing or manual refresh)
     pub async fn clear_cache(&self) {
         let mut cache = self.assigned_issues_cache.write().await;
         *cache = None;
+        if let Some(path) = &self.cache_file {
+            let _ = std::fs::remove_file(path);
+        }
         log::debug!("Cleared assigned issues cache");
     }
 }
+
+/// JQL for `search_issues`, matching words of the summary that start with `query`
+fn search_jql(query: &str) -> String {
+    format!(
+        "summary ~ \"{}*\" ORDER BY updated DESC",
+        escape_text_search(query.trim())
+    )
+}
+
+/// Escape `text` for a quoted JQL text search: Lucene's reserved characters get a
+/// backslash, itself escaped for the JQL string, and quotes can't end the literal
+fn escape_text_search(text: &str) -> String {
+    const RESERVED: &str = "+-&|!(){}[]^\"~*?:\\/";
+
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if RESERVED.contains(c) {
+            escaped.push_str("\\\\");
+        }
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `Retry-After` in seconds; Jira doesn't send the HTTP-date form
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Format a worklog start time the way Jira expects (`yyyy-MM-ddTHH:mm:ss.SSSZ`, e.g.
+/// `2024-05-01T09:00:00.000+0200`), in `timezone` or the system zone when `None`
+fn format_started(timestamp: DateTime<Utc>, timezone: Option<Tz>) -> String {
+    format_in_zone(timestamp, timezone, "%Y-%m-%dT%H:%M:%S%.3f%z")
+}
+
+/// Start time in `timezone` without an offset, as Tempo expects
+fn format_started_local(timestamp: DateTime<Utc>, timezone: Option<Tz>) -> String {
+    format_in_zone(timestamp, timezone, "%Y-%m-%dT%H:%M:%S%.3f")
+}
+
+fn format_in_zone(timestamp: DateTime<Utc>, timezone: Option<Tz>, format: &str) -> String {
+    match timezone {
+        Some(tz) => timestamp.with_timezone(&tz).format(format).to_string(),
+        None => timestamp.with_timezone(&Local).format(format).to_string(),
+    }
+}
+
+/// Replace `{name}` placeholders with their values in a single pass.
+/// Unknown placeholders are kept as written so typos stay visible.
+fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                match vars.iter().find(|(key, _)| *key == name) {
+                    Some((_, value)) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + end + 2]),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_jql_escapes_quotes() {
+        assert_eq!(
+            search_jql(r#"fix "login" \ page"#),
+            r#"summary ~ "fix \\\"login\\\" \\\\ page*" ORDER BY updated DESC"#
+        );
+        // Lucene's reserved characters match literally instead of breaking the query
+        assert_eq!(
+            search_jql(" proj-12 (v2)? "),
+            r#"summary ~ "proj\\-12 \\(v2\\)\\?*" ORDER BY updated DESC"#
+        );
+        // A quote can't close the literal and smuggle in another clause
+        assert_eq!(
+            search_jql(r#"X" OR project = "SECRET"#),
+            r#"summary ~ "X\\\" OR project = \\\"SECRET*" ORDER BY updated DESC"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_looks_up_typed_key_separately() {
+        use axum::{
+            extract::{Path, Query},
+            http::StatusCode,
+            routing::get,
+            Json, Router,
+        };
+        use std::collections::HashMap;
+
+        let app = Router::new()
+            .route(
+                "/rest/api/3/search/jql",
+                get(|Query(params): Query<HashMap<String, String>>| async move {
+                    // A `key =` clause would make Jira reject the query
+                    if params["jql"].contains("key") {
+                        return Err(StatusCode::BAD_REQUEST);
+                    }
+                    let issue =
+                        |key: &str| serde_json::json!({ "key": key, "fields": { "summary": key } });
+                    Ok(Json(serde_json::json!({
+                        "issues": [issue("PROJ-7"), issue("PROJ-12")],
+                        "isLast": true
+                    })))
+                }),
+            )
+            .route(
+                "/rest/api/3/issue/:key",
+                get(|Path(key): Path<String>| async move {
+                    if key != "PROJ-12" {
+                        let error =
+                            serde_json::json!({ "errorMessages": ["Issue does not exist"] });
+                        return (StatusCode::NOT_FOUND, Json(error));
+                    }
+                    let issue = serde_json::json!({ "key": key, "fields": { "summary": "Login" } });
+                    (StatusCode::OK, Json(issue))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client = JiraClient::new(base_url, "dev@example.com".to_string(), "token".to_string())
+            .with_search_api(JiraSearchApi::Jql);
+
+        let keys = |issues: Vec<AssignedIssue>| -> Vec<String> {
+            issues.into_iter().map(|issue| issue.key).collect()
+        };
+        assert_eq!(
+            keys(client.search_issues("proj-12").await.unwrap()),
+            vec!["PROJ-12", "PROJ-7"]
+        );
+        // A key that doesn't exist still gets the summary matches
+        assert_eq!(
+            keys(client.search_issues("NOPE-1").await.unwrap()),
+            vec!["PROJ-7", "PROJ-12"]
+        );
+    }
+
+    #[test]
+    fn test_delete_worklog_url() {
+        let client = JiraClient::new(
+            "https://example.atlassian.net".to_string(),
+            "dev@example.com".to_string(),
+            "token".to_string(),
+        );
+        assert_eq!(
+            client.worklog_url("PROJ-42", "10001"),
+            "https://example.atlassian.net/rest/api/3/issue/PROJ-42/worklog/10001"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_sends_nothing() {
+        use axum::{routing::any, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = Router::new().fallback(any(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { "{}" }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client = JiraClient::new(
+            format!("http://{}", addr),
+            "dev@example.com".to_string(),
+            "token".to_string(),
+        )
+        .with_dry_run(true);
+        let activity = Activity {
+            timestamp: Utc::now(),
+            duration_secs: 600,
+            window_title: "PROJ-42 main.rs".to_string(),
+            app_name: "Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        };
+
+        client.log_work("PROJ-42", &activity).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_worklog_is_retried_after_retry_after() {
+        use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = Router::new().route(
+            "/rest/api/3/issue/:key/worklog",
+            post(move || async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    (StatusCode::TOO_MANY_REQUESTS, [("Retry-After", "1")]).into_response()
+                } else {
+                    (
+                        StatusCode::CREATED,
+                        Json(serde_json::json!({ "id": "10001" })),
+                    )
+                        .into_response()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client = JiraClient::new(
+            format!("http://{}", addr),
+            "dev@example.com".to_string(),
+            "token".to_string(),
+        )
+        .with_rate_limit(100.0);
+        let activity = Activity {
+            timestamp: Utc::now(),
+            duration_secs: 600,
+            window_title: "PROJ-42 main.rs".to_string(),
+            app_name: "Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        };
+
+        let started = std::time::Instant::now();
+        let id = client.log_work("PROJ-42", &activity).await.unwrap();
+
+        assert_eq!(id, "10001");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_work_type_attribute_in_payload() {
+        let client = JiraClient::new(
+            "https://example.atlassian.net".to_string(),
+            "dev@example.com".to_string(),
+            "token".to_string(),
+        )
+        .with_worklog_attributes(
+            "workType".to_string(),
+            BTreeMap::from([(WorkType::Review, "Code Review".to_string())]),
+        );
+
+        let worklog = WorklogEntry {
+            comment: "Reviewed the login fix".to_string(),
+            time_spent_seconds: 900,
+            started: "2024-05-01T09:00:00.000+0000".to_string(),
+            properties: client.worklog_properties(WorkType::normalize("code review")),
+        };
+        let json = serde_json::to_value(&worklog).unwrap();
+        assert_eq!(
+            json["properties"],
+            serde_json::json!([{ "key": "workType", "value": "Code Review" }])
+        );
+
+        // Unmapped and unknown types send no properties at all
+        assert!(client.worklog_properties(Some(WorkType::Coding)).is_empty());
+        assert!(client.worklog_properties(None).is_empty());
+        let worklog = WorklogEntry {
+            properties: Vec::new(),
+            ..worklog
+        };
+        assert!(serde_json::to_value(&worklog)
+            .unwrap()
+            .get("properties")
+            .is_none());
+    }
+
+    #[test]
+    fn test_tempo_payload() {
+        let client = JiraClient::new(
+            "https://jira.example.com".to_string(),
+            "dev@example.com".to_string(),
+            "token".to_string(),
+        )
+        .with_timezone(Some(chrono_tz::Europe::Berlin))
+        .with_worklog_attributes(
+            "_WorkType_".to_string(),
+            BTreeMap::from([(WorkType::Coding, "Development".to_string())]),
+        );
+        let activity = Activity {
+            timestamp: "2024-05-01T07:30:00Z".parse().unwrap(),
+            duration_secs: 1800,
+            window_title: "PROJ-42 main.rs".to_string(),
+            app_name: "Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        };
+
+        let worklog = client.tempo_worklog(
+            "abc".to_string(),
+            "PROJ-42",
+            &activity,
+            "Fixed login".to_string(),
+            Some(WorkType::Coding),
+        );
+        assert_eq!(
+            serde_json::to_value(&worklog).unwrap(),
+            serde_json::json!({
+                "authorAccountId": "abc",
+                "originTaskId": "PROJ-42",
+                "comment": "Fixed login",
+                "started": "2024-05-01T09:30:00.000",
+                "timeSpentSeconds": 1800,
+                "attributes": [{ "key": "_WorkType_", "value": "Development" }]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tempo_flag_routes_worklogs_to_tempo() {
+        use axum::{
+            routing::{get, post},
+            Json, Router,
+        };
+        use std::sync::Mutex;
+
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let (core, tempo) = (hits.clone(), hits.clone());
+        let app = Router::new()
+            .route(
+                "/rest/api/3/myself",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "accountId": "abc",
+                        "emailAddress": "dev@example.com",
+                        "displayName": "Dev"
+                    }))
+                }),
+            )
+            .route(
+                "/rest/api/3/issue/:key/worklog",
+                post(move || async move {
+                    core.lock().unwrap().push("core".to_string());
+                    Json(serde_json::json!({ "id": "10001" }))
+                }),
+            )
+            .route(
+                "/rest/tempo-timesheets/4/worklogs",
+                post(move |Json(body): Json<serde_json::Value>| async move {
+                    tempo
+                        .lock()
+                        .unwrap()
+                        .push(format!("tempo:{}", body["authorAccountId"]));
+                    Json(serde_json::json!([{ "tempoWorklogId": 7, "originId": 10002 }]))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let activity = Activity {
+            timestamp: Utc::now(),
+            duration_secs: 600,
+            window_title: "PROJ-42 main.rs".to_string(),
+            app_name: "Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        };
+        for use_tempo in [false, true] {
+            let client = JiraClient::new(
+                format!("http://{}", addr),
+                "dev@example.com".to_string(),
+                "token".to_string(),
+            )
+            .with_tempo(use_tempo);
+            let id = client.log_work("PROJ-42", &activity).await.unwrap();
+            assert_eq!(id, if use_tempo { "10002" } else { "10001" });
+        }
+
+        assert_eq!(*hits.lock().unwrap(), vec!["core", "tempo:\"abc\""]);
+    }
+
+    #[tokio::test]
+    async fn test_only_a_confirmed_missing_issue_is_not_found() {
+        use axum::{
+            extract::Path,
+            http::StatusCode,
+            routing::{get, post},
+            Json, Router,
+        };
+
+        // Worklog endpoints all 404, as with a wrong base path or Tempo not installed
+        let app = Router::new()
+            .route(
+                "/rest/api/3/myself",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "accountId": "abc",
+                        "emailAddress": "dev@example.com",
+                        "displayName": "Dev"
+                    }))
+                }),
+            )
+            .route(
+                "/rest/api/3/issue/:key/worklog",
+                post(|| async { StatusCode::NOT_FOUND }),
+            )
+            .route(
+                "/rest/tempo-timesheets/4/worklogs",
+                post(|| async { StatusCode::NOT_FOUND }),
+            )
+            .route(
+                "/rest/api/3/issue/:key",
+                get(|Path(key): Path<String>| async move {
+                    if key == "GONE-1" {
+                        let error =
+                            serde_json::json!({ "errorMessages": ["Issue does not exist"] });
+                        return (StatusCode::NOT_FOUND, Json(error));
+                    }
+                    (StatusCode::OK, Json(serde_json::json!({ "key": key })))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let activity = Activity {
+            timestamp: Utc::now(),
+            duration_secs: 600,
+            window_title: "main.rs".to_string(),
+            app_name: "Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        };
+        let client = |use_tempo: bool| {
+            JiraClient::new(
+                format!("http://{}", addr),
+                "dev@example.com".to_string(),
+                "token".to_string(),
+            )
+            .with_tempo(use_tempo)
+        };
+
+        let err = client(false)
+            .log_work("GONE-1", &activity)
+            .await
+            .unwrap_err();
+        assert!(err.is::<IssueNotFound>());
+        let err = client(false)
+            .log_work("PROJ-1", &activity)
+            .await
+            .unwrap_err();
+        assert!(!err.is::<IssueNotFound>());
+        let err = client(true)
+            .log_work("GONE-1", &activity)
+            .await
+            .unwrap_err();
+        assert!(!err.is::<IssueNotFound>());
+        assert!(err.to_string().contains("Tempo API not found"));
+    }
+
+    #[test]
+    fn test_render_comment_placeholders() {
+        let client = JiraClient::new(
+            "https://example.atlassian.net".to_string(),
+            "dev@example.com".to_string(),
+            "token".to_string(),
+        )
+        .with_comment_template("[{issue}] {work_type}: {window} ({app}, {date})".to_string());
+        let activity = Activity {
+            timestamp: "2024-05-01T09:00:00Z".parse().unwrap(),
+            duration_secs: 600,
+            window_title: "main.rs".to_string(),
+            app_name: "Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        };
+
+        assert_eq!(
+            client.render_comment("PROJ-1", &activity, Some("coding")),
+            "[PROJ-1] coding: main.rs (Code, 2024-05-01)"
+        );
+        // Missing values render empty
+        assert_eq!(
+            client.render_comment("PROJ-1", &activity, None),
+            "[PROJ-1] : main.rs (Code, 2024-05-01)"
+        );
+    }
+
+    #[test]
+    fn test_started_in_configured_timezone() {
+        // 23:30 UTC is already the next day in Tokyo and still the same day in New York
+        let timestamp: DateTime<Utc> = "2024-05-01T23:30:00Z".parse().unwrap();
+
+        assert_eq!(
+            format_started(timestamp, Some(chrono_tz::Asia::Tokyo)),
+            "2024-05-02T08:30:00.000+0900"
+        );
+        assert_eq!(
+            format_started(timestamp, Some(chrono_tz::America::New_York)),
+            "2024-05-01T19:30:00.000-0400"
+        );
+        assert_eq!(
+            format_started(timestamp, Some(chrono_tz::UTC)),
+            "2024-05-01T23:30:00.000+0000"
+        );
+
+        let pattern =
+            regex::Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\.\d{3}[+-]\d{4}$").unwrap();
+        assert!(pattern.is_match(&format_started(timestamp, None)));
+    }
+
+    #[test]
+    fn test_render_template_keeps_unknown_placeholders() {
+        let vars = [("app", "{window}"), ("window", "x")];
+        // Values are not re-expanded, unknown and unterminated placeholders are kept
+        assert_eq!(
+            render_template("{app} {nope} {window", &vars),
+            "{window} {nope} {window"
+        );
+        assert_eq!(render_template("no placeholders", &vars), "no placeholders");
+    }
+
+    #[test]
+    fn test_issue_key_denylist_without_project_keys() {
+        let regex = build_issue_key_regex(&[]);
+        assert_eq!(detect_issue_key(&regex, "Encoding: UTF-8", true), None);
+        assert_eq!(
+            detect_issue_key(&regex, "UTF-8 - PROJ-42 fix parser", true),
+            Some("PROJ-42".to_string())
+        );
+    }
+
+    #[test]
+    fn test_issue_key_project_allowlist() {
+        let regex = build_issue_key_regex(&["proj".to_string(), "ENG".to_string()]);
+        assert_eq!(
+            detect_issue_key(&regex, "UTF-8 HTTP-2 COVID-19", false),
+            None
+        );
+        assert_eq!(
+            detect_issue_key(&regex, "PROJ-42: Fix login", false),
+            Some("PROJ-42".to_string())
+        );
+        assert_eq!(detect_issue_key(&regex, "OTHER-7 and XENG-3", false), None);
+        assert_eq!(
+            detect_issue_key(&regex, "ENG-3 review", false),
+            Some("ENG-3".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jql_search_follows_page_tokens() {
+        use axum::{extract::Query, routing::get, Json, Router};
+        use std::collections::HashMap;
+
+        let app = Router::new().route(
+            "/rest/api/3/search/jql",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                let issue =
+                    |key: &str| serde_json::json!({ "key": key, "fields": { "summary": key } });
+                Json(match params.get("nextPageToken").map(String::as_str) {
+                    None => serde_json::json!({
+                        "issues": [issue("PROJ-1"), issue("PROJ-2")],
+                        "nextPageToken": "page-2",
+                        "isLast": false
+                    }),
+                    Some("page-2") => serde_json::json!({
+                        "issues": [issue("PROJ-3")],
+                        "nextPageToken": "page-3",
+                        "isLast": false
+                    }),
+                    Some(_) => serde_json::json!({ "issues": [issue("PROJ-4")], "isLast": true }),
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client = JiraClient::new(base_url, "dev@example.com".to_string(), "token".to_string())
+            .with_search_api(JiraSearchApi::Jql);
+
+        let keys: Vec<String> = client
+            .search("assignee = currentUser()", 100)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|issue| issue.key)
+            .collect();
+        assert_eq!(keys, vec!["PROJ-1", "PROJ-2", "PROJ-3", "PROJ-4"]);
+
+        // Stops paging once enough issues are in
+        assert_eq!(
+            client
+                .search("assignee = currentUser()", 3)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_projects_parses_search_pages() {
+        use axum::{extract::Query, routing::get, Json, Router};
+        use std::collections::HashMap;
+
+        let app = Router::new().route(
+            "/rest/api/3/project/search",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                let project = |id: &str, key: &str, name: &str| {
+                    serde_json::json!({
+                        "self": format!("https://example.atlassian.net/rest/api/3/project/{}", id),
+                        "id": id,
+                        "key": key,
+                        "name": name,
+                        "projectTypeKey": "software",
+                        "simplified": false
+                    })
+                };
+                Json(match params.get("startAt").map(String::as_str) {
+                    Some("0") => serde_json::json!({
+                        "startAt": 0,
+                        "maxResults": 2,
+                        "total": 3,
+                        "isLast": false,
+                        "values": [project("10000", "OPS", "Operations"), project("10001", "PROJ", "Product")]
+                    }),
+                    _ => serde_json::json!({
+                        "startAt": 2,
+                        "maxResults": 2,
+                        "total": 3,
+                        "isLast": true,
+                        "values": [project("10002", "WEB", "Website")]
+                    }),
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client = JiraClient::new(base_url, "dev@example.com".to_string(), "token".to_string());
+        let projects = client.list_projects().await.unwrap();
+
+        let listed: Vec<(&str, &str)> = projects
+            .iter()
+            .map(|project| (project.key.as_str(), project.name.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("OPS", "Operations"),
+                ("PROJ", "Product"),
+                ("WEB", "Website")
+            ]
+        );
+
+        assert_eq!(
+            select_projects("proj, web PROJ", &projects).unwrap(),
+            vec!["PROJ", "WEB"]
+        );
+        assert!(select_projects("", &projects).unwrap().is_empty());
+        assert!(select_projects("PROJ, NOPE", &projects).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_filter_assigned() {
+        use axum::{routing::get, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let searches = Arc::new(AtomicUsize::new(0));
+        let counter = searches.clone();
+        let app = Router::new()
+            .route(
+                "/rest/api/3/myself",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "accountId": "abc",
+                        "emailAddress": "dev@example.com",
+                        "displayName": "Dev"
+                    }))
+                }),
+            )
+            .route(
+                "/rest/api/3/search",
+                get(move || async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Json(serde_json::json!({
+                        "issues": [
+                            { "key": "PROJ-1", "fields": { "summary": "Fix login" } },
+                            { "key": "PROJ-2", "fields": { "summary": "Add search" } }
+                        ],
+                        "total": 2
+                    }))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        let client = JiraClient::new(base_url, "dev@example.com".to_string(), "token".to_string());
+
+        let keys: Vec<String> = ["PROJ-1", "OTHER-3", "PROJ-2", "PROJ-1", "PROJ-22"]
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        let assigned = client.filter_assigned(&keys).await.unwrap();
+        let expected: HashSet<String> = ["PROJ-1".to_string(), "PROJ-2".to_string()].into();
+        assert_eq!(assigned, expected);
+        assert!(client.filter_assigned(&[]).await.unwrap().is_empty());
+
+        // Both calls were answered from one search
+        assert_eq!(searches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_assigned_issues_cache_survives_restart() {
+        use axum::{routing::get, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let (myself, search) = (requests.clone(), requests.clone());
+        let app = Router::new()
+            .route(
+                "/rest/api/3/myself",
+                get(move || async move {
+                    myself.fetch_add(1, Ordering::SeqCst);
+                    Json(serde_json::json!({
+                        "accountId": "abc",
+                        "emailAddress": "dev@example.com",
+                        "displayName": "Dev"
+                    }))
+                }),
+            )
+            .route(
+                "/rest/api/3/search",
+                get(move || async move {
+                    search.fetch_add(1, Ordering::SeqCst);
+                    Json(serde_json::json!({
+                        "issues": [{ "key": "PROJ-1", "fields": { "summary": "Fix login" } }],
+                        "total": 1
+                    }))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache_file = dir.path().join("assigned_issues_cache.json");
+        let client = |cache_duration_secs| {
+            JiraClient::new(
+                base_url.clone(),
+                "dev@example.com".to_string(),
+                "token".to_string(),
+            )
+            .with_cache_duration(cache_duration_secs)
+            .with_cache_file(cache_file.clone())
+        };
+
+        assert_eq!(client(3600).get_assigned_issues().await.unwrap().len(), 1);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // A fresh client (e.g. after a restart) reads the still-valid cache from disk
+        let issues = client(3600).get_assigned_issues().await.unwrap();
+        assert_eq!(issues[0].key, "PROJ-1");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // An expired cache is ignored
+        client(0).get_assigned_issues().await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+}
@@ -1,9 +1,15 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use crate::database::StoredActivity;
+use crate::issue_key;
+use crate::redact::{default_redactor, Redactor};
+use crate::work_type::WorkType;
 
 /// Jira issue information for the LLM context
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,16 +28,20 @@ pub struct ActivityForAnalysis {
     pub window_title: String,
     #[serde(skip_serializing_if = "String::is_empty")]
     pub ocr_sample: String, // Limited sample of description to avoid sending too much data
+    /// Category from the user's keyword rules, a hint for grouping
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
 }
 
-impl From<&StoredActivity> for ActivityForAnalysis {
-    fn from(activity: &StoredActivity) -> Self {
-        // Limit OCR text to first 500 chars to avoid overwhelming the LLM
-        let ocr_sample = if activity.description.len() > 500 {
-            format!("{}...", &activity.description[..500])
-        } else {
-            activity.description.clone()
-        };
+impl ActivityForAnalysis {
+    /// Prepare `activity` for the LLM, masking its OCR text with `redactor`
+    pub fn redacted(activity: &StoredActivity, redactor: &Redactor) -> Self {
+        // Mask secrets (also in rows stored before redaction was configured), collapse
+        // OCR whitespace, then limit to 500 chars to avoid overwhelming the LLM
+        let ocr_sample = truncate_chars(
+            &collapse_whitespace(&redactor.redact(&activity.description)),
+            500,
+        );
 
         Self {
             id: activity.id,
@@ -40,10 +50,37 @@ impl From<&StoredActivity> for ActivityForAnalysis {
             app_name: activity.app_name.clone(),
             window_title: activity.window_title.clone(),
             ocr_sample,
+            category: activity.category.clone(),
         }
     }
 }
 
+impl From<&StoredActivity> for ActivityForAnalysis {
+    fn from(activity: &StoredActivity) -> Self {
+        Self::redacted(activity, default_redactor())
+    }
+}
+
+/// Collapse runs of whitespace (including newlines) into single spaces
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Truncate to at most `max` characters without splitting a UTF-8 character, ending
+/// with "..." (counted in `max`) when text was cut off
+pub(crate) fn truncate_chars(s: &str, max: usize) -> String {
+    const ELLIPSIS: &str = "...";
+
+    if s.chars().nth(max).is_none() {
+        return s.to_string();
+    }
+    if max < ELLIPSIS.len() {
+        return s.chars().take(max).collect();
+    }
+    let kept: String = s.chars().take(max - ELLIPSIS.len()).collect();
+    format!("{}{}", kept, ELLIPSIS)
+}
+
 /// Request payload sent to corporate LLM API
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LLMAnalysisRequest {
@@ -53,6 +90,22 @@ pub struct LLMAnalysisRequest {
     pub task: TaskInstructions,
 }
 
+impl LLMAnalysisRequest {
+    /// Copy of the request without OCR samples, for keeping alongside the analysis
+    pub fn without_ocr(&self) -> Self {
+        let mut request = self.clone();
+        for activity in request
+            .activities
+            .billable
+            .iter_mut()
+            .chain(request.activities.micro.iter_mut())
+        {
+            activity.ocr_sample.clear();
+        }
+        request
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserContext {
     pub email: String,
@@ -96,6 +149,71 @@ pub struct AnalysisResult {
     pub red_flags: Vec<String>,
 }
 
+/// An analysis that parsed but breaks the response contract, e.g. a confidence of 1.4 or
+/// an activity id that wasn't in the request; find it with
+/// `err.downcast_ref::<InvalidAnalysis>()`
+#[derive(Debug, thiserror::Error)]
+#[error("LLM response failed validation: {}", .0.join("; "))]
+pub struct InvalidAnalysis(pub Vec<String>);
+
+impl AnalysisResult {
+    /// Check the invariants serde can't: confidences within 0..=1, issue keys shaped like
+    /// `PROJECT-NUMBER`, and activity ids among `known_ids` (those sent in the request).
+    /// Times can't be negative as they are unsigned.
+    pub fn validate(&self, known_ids: &HashSet<i64>) -> Result<(), InvalidAnalysis> {
+        let mut violations = Vec::new();
+        let unknown_ids = |ids: &[i64]| -> Vec<String> {
+            ids.iter()
+                .filter(|id| !known_ids.contains(id))
+                .map(|id| id.to_string())
+                .collect()
+        };
+
+        if !(0.0..=1.0).contains(&self.confidence) {
+            violations.push(format!(
+                "confidence {} is not between 0 and 1",
+                self.confidence
+            ));
+        }
+        for (index, issue) in self.issues.iter().enumerate() {
+            let name = format!("issues[{}] ({})", index, issue.key);
+            if !issue_key::is_issue_key(&issue.key) {
+                violations.push(format!(
+                    "issues[{}] key '{}' is not an issue key",
+                    index, issue.key
+                ));
+            }
+            if !(0.0..=1.0).contains(&issue.confidence) {
+                violations.push(format!(
+                    "{} confidence {} is not between 0 and 1",
+                    name, issue.confidence
+                ));
+            }
+            let unknown = unknown_ids(&issue.activities_included);
+            if !unknown.is_empty() {
+                violations.push(format!(
+                    "{} includes activities not in the request: {}",
+                    name,
+                    unknown.join(", ")
+                ));
+            }
+        }
+        let unknown = unknown_ids(&self.unmatched.activities);
+        if !unknown.is_empty() {
+            violations.push(format!(
+                "unmatched lists activities not in the request: {}",
+                unknown.join(", ")
+            ));
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidAnalysis(violations))
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IssueMatch {
     pub key: String,
@@ -106,6 +224,14 @@ pub struct IssueMatch {
     pub confidence: f64,
 }
 
+/// Assigned issue the LLM placed a single activity in (see `LLMAnalyzer::suggest_issue`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct IssueSuggestion {
+    pub key: String,
+    /// 0.0-1.0 as reported by the LLM; `None` when it didn't say
+    pub confidence: Option<f64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnmatchedActivities {
     pub total_time_secs: u64,
@@ -113,12 +239,138 @@ pub struct UnmatchedActivities {
     pub likely_reason: String,
 }
 
+/// Split billable and micro activities into chunks of at most `batch_size` activities each.
+/// Billable activities fill the chunks first, followed by micro activities.
+/// A `batch_size` of 0 disables chunking.
+pub fn chunk_activities(
+    billable: Vec<StoredActivity>,
+    micro: Vec<StoredActivity>,
+    batch_size: usize,
+) -> Vec<(Vec<StoredActivity>, Vec<StoredActivity>)> {
+    if batch_size == 0 || billable.len() + micro.len() <= batch_size {
+        return vec![(billable, micro)];
+    }
+
+    let mut chunks = Vec::new();
+    let mut current: (Vec<StoredActivity>, Vec<StoredActivity>) = (Vec::new(), Vec::new());
+
+    for activity in billable {
+        current.0.push(activity);
+        if current.0.len() + current.1.len() == batch_size {
+            chunks.push(std::mem::take(&mut current));
+        }
+    }
+
+    for activity in micro {
+        current.1.push(activity);
+        if current.0.len() + current.1.len() == batch_size {
+            chunks.push(std::mem::take(&mut current));
+        }
+    }
+
+    if !current.0.is_empty() || !current.1.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Merge partial analyses of a chunked session into a single response.
+/// Issues are merged by key in order of first appearance: times are summed,
+/// activity ids concatenated and confidence weighted by time.
+pub fn merge_analyses(responses: Vec<LLMAnalysisResponse>) -> LLMAnalysisResponse {
+    let mut issues: Vec<IssueMatch> = Vec::new();
+    let mut issue_index: HashMap<String, usize> = HashMap::new();
+    let mut unmatched = UnmatchedActivities {
+        total_time_secs: 0,
+        activities: Vec::new(),
+        likely_reason: String::new(),
+    };
+    let mut reasons: Vec<String> = Vec::new();
+    let mut red_flags: Vec<String> = Vec::new();
+    let mut total_productive_time_secs = 0;
+    let mut weighted_confidence = 0.0;
+    let mut confidence_sum = 0.0;
+    let mut micro_activities_merged = false;
+    let count = responses.len();
+
+    for response in responses {
+        let analysis = response.analysis;
+
+        total_productive_time_secs += analysis.total_productive_time_secs;
+        weighted_confidence += analysis.confidence * analysis.total_productive_time_secs as f64;
+        confidence_sum += analysis.confidence;
+        micro_activities_merged |= analysis.micro_activities_merged;
+
+        for issue in analysis.issues {
+            match issue_index.get(&issue.key) {
+                Some(&idx) => {
+                    let existing = &mut issues[idx];
+                    let combined_time = existing.total_time_secs + issue.total_time_secs;
+                    if combined_time > 0 {
+                        existing.confidence = (existing.confidence
+                            * existing.total_time_secs as f64
+                            + issue.confidence * issue.total_time_secs as f64)
+                            / combined_time as f64;
+                    }
+                    existing.total_time_secs = combined_time;
+                    existing
+                        .activities_included
+                        .extend(issue.activities_included);
+                }
+                None => {
+                    issue_index.insert(issue.key.clone(), issues.len());
+                    issues.push(issue);
+                }
+            }
+        }
+
+        unmatched.total_time_secs += analysis.unmatched.total_time_secs;
+        unmatched.activities.extend(analysis.unmatched.activities);
+        if !analysis.unmatched.likely_reason.is_empty()
+            && !reasons.contains(&analysis.unmatched.likely_reason)
+        {
+            reasons.push(analysis.unmatched.likely_reason);
+        }
+
+        for flag in analysis.red_flags {
+            if !red_flags.contains(&flag) {
+                red_flags.push(flag);
+            }
+        }
+    }
+
+    unmatched.likely_reason = reasons.join("; ");
+
+    let confidence = if total_productive_time_secs > 0 {
+        weighted_confidence / total_productive_time_secs as f64
+    } else if count > 0 {
+        confidence_sum / count as f64
+    } else {
+        0.0
+    };
+
+    LLMAnalysisResponse {
+        analysis: AnalysisResult {
+            total_productive_time_secs,
+            confidence,
+            issues,
+            unmatched,
+            micro_activities_merged,
+            red_flags,
+        },
+    }
+}
+
 /// LLM analyzer client for corporate API
 pub struct LLMAnalyzer {
     endpoint: String,
     api_key: String,
     timeout: Duration,
     client: reqwest::Client,
+    extra_rules: Vec<String>,
+    summary_max_chars: usize,
+    redactor: Redactor,
 }
 
 impl LLMAnalyzer {
@@ -133,10 +385,91 @@ impl LLMAnalyzer {
             api_key,
             timeout: Duration::from_secs(timeout_secs),
             client,
+            extra_rules: Vec::new(),
+            summary_max_chars: 200,
+            redactor: Redactor::default(),
         })
     }
 
-    /// Analyze a batch of activities using the corporate LLM API
+    /// Use a shared client (see `http::build_client`); requests still time out after
+    /// the analyzer's own timeout
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Customize the prompt rules sent with every batch analysis
+    pub fn with_rules(mut self, extra_rules: Vec<String>, summary_max_chars: usize) -> Self {
+        self.extra_rules = extra_rules;
+        self.summary_max_chars = summary_max_chars;
+        self
+    }
+
+    /// Mask matches of `patterns` in OCR text sent to the LLM instead of the defaults
+    pub fn with_redaction_patterns(mut self, patterns: &[String]) -> Result<Self> {
+        self.redactor = Redactor::new(patterns)?;
+        Ok(self)
+    }
+
+    /// Check that the endpoint is reachable and accepts the API key.
+    /// Sends an authenticated HEAD so no analysis is billed; only 401/403 or a
+    /// transport error count as unhealthy, since the endpoint may not allow HEAD.
+    pub async fn health_check(&self) -> Result<bool> {
+        match self
+            .client
+            .head(&self.endpoint)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .timeout(self.timeout)
+            .send()
+            .await
+        {
+            Ok(response) => {
+                let status = response.status();
+                if status == reqwest::StatusCode::UNAUTHORIZED
+                    || status == reqwest::StatusCode::FORBIDDEN
+                {
+                    log::warn!("LLM API rejected the configured API key ({})", status);
+                    Ok(false)
+                } else {
+                    Ok(true)
+                }
+            }
+            Err(e) => {
+                log::warn!("LLM API unreachable: {}", e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Build the task instructions, merging built-in rules with configured ones
+    fn task_instructions(&self) -> TaskInstructions {
+        let mut rules = vec![
+            "ONLY match to assigned_issues list".to_string(),
+            "Combine micro-activities with related billable activities when logical".to_string(),
+            format!(
+                "Generate summaries max {} characters",
+                self.summary_max_chars
+            ),
+            "Return confidence scores (0-1)".to_string(),
+            "Flag unmatched activities (possible personal/other client work)".to_string(),
+            "Calculate actual productive time per issue".to_string(),
+            "Use an activity's category, when given, as a hint to what kind of work it was"
+                .to_string(),
+            format!(
+                "Set work_type to one of: {}",
+                WorkType::ALL.map(|t| t.as_str()).join(", ")
+            ),
+        ];
+        rules.extend(self.extra_rules.iter().cloned());
+
+        TaskInstructions {
+            primary: "Analyze this work session. Group activities by issue, generate summaries, calculate productive time. ONLY match to assigned issues. Return grouped results.".to_string(),
+            rules,
+        }
+    }
+
+    /// Analyze a batch of activities using the corporate LLM API, returning the request
+    /// that was sent along with the response
     pub async fn analyze_batch(
         &self,
         user_email: String,
@@ -148,7 +481,7 @@ impl LLMAnalyzer {
         break_duration_secs: u64,
         billable_activities: Vec<StoredActivity>,
         micro_activities: Vec<StoredActivity>,
-    ) -> Result<LLMAnalysisResponse> {
+    ) -> Result<(LLMAnalysisRequest, LLMAnalysisResponse)> {
         let request = LLMAnalysisRequest {
             user: UserContext {
                 email: user_email,
@@ -164,24 +497,14 @@ impl LLMAnalyzer {
             activities: ActivitiesContext {
                 billable: billable_activities
                     .iter()
-                    .map(ActivityForAnalysis::from)
+                    .map(|a| ActivityForAnalysis::redacted(a, &self.redactor))
                     .collect(),
                 micro: micro_activities
                     .iter()
-                    .map(ActivityForAnalysis::from)
+                    .map(|a| ActivityForAnalysis::redacted(a, &self.redactor))
                     .collect(),
             },
-            task: TaskInstructions {
-                primary: "Analyze this work session. Group activities by issue, generate summaries, calculate productive time. ONLY match to assigned issues. Return grouped results.".to_string(),
-                rules: vec![
-                    "ONLY match to assigned_issues list".to_string(),
-                    "Combine micro-activities with related billable activities when logical".to_string(),
-                    "Generate summaries max 200 characters".to_string(),
-                    "Return confidence scores (0-1)".to_string(),
-                    "Flag unmatched activities (possible personal/other client work)".to_string(),
-                    "Calculate actual productive time per issue".to_string(),
-                ],
-            },
+            task: self.task_instructions(),
         };
 
         log::debug!(
@@ -194,6 +517,7 @@ impl LLMAnalyzer {
             .client
             .post(&self.endpoint)
             .header("Authorization", format!("Bearer {}", self.api_key))
+            .timeout(self.timeout)
             .header("Content-Type", "application/json")
             .json(&request)
             .send()
@@ -214,13 +538,20 @@ impl LLMAnalyzer {
             .await
             .context("Failed to parse LLM API response")?;
 
+        let sent_ids: HashSet<i64> = billable_activities
+            .iter()
+            .chain(&micro_activities)
+            .map(|a| a.id)
+            .collect();
+        llm_response.analysis.validate(&sent_ids)?;
+
         log::info!(
             "LLM analysis completed: {} issues matched, confidence: {:.2}",
             llm_response.analysis.issues.len(),
             llm_response.analysis.confidence
         );
 
-        Ok(llm_response)
+        Ok((request, llm_response))
     }
 
     /// Simple issue detection using LLM for a single activity
@@ -229,24 +560,28 @@ impl LLMAnalyzer {
         &self,
         activity: &StoredActivity,
         assigned_issues: &[AssignedIssue],
-    ) -> Result<Option<String>> {
+    ) -> Result<Option<IssueSuggestion>> {
         // Create a minimal request for single activity analysis
-        let activity_for_analysis = ActivityForAnalysis::from(activity);
+        let activity_for_analysis = ActivityForAnalysis::redacted(activity, &self.redactor);
 
         let request = serde_json::json!({
             "user": {
                 "assigned_issues": assigned_issues,
             },
             "activity": activity_for_analysis,
-            "task": "Identify which assigned issue this activity relates to. Return only the issue key or null if uncertain. Max 10 words."
+            "task": "Identify which assigned issue this activity relates to. Return JSON with \"issue_key\" (null if uncertain) and \"confidence\" (0.0-1.0)."
         });
 
-        log::debug!("Requesting LLM issue suggestion for activity: {}", activity.id);
+        log::debug!(
+            "Requesting LLM issue suggestion for activity: {}",
+            activity.id
+        );
 
         let response = self
             .client
             .post(&self.endpoint)
             .header("Authorization", format!("Bearer {}", self.api_key))
+            .timeout(self.timeout)
             .header("Content-Type", "application/json")
             .json(&request)
             .send()
@@ -268,8 +603,16 @@ impl LLMAnalyzer {
         if let Some(issue_key) = response_json.get("issue_key").and_then(|v| v.as_str()) {
             // Verify the suggested issue is in the assigned list
             if assigned_issues.iter().any(|i| i.key == issue_key) {
-                log::info!("LLM suggested issue: {}", issue_key);
-                return Ok(Some(issue_key.to_string()));
+                let confidence = response_json.get("confidence").and_then(|v| v.as_f64());
+                log::info!(
+                    "LLM suggested issue: {} (confidence {:?})",
+                    issue_key,
+                    confidence
+                );
+                return Ok(Some(IssueSuggestion {
+                    key: issue_key.to_string(),
+                    confidence,
+                }));
             } else {
                 log::warn!("LLM suggested non-assigned issue: {}", issue_key);
             }
@@ -295,11 +638,20 @@ mod tests {
             description: "Test description".to_string(),
             tier: crate::database::ActivityTier::Micro,
             logged_to_jira: false,
+            browser_url: None,
+            source: crate::database::ActivitySource::Auto,
+            issue_key: None,
+            attributed_issue: None,
+            attribution_source: None,
+            billable: true,
+            category: Some("review".to_string()),
         };
 
         let for_analysis = ActivityForAnalysis::from(&activity);
         assert_eq!(for_analysis.id, 1);
         assert_eq!(for_analysis.duration_secs, 300);
+        let json = serde_json::to_value(&for_analysis).unwrap();
+        assert_eq!(json["category"], "review");
     }
 
     #[test]
@@ -315,9 +667,341 @@ mod tests {
             description: long_text,
             tier: crate::database::ActivityTier::Micro,
             logged_to_jira: false,
+            browser_url: None,
+            source: crate::database::ActivitySource::Auto,
+            issue_key: None,
+            attributed_issue: None,
+            attribution_source: None,
+            billable: true,
+            category: None,
+        };
+
+        let for_analysis = ActivityForAnalysis::from(&activity);
+        assert!(for_analysis.ocr_sample.len() <= 500);
+    }
+
+    #[test]
+    fn test_ocr_truncation_multibyte() {
+        // Byte 500 falls inside a multi-byte character here
+        let text = format!("a{}", "é🚀".repeat(400));
+        let activity = StoredActivity {
+            id: 1,
+            session_id: 1,
+            timestamp: Utc::now(),
+            duration_secs: 300,
+            window_title: "Test".to_string(),
+            app_name: "Test App".to_string(),
+            description: text,
+            tier: crate::database::ActivityTier::Micro,
+            logged_to_jira: false,
+            browser_url: None,
+            source: crate::database::ActivitySource::Auto,
+            issue_key: None,
+            attributed_issue: None,
+            attribution_source: None,
+            billable: true,
+            category: None,
         };
 
         let for_analysis = ActivityForAnalysis::from(&activity);
-        assert!(for_analysis.ocr_sample.len() <= 503); // 500 + "..."
+        assert_eq!(for_analysis.ocr_sample.chars().count(), 500);
+        assert!(for_analysis.ocr_sample.ends_with("..."));
+    }
+
+    #[test]
+    fn test_ocr_whitespace_collapsed() {
+        assert_eq!(collapse_whitespace("  foo\n\n  bar\tbaz  "), "foo bar baz");
+        assert_eq!(truncate_chars("héllo", 10), "héllo");
+        assert_eq!(truncate_chars("héllo", 5), "héllo");
+        assert_eq!(truncate_chars("héllo", 4), "h...");
+        assert_eq!(truncate_chars("héllo", 2), "hé");
+    }
+
+    #[test]
+    fn test_custom_rules_in_request() {
+        let analyzer = LLMAnalyzer::new("http://localhost".to_string(), "key".to_string(), 5)
+            .unwrap()
+            .with_rules(vec!["Write summaries in past tense".to_string()], 120);
+
+        let request = LLMAnalysisRequest {
+            user: UserContext {
+                email: "dev@example.com".to_string(),
+                company: "Acme".to_string(),
+                assigned_issues: vec![],
+            },
+            session: SessionContext {
+                start: Utc::now(),
+                end: Utc::now(),
+                tracking_duration_secs: 0,
+                break_duration_secs: 0,
+            },
+            activities: ActivitiesContext {
+                billable: vec![],
+                micro: vec![],
+            },
+            task: analyzer.task_instructions(),
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        let rules: Vec<&str> = json["task"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r.as_str().unwrap())
+            .collect();
+
+        assert!(rules.contains(&"Write summaries in past tense"));
+        assert!(rules.contains(&"Generate summaries max 120 characters"));
+        assert!(rules.contains(&"ONLY match to assigned_issues list"));
+        assert!(rules.contains(&"Set work_type to one of: coding, review, meeting, docs, ops"));
+    }
+
+    fn issue(key: &str, secs: u64, ids: Vec<i64>, confidence: f64) -> IssueMatch {
+        IssueMatch {
+            key: key.to_string(),
+            total_time_secs: secs,
+            summary: format!("Work on {}", key),
+            work_type: "development".to_string(),
+            activities_included: ids,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_merge_analyses() {
+        let first = LLMAnalysisResponse {
+            analysis: AnalysisResult {
+                total_productive_time_secs: 3600,
+                confidence: 0.9,
+                issues: vec![
+                    issue("PROJ-1", 3000, vec![1, 2], 0.9),
+                    issue("PROJ-2", 600, vec![3], 0.8),
+                ],
+                unmatched: UnmatchedActivities {
+                    total_time_secs: 300,
+                    activities: vec![4],
+                    likely_reason: "personal browsing".to_string(),
+                },
+                micro_activities_merged: false,
+                red_flags: vec!["long idle gap".to_string()],
+            },
+        };
+        let second = LLMAnalysisResponse {
+            analysis: AnalysisResult {
+                total_productive_time_secs: 1200,
+                confidence: 0.5,
+                issues: vec![
+                    issue("PROJ-1", 1000, vec![5], 0.5),
+                    issue("PROJ-3", 200, vec![6], 0.7),
+                ],
+                unmatched: UnmatchedActivities {
+                    total_time_secs: 100,
+                    activities: vec![7],
+                    likely_reason: "personal browsing".to_string(),
+                },
+                micro_activities_merged: true,
+                red_flags: vec!["long idle gap".to_string(), "weekend work".to_string()],
+            },
+        };
+
+        let merged = merge_analyses(vec![first, second]).analysis;
+
+        let keys: Vec<&str> = merged.issues.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(keys, vec!["PROJ-1", "PROJ-2", "PROJ-3"]);
+        assert_eq!(merged.issues[0].total_time_secs, 4000);
+        assert_eq!(merged.issues[0].activities_included, vec![1, 2, 5]);
+        assert!((merged.issues[0].confidence - 0.8).abs() < 1e-9);
+        assert_eq!(merged.total_productive_time_secs, 4800);
+        assert!((merged.confidence - 0.8).abs() < 1e-9);
+        assert_eq!(merged.unmatched.total_time_secs, 400);
+        assert_eq!(merged.unmatched.activities, vec![4, 7]);
+        assert_eq!(merged.unmatched.likely_reason, "personal browsing");
+        assert!(merged.micro_activities_merged);
+        assert_eq!(merged.red_flags, vec!["long idle gap", "weekend work"]);
+    }
+
+    fn malformed_response() -> serde_json::Value {
+        serde_json::json!({
+            "analysis": {
+                "total_productive_time_secs": 1800,
+                "confidence": 1.4,
+                "issues": [
+                    {
+                        "key": "PROJ-1",
+                        "total_time_secs": 1200,
+                        "summary": "Work on PROJ-1",
+                        "work_type": "development",
+                        "activities_included": [1, 99],
+                        "confidence": -0.1
+                    },
+                    {
+                        "key": "misc",
+                        "total_time_secs": 600,
+                        "summary": "Other",
+                        "work_type": "development",
+                        "activities_included": [2],
+                        "confidence": 0.5
+                    }
+                ],
+                "unmatched": {"total_time_secs": 0, "activities": [42], "likely_reason": ""},
+                "micro_activities_merged": false,
+                "red_flags": []
+            }
+        })
+    }
+
+    #[test]
+    fn test_validate_lists_every_violation() {
+        let response: LLMAnalysisResponse = serde_json::from_value(malformed_response()).unwrap();
+        let known_ids: HashSet<i64> = [1, 2].into_iter().collect();
+
+        let error = response.analysis.validate(&known_ids).unwrap_err();
+        let message = error.to_string();
+        assert_eq!(error.0.len(), 5, "{}", message);
+        assert!(message.contains("confidence 1.4 is not between 0 and 1"));
+        assert!(message.contains("issues[0] (PROJ-1) confidence -0.1"));
+        assert!(message.contains("issues[0] (PROJ-1) includes activities not in the request: 99"));
+        assert!(message.contains("issues[1] key 'misc' is not an issue key"));
+        assert!(message.contains("unmatched lists activities not in the request: 42"));
+
+        let valid = AnalysisResult {
+            total_productive_time_secs: 600,
+            confidence: 1.0,
+            issues: vec![issue("PROJ-1", 600, vec![1, 2], 0.0)],
+            unmatched: UnmatchedActivities {
+                total_time_secs: 0,
+                activities: vec![],
+                likely_reason: String::new(),
+            },
+            micro_activities_merged: false,
+            red_flags: vec![],
+        };
+        assert!(valid.validate(&known_ids).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_batch_rejects_invalid_response() {
+        use axum::{routing::any, Json, Router};
+
+        let app = Router::new().fallback(any(|| async { Json(malformed_response()) }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let llm =
+            LLMAnalyzer::new(format!("http://{}/analyze", addr), "key".to_string(), 5).unwrap();
+        let now = Utc::now();
+        let error = llm
+            .analyze_batch(
+                "dev@example.com".to_string(),
+                "Example".to_string(),
+                vec![],
+                now,
+                now,
+                0,
+                0,
+                vec![],
+                vec![],
+            )
+            .await
+            .unwrap_err();
+
+        let invalid = error.downcast_ref::<InvalidAnalysis>().unwrap();
+        assert!(invalid.to_string().contains("not in the request: 1, 99"));
+    }
+
+    #[tokio::test]
+    async fn test_configured_redaction_applies_to_request() {
+        use axum::{routing::any, Json, Router};
+
+        let app = Router::new().fallback(any(|| async {
+            Json(serde_json::json!({
+                "analysis": {
+                    "total_productive_time_secs": 0,
+                    "confidence": 0.0,
+                    "issues": [],
+                    "unmatched": {"total_time_secs": 0, "activities": [], "likely_reason": ""},
+                    "micro_activities_merged": false,
+                    "red_flags": []
+                }
+            }))
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let llm = LLMAnalyzer::new(format!("http://{}/analyze", addr), "key".to_string(), 5)
+            .unwrap()
+            .with_redaction_patterns(&[r"CUST-\d+".to_string()])
+            .unwrap();
+        let activity = StoredActivity {
+            id: 1,
+            session_id: 1,
+            timestamp: Utc::now(),
+            duration_secs: 300,
+            window_title: "Ticket".to_string(),
+            app_name: "Browser".to_string(),
+            description: "Customer CUST-4821 reported the login bug".to_string(),
+            tier: crate::database::ActivityTier::Billable,
+            logged_to_jira: false,
+            browser_url: None,
+            source: crate::database::ActivitySource::Auto,
+            issue_key: None,
+            attributed_issue: None,
+            attribution_source: None,
+            billable: true,
+            category: None,
+        };
+
+        let now = Utc::now();
+        let (request, _) = llm
+            .analyze_batch(
+                "dev@example.com".to_string(),
+                "Example".to_string(),
+                vec![],
+                now,
+                now,
+                300,
+                0,
+                vec![activity],
+                vec![],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            request.activities.billable[0].ocr_sample,
+            "Customer [REDACTED] reported the login bug"
+        );
+    }
+
+    async fn serve_status(status: axum::http::StatusCode) -> String {
+        use axum::{routing::any, Router};
+
+        let app = Router::new().fallback(any(move || async move { status }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}/analyze", addr)
+    }
+
+    #[tokio::test]
+    async fn test_health_check_ok() {
+        let endpoint = serve_status(axum::http::StatusCode::OK).await;
+        let llm = LLMAnalyzer::new(endpoint, "key".to_string(), 5).unwrap();
+        assert!(llm.health_check().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_health_check_rejected_key() {
+        let endpoint = serve_status(axum::http::StatusCode::UNAUTHORIZED).await;
+        let llm = LLMAnalyzer::new(endpoint, "bad-key".to_string(), 5).unwrap();
+        assert!(!llm.health_check().await.unwrap());
     }
 }
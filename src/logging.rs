@@ -0,0 +1,92 @@
+use crate::config::{Config, LogFormat, LoggingConfig};
+use crate::verbosity;
+use log::LevelFilter;
+use serde::Deserialize;
+
+/// Environment variable that overrides `[logging] format`
+pub const LOG_FORMAT_ENV: &str = "WORK_TO_JIRA_LOG_FORMAT";
+
+/// Initialize logging in the configured format at `level` (from `-v`/`-q`). Both formats
+/// use `RUST_LOG` instead when it is set.
+pub fn init(level: LevelFilter) {
+    match configured_format() {
+        LogFormat::Text => verbosity::init_env_logger(level),
+        LogFormat::Json => {
+            // `log` records are bridged into tracing, so existing log macros also come out as JSON
+            let filter = tracing_subscriber::EnvFilter::try_new(verbosity::filter(level))
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("error"));
+            tracing_subscriber::fmt()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_env_filter(filter)
+                .init();
+        }
+    }
+}
+
+/// The log format from the environment, else from the config file if one exists.
+/// Logging starts before the config is loaded, so this never creates or fails on it.
+fn configured_format() -> LogFormat {
+    if let Ok(value) = std::env::var(LOG_FORMAT_ENV) {
+        match parse_format(&value) {
+            Some(format) => return format,
+            None => eprintln!(
+                "Ignoring {}={:?}; expected \"text\" or \"json\"",
+                LOG_FORMAT_ENV, value
+            ),
+        }
+    }
+
+    Config::config_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| format_from_config(&content))
+        .unwrap_or_default()
+}
+
+fn parse_format(value: &str) -> Option<LogFormat> {
+    match value.trim().to_lowercase().as_str() {
+        "text" => Some(LogFormat::Text),
+        "json" => Some(LogFormat::Json),
+        _ => None,
+    }
+}
+
+/// Read only the `[logging]` table so an otherwise incomplete config still selects a format
+fn format_from_config(content: &str) -> Option<LogFormat> {
+    #[derive(Deserialize)]
+    struct LoggingOnly {
+        #[serde(default)]
+        logging: LoggingConfig,
+    }
+
+    toml::from_str::<LoggingOnly>(content)
+        .ok()
+        .map(|config| config.logging.format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_config() {
+        assert_eq!(
+            format_from_config("[logging]\nformat = \"json\"\n"),
+            Some(LogFormat::Json)
+        );
+        assert_eq!(
+            format_from_config("[jira]\nurl = \"https://example.atlassian.net\"\n"),
+            Some(LogFormat::Text)
+        );
+        assert_eq!(format_from_config("[logging]\nformat = \"xml\"\n"), None);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(parse_format(" JSON "), Some(LogFormat::Json));
+        assert_eq!(parse_format("text"), Some(LogFormat::Text));
+        assert_eq!(parse_format("yaml"), None);
+    }
+}
@@ -1,29 +1,85 @@
+mod activity_file;
+mod backup;
+mod category;
 mod config;
 mod daemon;
 mod database;
+mod detection;
+mod doctor;
+mod events;
+mod export;
+mod format;
+mod heartbeat;
+mod http;
+mod issue_key;
 mod jira;
 mod llm;
+mod logging;
+mod metrics;
+mod notifications;
+mod nudge;
+mod paths;
+#[cfg(windows)]
+mod process_job;
+mod rate_limit;
+mod redact;
 mod salesforce;
 mod screenpipe;
 mod screenpipe_manager;
 mod state;
 mod tracker;
+mod verbosity;
+mod version;
+mod work_type;
 
-use anyhow::Result;
+use activity_file::FileFormat;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use config::Config;
+use config::{Config, NotificationFrequency, ScreenpipeConfig};
 use daemon::run_daemon;
-use directories::ProjectDirs;
-use screenpipe_manager::ScreenpipeManager;
-use std::{path::PathBuf, sync::Arc};
+use database::Database;
+use screenpipe_manager::{ScreenpipeManager, ScreenpipeNotInstalled};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 use tracker::WorkTracker;
 
 #[derive(Parser)]
 #[command(name = "work-to-jira-effort")]
-#[command(version = "0.1.0")]
+#[command(version)]
 #[command(about = "Automatically track work time via Screenpipe and log to Jira & Salesforce", long_about = None)]
 struct Cli {
+    /// Install Screenpipe without asking if it is missing
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Don't start Screenpipe; use one you run yourself on screenpipe.port
+    #[arg(long, global = true)]
+    skip_screenpipe: bool,
+
+    /// Log more: -v for debug, -vv for trace (RUST_LOG wins when set)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only log warnings and errors (RUST_LOG wins when set)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log the worklogs and time entries that would be sent without sending them
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Confidence needed to log LLM matches, replacing llm.confidence_threshold for this run
+    #[arg(long, global = true, value_name = "0-1")]
+    confidence_threshold: Option<f64>,
+
+    /// Use this config file instead of the default location
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Keep Screenpipe data and the database in this directory (overrides WTJE_DATA_DIR)
+    #[arg(long, global = true, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -31,11 +87,81 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Start tracking work time
-    Start,
+    Start {
+        /// Poll Screenpipe every this many seconds for this run, instead of
+        /// tracking.screenpipe_poll_interval_secs
+        #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+    },
     /// Check configuration and service connectivity
     Check,
+    /// Diagnose the config, Screenpipe, integrations and database, with hints for each problem
+    Doctor,
     /// Initialize configuration file
     Init,
+    /// Delete the worklogs posted for the most recent session
+    Undo,
+    /// Check database integrity and compact it
+    Maintenance,
+    /// List the Jira projects you can see, and optionally pick the ones to track
+    Projects {
+        /// Save these project keys (comma-separated) as jira.project_keys; asks for them
+        /// when given without a value
+        #[arg(long, value_name = "KEYS", num_args = 0..=1, default_missing_value = "")]
+        select: Option<String>,
+    },
+    /// Back up the analytics database while it's in use
+    Backup {
+        /// Write the backup to this file instead of a timestamped one in the backup directory
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Import activities from a CSV or JSON file (e.g. from a previous tracker)
+    Import {
+        /// File with timestamp, duration_secs, app_name, window_title and description columns
+        file: PathBuf,
+        /// File format; guessed from the extension when omitted
+        #[arg(long, value_enum)]
+        format: Option<FileFormat>,
+        /// Add the activities to this session instead of creating a new one
+        #[arg(long)]
+        session: Option<i64>,
+    },
+    /// Export a session's activities as CSV or JSON
+    Export {
+        /// Session to export
+        session_id: i64,
+        /// File format; guessed from --out's extension, otherwise CSV
+        #[arg(long, value_enum)]
+        format: Option<FileFormat>,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Show the embedded Screenpipe server's log output
+    Logs {
+        /// Number of lines to show
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: usize,
+        /// Keep printing new lines as they are written
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Show or change logged-time summary notifications
+    Notifications {
+        /// Turn summary notifications on or off
+        #[arg(long)]
+        enabled: Option<bool>,
+        /// How often to show summaries: immediate, hourly or daily
+        #[arg(long)]
+        frequency: Option<NotificationFrequency>,
+    },
+    /// Show the version, commit, build date and enabled integrations, for bug reports
+    Version {
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Run daemon for menubar/GUI clients
     Daemon {
         /// Port for the local control API
@@ -46,9 +172,16 @@ enum Commands {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
-
     let cli = Cli::parse();
+    if let Some(path) = &cli.config {
+        Config::set_path_override(path)?;
+    }
+    if let Some(dir) = &cli.data_dir {
+        paths::set_data_dir_flag(dir.clone());
+    }
+
+    // After the overrides, so the log format is read from the right config file
+    logging::init(verbosity::level_filter(cli.verbose, cli.quiet));
 
     match cli.command {
         Commands::Init => {
@@ -63,16 +196,13 @@ async fn main() -> Result<()> {
         }
         Commands::Check => {
             println!("Loading configuration...");
-            let config = Config::load()?;
+            let mut config = load_config(&cli, Config::load()?)?;
+            config.ensure_credentials(&Config::config_path()?)?;
             println!("Configuration loaded successfully!");
 
-            // Get data directory for embedded Screenpipe
-            let data_dir = get_data_dir()?;
-
-            // Start embedded Screenpipe server
-            println!("\nStarting embedded Screenpipe server...");
-            let mut screenpipe = ScreenpipeManager::new();
-            screenpipe.start(data_dir, 3030).await?;
+            println!();
+            let mut screenpipe = start_screenpipe(&cli, &config.screenpipe).await?;
+            config.screenpipe.port = screenpipe.port();
 
             println!("\nChecking service connectivity...");
             let mut tracker = WorkTracker::new(config, Arc::new(RwLock::new(None)))?;
@@ -84,18 +214,249 @@ async fn main() -> Result<()> {
             println!("\nAll checks completed!");
             Ok(())
         }
-        Commands::Start => {
+        Commands::Doctor => {
+            let report = doctor::run().await;
+            report.print();
+
+            let failures = report.failures();
+            if failures > 0 {
+                anyhow::bail!("{} critical check(s) failed", failures);
+            }
+            println!("\nNo problems found");
+            Ok(())
+        }
+        Commands::Undo => {
+            let config = Config::load_strict()?;
+            let mut tracker = WorkTracker::new(config, Arc::new(RwLock::new(None)))?;
+
+            let outcome = tracker.undo_last_worklogs().await?;
+            if outcome.undone.is_empty() && outcome.failed.is_empty() {
+                println!("No worklogs to undo");
+            }
+            for worklog in &outcome.undone {
+                println!(
+                    "Deleted {} from {} (session {}, logged {})",
+                    format::format_hm(worklog.time_spent_secs),
+                    worklog.issue_key,
+                    worklog.session_id,
+                    worklog.logged_at.format("%Y-%m-%d %H:%M")
+                );
+            }
+            for (worklog, error) in &outcome.failed {
+                eprintln!(
+                    "Could not delete {} from {} (worklog {}): {}",
+                    format::format_hm(worklog.time_spent_secs),
+                    worklog.issue_key,
+                    worklog.worklog_id,
+                    error
+                );
+            }
+            if !outcome.failed.is_empty() {
+                anyhow::bail!("{} worklogs could not be undone", outcome.failed.len());
+            }
+            Ok(())
+        }
+        Commands::Maintenance => {
+            let config = Config::load_strict()?;
+            let db_path = paths::database_path(&config)?;
+            println!("Running maintenance on {}", db_path.display());
+
+            let database = WorkTracker::open_database(&config)?;
+            let report = database.maintenance()?;
+
+            println!("Integrity check: {}", report.integrity);
+            println!(
+                "Trimmed {} oversized descriptions",
+                report.descriptions_trimmed
+            );
+            println!("Reclaimed {} KB", report.bytes_reclaimed / 1024);
+            Ok(())
+        }
+        Commands::Projects { ref select } => {
+            let mut config = Config::load_strict()?;
+            config.ensure_credentials(&Config::config_path()?)?;
+            let jira = WorkTracker::open_jira(&config, http::build_client(&config.network)?)?
+                .context("Jira is disabled in the config")?;
+
+            let projects = jira.list_projects().await?;
+            if projects.is_empty() {
+                println!("No Jira projects are visible to {}", config.jira.email);
+                return Ok(());
+            }
+            for project in &projects {
+                let tracked = config.jira.project_keys.contains(&project.key);
+                println!(
+                    "{} {:<10} {}",
+                    if tracked { "*" } else { " " },
+                    project.key,
+                    project.name
+                );
+            }
+
+            let Some(select) = select else {
+                return Ok(());
+            };
+            let input = if select.is_empty() {
+                print!("\nProject keys to track (comma-separated, empty for any): ");
+                std::io::Write::flush(&mut std::io::stdout())?;
+                let mut line = String::new();
+                std::io::stdin().read_line(&mut line)?;
+                line
+            } else {
+                select.clone()
+            };
+
+            config.jira.project_keys = jira::select_projects(&input, &projects)?;
+            config.save()?;
+            if config.jira.project_keys.is_empty() {
+                println!("Issue keys from any project will be detected");
+            } else {
+                println!("Tracking projects: {}", config.jira.project_keys.join(", "));
+            }
+            Ok(())
+        }
+        Commands::Backup { ref out } => {
+            let config = Config::load_strict()?;
+            let database = WorkTracker::open_database(&config)?;
+
+            let path = match out {
+                Some(out) => {
+                    database.backup_to(out)?;
+                    out.clone()
+                }
+                None => backup::snapshot(
+                    &database,
+                    &paths::backup_dir(&config)?,
+                    config.analytics.backup_keep,
+                )?,
+            };
+            println!("Backed up the database to {}", path.display());
+            Ok(())
+        }
+        Commands::Import {
+            ref file,
+            format,
+            session,
+        } => {
+            let format = format
+                .or_else(|| FileFormat::from_path(file))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Cannot tell the format of {}; pass --format csv|json",
+                        file.display()
+                    )
+                })?;
+            let content = std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+
+            let config = load_config(&cli, Config::load_strict()?)?;
+            let database = WorkTracker::open_database(&config)?;
+
+            let report = activity_file::import_activities(&database, &content, format, session)?;
+            for (location, error) in &report.errors {
+                eprintln!("Skipped {}: {}", location, error);
+            }
+            println!(
+                "Imported {} activities into session {} ({} skipped)",
+                report.imported,
+                report.session_id,
+                report.errors.len()
+            );
+            Ok(())
+        }
+        Commands::Export {
+            session_id,
+            format,
+            ref out,
+        } => {
+            let format = format
+                .or_else(|| out.as_deref().and_then(FileFormat::from_path))
+                .unwrap_or(FileFormat::Csv);
+
+            let config = load_config(&cli, Config::load_strict()?)?;
+            let database = Database::new(paths::database_path(&config)?)?;
+            let content = export::export_session(&database, session_id, format)?;
+
+            match out {
+                Some(path) => {
+                    std::fs::write(path, content)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    eprintln!("Exported session {} to {}", session_id, path.display());
+                }
+                None => print!("{}", content),
+            }
+            Ok(())
+        }
+        Commands::Logs { lines, follow } => {
+            let path = screenpipe_manager::log_path(&paths::screenpipe_data_dir()?);
+            if !path.exists() {
+                println!("No Screenpipe log at {}", path.display());
+                println!(
+                    "Logs are written while Screenpipe runs with `screenpipe.log_to_file` enabled."
+                );
+                return Ok(());
+            }
+
+            let all = screenpipe_manager::tail_log(&path, usize::MAX)?;
+            for line in &all[all.len().saturating_sub(lines)..] {
+                println!("{}", line);
+            }
+
+            let mut seen = all.len();
+            if follow {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                    let Ok(all) = screenpipe_manager::tail_log(&path, usize::MAX) else {
+                        continue;
+                    };
+                    // A shorter file means it was rotated, so print it from the start
+                    let skip = if all.len() >= seen { seen } else { 0 };
+                    for line in &all[skip..] {
+                        println!("{}", line);
+                    }
+                    seen = all.len();
+                }
+            }
+            Ok(())
+        }
+        Commands::Notifications { enabled, frequency } => {
+            let mut config = Config::load_strict()?;
+            if enabled.is_some() || frequency.is_some() {
+                if let Some(enabled) = enabled {
+                    config.notifications.enabled = enabled;
+                }
+                if let Some(frequency) = frequency {
+                    config.notifications.frequency = frequency;
+                }
+                config.save()?;
+                println!("Notification preferences updated");
+            }
+
+            println!(
+                "Summary notifications: {} ({})",
+                if config.notifications.enabled {
+                    "on"
+                } else {
+                    "off"
+                },
+                config.notifications.frequency.as_str()
+            );
+            Ok(())
+        }
+        Commands::Start { interval } => {
             println!("Starting work time tracker with embedded Screenpipe...");
-            let config = Config::load()?;
+            let mut config = load_config(&cli, Config::load_strict()?)?;
+            config.ensure_credentials(&Config::config_path()?)?;
+            if config.tracking.dry_run {
+                println!("Dry run: worklogs will be logged but not sent");
+            }
+            if let Some(interval) = interval {
+                config.tracking.screenpipe_poll_interval_secs = interval;
+            }
             let interval = config.tracking.screenpipe_poll_interval_secs;
 
-            // Get data directory for embedded Screenpipe
-            let data_dir = get_data_dir()?;
-
-            // Start embedded Screenpipe server
-            println!("Starting embedded Screenpipe server...");
-            let mut screenpipe = ScreenpipeManager::new();
-            screenpipe.start(data_dir, 3030).await?;
+            let mut screenpipe = start_screenpipe(&cli, &config.screenpipe).await?;
+            config.screenpipe.port = screenpipe.port();
 
             let mut tracker = WorkTracker::new(config, Arc::new(RwLock::new(None)))?;
 
@@ -108,47 +469,116 @@ async fn main() -> Result<()> {
             );
             println!("Press Ctrl+C to stop");
 
-            // Set up Ctrl+C handler
-            let result = tokio::select! {
-                res = tracker.run(interval) => res,
-                _ = tokio::signal::ctrl_c() => {
-                    println!("\nShutdown signal received, stopping...");
-                    Ok(())
+            // Set up Ctrl+C handler; the tracker flushes a final analysis before returning
+            let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    println!("\nShutdown signal received, finishing up...");
+                    let _ = shutdown_tx.send(true);
                 }
-            };
+            });
+            let result = tracker.run(interval, shutdown_rx).await;
 
             // Stop Screenpipe server
             screenpipe.stop().await?;
 
             result
         }
+        Commands::Version { json } => {
+            // Only read an existing config; `version` shouldn't create one
+            let config_path = Config::config_path()?;
+            let config = if config_path.exists() {
+                // A broken config is worth reporting, not a reason to hide the version
+                Config::load_strict()
+                    .and_then(|config| load_config(&cli, config))
+                    .map_err(|e| eprintln!("Could not read the config: {:#}", e))
+                    .ok()
+            } else {
+                None
+            };
+
+            let info = version::VersionInfo::new(config.as_ref());
+            if json {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+            } else {
+                info.print();
+            }
+            Ok(())
+        }
         Commands::Daemon { port } => {
             println!(
                 "Starting WorkToJiraEffort daemon on http://127.0.0.1:{}",
                 port
             );
 
-            // Get data directory for embedded Screenpipe
-            let data_dir = get_data_dir()?;
+            let config = load_config(&cli, Config::load_strict()?)?;
 
-            // Start embedded Screenpipe server
-            println!("Starting embedded Screenpipe server...");
-            let mut screenpipe = ScreenpipeManager::new();
-            screenpipe.start(data_dir, 3030).await?;
-            println!("Screenpipe server started successfully");
+            let screenpipe = start_screenpipe(&cli, &config.screenpipe).await?;
+            let screenpipe_port = screenpipe.port();
+            if screenpipe.is_external() {
+                println!(
+                    "Using the Screenpipe server already running on port {}",
+                    screenpipe_port
+                );
+            } else {
+                println!(
+                    "Screenpipe server started successfully on port {}",
+                    screenpipe_port
+                );
+            }
 
-            run_daemon(port, screenpipe).await
+            run_daemon(config, port, screenpipe).await
         }
     }
 }
 
-/// Get the data directory for storing Screenpipe data
-fn get_data_dir() -> Result<PathBuf> {
-    let proj_dirs = ProjectDirs::from("com", "worktojiraeffort", "WorkToJiraEffort")
-        .ok_or_else(|| anyhow::anyhow!("Failed to determine project directories"))?;
+/// Apply command-line overrides to the loaded config file
+fn load_config(cli: &Cli, mut config: Config) -> Result<Config> {
+    if cli.dry_run {
+        config.tracking.dry_run = true;
+    }
+    if let Some(threshold) = cli.confidence_threshold {
+        config.llm.confidence_threshold = threshold;
+        config.validate()?;
+    }
+    Ok(config)
+}
+
+/// Start the embedded Screenpipe server, or with `--skip-screenpipe` use the one already
+/// running on the configured port. A missing Screenpipe ends the command with instructions
+/// rather than an error chain.
+async fn start_screenpipe(cli: &Cli, config: &ScreenpipeConfig) -> Result<ScreenpipeManager> {
+    if cli.skip_screenpipe {
+        println!(
+            "Using your own Screenpipe at {} (--skip-screenpipe)",
+            config.url()
+        );
+        return Ok(ScreenpipeManager::external(config.port));
+    }
+
+    // Get data directory for embedded Screenpipe
+    let data_dir = paths::screenpipe_data_dir()?;
 
-    let data_dir = proj_dirs.data_dir().join("screenpipe");
-    std::fs::create_dir_all(&data_dir)?;
+    println!("Starting embedded Screenpipe server...");
+    let mut screenpipe = screenpipe_manager(config, cli.yes);
+    match screenpipe.start(data_dir, config.port).await {
+        Ok(_) => Ok(screenpipe),
+        Err(e) => match e.downcast_ref::<ScreenpipeNotInstalled>() {
+            Some(not_installed) => anyhow::bail!(
+                "{}\n\n\
+                 To run against a Screenpipe you start yourself at {}, rerun with --skip-screenpipe.",
+                not_installed,
+                config.url()
+            ),
+            None => Err(e),
+        },
+    }
+}
 
-    Ok(data_dir)
+/// Build a Screenpipe manager from the `[screenpipe]` config section
+fn screenpipe_manager(config: &ScreenpipeConfig, assume_yes: bool) -> ScreenpipeManager {
+    ScreenpipeManager::new()
+        .with_startup_timeout(config.startup_timeout_secs)
+        .with_log_file(config.log_to_file, config.log_max_bytes)
+        .with_auto_install(assume_yes || config.auto_install_screenpipe)
 }
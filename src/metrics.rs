@@ -0,0 +1,159 @@
+//! Counters behind the daemon's `GET /metrics`, written in the Prometheus text format.
+//! The tracker bumps them as it works; like any Prometheus counter they start from zero
+//! when the process does.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// `Content-Type` of the Prometheus text format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// What failed, as the `kind` label of `wtje_failures_total`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// A Screenpipe sync
+    Sync,
+    /// An LLM request
+    Llm,
+    /// Posting a worklog to Jira
+    Worklog,
+}
+
+impl Failure {
+    const ALL: [Failure; 3] = [Failure::Sync, Failure::Llm, Failure::Worklog];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Failure::Sync => "sync",
+            Failure::Llm => "llm",
+            Failure::Worklog => "worklog",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    activities_stored: AtomicU64,
+    worklogs_posted: AtomicU64,
+    llm_calls: AtomicU64,
+    failures: [AtomicU64; 3],
+}
+
+impl Metrics {
+    pub fn record_activities_stored(&self, count: usize) {
+        self.activities_stored
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_worklog_posted(&self) {
+        self.worklogs_posted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_llm_call(&self) {
+        self.llm_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self, failure: Failure) {
+        self.failures[failure as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The counters, plus `tracked_secs_today` as a gauge, in the Prometheus text format
+    pub fn render(&self, tracked_secs_today: u64) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, u64)]| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{}{} {}", name, labels, value);
+            }
+        };
+
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        metric(
+            "wtje_activities_stored_total",
+            "counter",
+            "Activities stored from Screenpipe",
+            &[("", load(&self.activities_stored))],
+        );
+        metric(
+            "wtje_worklogs_posted_total",
+            "counter",
+            "Worklogs posted to Jira",
+            &[("", load(&self.worklogs_posted))],
+        );
+        metric(
+            "wtje_llm_calls_total",
+            "counter",
+            "Requests sent to the LLM for batch analysis or single-activity suggestions",
+            &[("", load(&self.llm_calls))],
+        );
+        let labels: Vec<String> = Failure::ALL
+            .iter()
+            .map(|failure| format!("{{kind=\"{}\"}}", failure.as_str()))
+            .collect();
+        let failures: Vec<(&str, u64)> = Failure::ALL
+            .iter()
+            .zip(&labels)
+            .map(|(failure, labels)| (labels.as_str(), load(&self.failures[*failure as usize])))
+            .collect();
+        metric(
+            "wtje_failures_total",
+            "counter",
+            "Failed syncs, LLM requests and worklog posts",
+            &failures,
+        );
+        metric(
+            "wtje_tracked_seconds_today",
+            "gauge",
+            "Time tracked since local midnight, without breaks",
+            &[("", tracked_secs_today)],
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sample lines of `text` as (name with labels, value), checking each line is well formed
+    fn parse(text: &str) -> Vec<(String, f64)> {
+        text.lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                let (name, value) = line.rsplit_once(' ').expect(line);
+                (name.to_string(), value.parse().expect(line))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_is_prometheus_text() {
+        let metrics = Metrics::default();
+        metrics.record_activities_stored(3);
+        metrics.record_worklog_posted();
+        metrics.record_llm_call();
+        metrics.record_llm_call();
+        metrics.record_failure(Failure::Llm);
+
+        let text = metrics.render(5400);
+        for line in text.lines().filter(|line| line.starts_with('#')) {
+            assert!(
+                line.starts_with("# HELP wtje_") || line.starts_with("# TYPE wtje_"),
+                "{}",
+                line
+            );
+        }
+
+        let samples = parse(&text);
+        let value = |name: &str| samples.iter().find(|(n, _)| n == name).map(|(_, v)| *v);
+        assert_eq!(value("wtje_activities_stored_total"), Some(3.0));
+        assert_eq!(value("wtje_worklogs_posted_total"), Some(1.0));
+        assert_eq!(value("wtje_llm_calls_total"), Some(2.0));
+        assert_eq!(value(r#"wtje_failures_total{kind="sync"}"#), Some(0.0));
+        assert_eq!(value(r#"wtje_failures_total{kind="llm"}"#), Some(1.0));
+        assert_eq!(value(r#"wtje_failures_total{kind="worklog"}"#), Some(0.0));
+        assert_eq!(value("wtje_tracked_seconds_today"), Some(5400.0));
+        assert_eq!(samples.len(), 7);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::config::{NotificationFrequency, NotificationsConfig};
+use crate::format::format_hm_compact;
+use chrono::{DateTime, Duration, Utc};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::sync::RwLock;
+
+/// Show a desktop notification, logging instead of failing when none can be shown
+pub fn show(summary: &str, body: &str) {
+    let result = notify_rust::Notification::new()
+        .summary(summary)
+        .body(body)
+        .appname("WorkToJiraEffort")
+        .show();
+
+    if let Err(e) = result {
+        log::warn!("Failed to show notification '{}': {}", summary, e);
+    }
+}
+
+/// Collects logged and unmatched time from analyses and shows it as a summary at the
+/// configured frequency
+pub struct SummaryNotifier {
+    prefs: Arc<RwLock<NotificationsConfig>>,
+    pending: BTreeMap<String, u64>,
+    unmatched_secs: u64,
+    last_sent: Option<DateTime<Utc>>,
+}
+
+impl SummaryNotifier {
+    pub fn new(prefs: Arc<RwLock<NotificationsConfig>>) -> Self {
+        Self {
+            prefs,
+            pending: BTreeMap::new(),
+            unmatched_secs: 0,
+            last_sent: None,
+        }
+    }
+
+    /// Remember time logged to an issue for the next summary
+    pub fn record(&mut self, issue_key: &str, time_spent_secs: u64) {
+        *self.pending.entry(issue_key.to_string()).or_insert(0) += time_spent_secs;
+    }
+
+    /// Remember time an analysis couldn't match to an issue for the next summary
+    pub fn record_unmatched(&mut self, secs: u64) {
+        self.unmatched_secs += secs;
+    }
+
+    /// Show the pending summary if notifications are enabled and one is due
+    pub async fn flush(&mut self) {
+        let prefs = self.prefs.read().await.clone();
+        if !prefs.enabled {
+            self.pending.clear();
+            self.unmatched_secs = 0;
+            return;
+        }
+
+        if let Some(body) = self.take_due(prefs.frequency, Utc::now()) {
+            show("Time logged to Jira", &body);
+        }
+    }
+
+    /// Build and clear the pending summary if `frequency` allows sending one at `now`
+    fn take_due(&mut self, frequency: NotificationFrequency, now: DateTime<Utc>) -> Option<String> {
+        if self.pending.is_empty() && self.unmatched_secs == 0 {
+            return None;
+        }
+
+        if let Some(last_sent) = self.last_sent {
+            if now - last_sent < Duration::seconds(frequency.interval_secs()) {
+                return None;
+            }
+        }
+
+        let body = summary_text(&self.pending, self.unmatched_secs);
+        self.pending.clear();
+        self.unmatched_secs = 0;
+        self.last_sent = Some(now);
+
+        Some(body)
+    }
+}
+
+/// "Logged 2h 15m across 3 issues, 20m unmatched", followed by the time per issue
+fn summary_text(logged: &BTreeMap<String, u64>, unmatched_secs: u64) -> String {
+    let total_secs: u64 = logged.values().sum();
+    let mut headline = format!(
+        "Logged {} across {} issue{}",
+        format_hm_compact(total_secs),
+        logged.len(),
+        if logged.len() == 1 { "" } else { "s" }
+    );
+    if unmatched_secs > 0 {
+        headline.push_str(&format!(
+            ", {} unmatched",
+            format_hm_compact(unmatched_secs)
+        ));
+    }
+
+    std::iter::once(headline)
+        .chain(
+            logged
+                .iter()
+                .map(|(issue_key, secs)| format!("{}: {}", issue_key, format_hm_compact(*secs))),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notifier() -> SummaryNotifier {
+        SummaryNotifier::new(Arc::new(RwLock::new(NotificationsConfig::default())))
+    }
+
+    #[test]
+    fn test_immediate_summaries_every_time() {
+        let mut notifier = notifier();
+        let now = Utc::now();
+
+        notifier.record("PROJ-1", 600);
+        notifier.record("PROJ-1", 300);
+        assert_eq!(
+            notifier
+                .take_due(NotificationFrequency::Immediate, now)
+                .as_deref(),
+            Some("Logged 15m across 1 issue\nPROJ-1: 15m")
+        );
+        assert!(notifier
+            .take_due(NotificationFrequency::Immediate, now)
+            .is_none());
+
+        notifier.record("PROJ-2", 60);
+        assert!(notifier
+            .take_due(NotificationFrequency::Immediate, now)
+            .is_some());
+    }
+
+    #[test]
+    fn test_hourly_summaries_accumulate() {
+        let mut notifier = notifier();
+        let now = Utc::now();
+
+        notifier.record("PROJ-1", 600);
+        assert!(notifier
+            .take_due(NotificationFrequency::Hourly, now)
+            .is_some());
+
+        notifier.record("PROJ-2", 1200);
+        notifier.record("PROJ-1", 60);
+        let later = now + Duration::minutes(30);
+        assert!(notifier
+            .take_due(NotificationFrequency::Hourly, later)
+            .is_none());
+
+        let next_hour = now + Duration::minutes(60);
+        assert_eq!(
+            notifier
+                .take_due(NotificationFrequency::Hourly, next_hour)
+                .as_deref(),
+            Some("Logged 21m across 2 issues\nPROJ-1: 1m\nPROJ-2: 20m")
+        );
+    }
+
+    #[test]
+    fn test_summary_text() {
+        let logged = BTreeMap::from([
+            ("PROJ-1".to_string(), 5400),
+            ("PROJ-2".to_string(), 1800),
+            ("PROJ-3".to_string(), 900),
+        ]);
+        assert_eq!(
+            summary_text(&logged, 1200),
+            "Logged 2h 15m across 3 issues, 20m unmatched\nPROJ-1: 1h 30m\nPROJ-2: 30m\nPROJ-3: 15m"
+        );
+        assert_eq!(
+            summary_text(&BTreeMap::new(), 3600),
+            "Logged 0m across 0 issues, 1h unmatched"
+        );
+    }
+
+    #[test]
+    fn test_unmatched_time_alone_is_summarized() {
+        let mut notifier = notifier();
+        notifier.record_unmatched(600);
+        assert_eq!(
+            notifier
+                .take_due(NotificationFrequency::Immediate, Utc::now())
+                .as_deref(),
+            Some("Logged 0m across 0 issues, 10m unmatched")
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{config::NudgingConfig, notifications};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+/// Reminds the user when they work on an issue that isn't assigned to them,
+/// at most once per `cooldown_secs` for each issue
+pub struct NudgeManager {
+    enabled: bool,
+    cooldown: Duration,
+    last_nudged: HashMap<String, DateTime<Utc>>,
+}
+
+impl NudgeManager {
+    pub fn new(config: &NudgingConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            cooldown: Duration::seconds(config.cooldown_secs as i64),
+            last_nudged: HashMap::new(),
+        }
+    }
+
+    /// Whether `issue_key` may be nudged at `now`; records the nudge when it may
+    pub fn should_nudge(&mut self, issue_key: &str, now: DateTime<Utc>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if let Some(last) = self.last_nudged.get(issue_key) {
+            if now - *last < self.cooldown {
+                return false;
+            }
+        }
+
+        self.last_nudged.insert(issue_key.to_string(), now);
+        true
+    }
+
+    /// Show a desktop notification for work on an unassigned issue, subject to the cooldown
+    pub fn nudge_unassigned(&mut self, issue_key: &str, window_title: &str) {
+        if !self.should_nudge(issue_key, Utc::now()) {
+            return;
+        }
+
+        log::info!("Nudging about unassigned issue {}", issue_key);
+
+        notifications::show(
+            &format!("Working on {}?", issue_key),
+            &format!(
+                "{} isn't assigned to you, so its time won't be logged.\n\
+                 Assign it to yourself or set it as the issue override.\n\n{}",
+                issue_key, window_title
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(enabled: bool, cooldown_secs: u64) -> NudgeManager {
+        NudgeManager::new(&NudgingConfig {
+            enabled,
+            cooldown_secs,
+            detect_assigned_issues_in_titles: true,
+        })
+    }
+
+    #[test]
+    fn test_cooldown_is_per_issue() {
+        let mut nudges = manager(true, 1800);
+        let now = Utc::now();
+
+        assert!(nudges.should_nudge("PROJ-1", now));
+        assert!(!nudges.should_nudge("PROJ-1", now + Duration::seconds(60)));
+        assert!(nudges.should_nudge("PROJ-2", now + Duration::seconds(60)));
+        assert!(nudges.should_nudge("PROJ-1", now + Duration::seconds(1800)));
+    }
+
+    #[test]
+    fn test_disabled_never_nudges() {
+        let mut nudges = manager(false, 0);
+        assert!(!nudges.should_nudge("PROJ-1", Utc::now()));
+    }
+}
@@ -0,0 +1,151 @@
+//! Where the tracker keeps its data: Screenpipe's recordings and the analytics database.
+//! Both follow the platform defaults unless a data directory is given with `--data-dir`
+//! or `WTJE_DATA_DIR`, which keeps everything under one directory for sandboxed or
+//! portable installs.
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable overriding the data directory
+pub const DATA_DIR_ENV: &str = "WTJE_DATA_DIR";
+
+const DEFAULT_DATABASE_FILE: &str = "analytics.db";
+
+static DATA_DIR_FLAG: OnceLock<PathBuf> = OnceLock::new();
+
+/// Record the `--data-dir` flag; call once at startup, before resolving any path
+pub fn set_data_dir_flag(dir: PathBuf) {
+    let _ = DATA_DIR_FLAG.set(dir);
+}
+
+/// The data directory override in effect, if any
+pub fn data_dir_override() -> Option<PathBuf> {
+    resolve_override(
+        DATA_DIR_FLAG.get().map(PathBuf::as_path),
+        std::env::var_os(DATA_DIR_ENV),
+    )
+}
+
+/// `--data-dir` wins over `WTJE_DATA_DIR`; an empty variable counts as unset
+fn resolve_override(flag: Option<&Path>, env: Option<OsString>) -> Option<PathBuf> {
+    flag.map(Path::to_path_buf)
+        .or_else(|| env.filter(|value| !value.is_empty()).map(PathBuf::from))
+}
+
+/// Directory for Screenpipe's data, created if needed
+pub fn screenpipe_data_dir() -> Result<PathBuf> {
+    let dir = match data_dir_override() {
+        Some(dir) => dir.join("screenpipe"),
+        None => ProjectDirs::from("com", "worktojiraeffort", "WorkToJiraEffort")
+            .context("Failed to determine project directories")?
+            .data_dir()
+            .join("screenpipe"),
+    };
+    ensure_writable(&dir)?;
+    Ok(dir)
+}
+
+/// Path of the analytics database: inside the data directory when one is set,
+/// otherwise `analytics.database_path` with `~` expanded
+pub fn database_path(config: &Config) -> Result<PathBuf> {
+    database_path_in(
+        data_dir_override().as_deref(),
+        &config.analytics.database_path,
+    )
+}
+
+fn database_path_in(data_dir: Option<&Path>, configured: &str) -> Result<PathBuf> {
+    if let Some(dir) = data_dir {
+        ensure_writable(dir)?;
+        let file_name = Path::new(configured)
+            .file_name()
+            .unwrap_or(DEFAULT_DATABASE_FILE.as_ref());
+        return Ok(dir.join(file_name));
+    }
+
+    expand_home(configured)
+}
+
+/// Directory for database snapshots: `analytics.backup_dir` with `~` expanded, otherwise
+/// `backups` next to the database
+pub fn backup_dir(config: &Config) -> Result<PathBuf> {
+    match &config.analytics.backup_dir {
+        Some(dir) => expand_home(dir),
+        None => Ok(database_path(config)?
+            .parent()
+            .map(|parent| parent.join("backups"))
+            .unwrap_or_else(|| PathBuf::from("backups"))),
+    }
+}
+
+/// Expand a leading `~` to the home directory
+fn expand_home(path: &str) -> Result<PathBuf> {
+    let expanded = if path.starts_with('~') {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .context("Could not determine home directory")?;
+        path.replacen('~', &home, 1)
+    } else {
+        path.to_string()
+    };
+
+    Ok(PathBuf::from(expanded))
+}
+
+/// Create `dir` if missing and check files can be written to it
+fn ensure_writable(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create data directory {}", dir.display()))?;
+
+    let probe = dir.join(".write-test");
+    std::fs::write(&probe, b"")
+        .with_context(|| format!("Data directory {} is not writable", dir.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_overrides_env_var() {
+        let flag = Path::new("/from/flag");
+
+        assert_eq!(
+            resolve_override(Some(flag), Some("/from/env".into())),
+            Some(PathBuf::from("/from/flag"))
+        );
+        assert_eq!(
+            resolve_override(None, Some("/from/env".into())),
+            Some(PathBuf::from("/from/env"))
+        );
+        assert_eq!(resolve_override(None, Some(OsString::new())), None);
+        assert_eq!(resolve_override(None, None), None);
+    }
+
+    #[test]
+    fn test_database_path_follows_data_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let data_dir = temp.path().join("portable");
+
+        let path = database_path_in(Some(&data_dir), "~/.work-tracker/analytics.db").unwrap();
+        assert_eq!(path, data_dir.join("analytics.db"));
+        assert!(data_dir.is_dir());
+
+        let configured = database_path_in(None, "/var/lib/tracker.db").unwrap();
+        assert_eq!(configured, PathBuf::from("/var/lib/tracker.db"));
+    }
+
+    #[test]
+    fn test_unusable_data_dir_is_an_error() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+
+        let error = database_path_in(Some(temp.path()), "analytics.db").unwrap_err();
+        assert!(format!("{:#}", error).contains(&temp.path().display().to_string()));
+    }
+}
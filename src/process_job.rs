@@ -0,0 +1,69 @@
+//! Windows job object tying child processes to this one.
+//! Windows has no process groups or parent-death signal, so without a job a child
+//! (Screenpipe, or the daemon started by the tray) keeps running after its parent dies.
+
+use anyhow::{Context, Result};
+use std::os::windows::io::AsRawHandle;
+use std::process::Child;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+    SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+};
+
+/// Job whose processes are killed when its last handle closes: on `drop`,
+/// or by the OS when this process exits or crashes
+pub struct ProcessJob {
+    handle: HANDLE,
+}
+
+// Kernel handles can be used and closed from any thread
+unsafe impl Send for ProcessJob {}
+unsafe impl Sync for ProcessJob {}
+
+impl ProcessJob {
+    pub fn new() -> Result<Self> {
+        unsafe {
+            let handle =
+                CreateJobObjectW(None, PCWSTR::null()).context("Failed to create job object")?;
+            let job = Self { handle };
+
+            let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            SetInformationJobObject(
+                job.handle,
+                JobObjectExtendedLimitInformation,
+                &limits as *const _ as *const core::ffi::c_void,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            )
+            .context("Failed to configure job object")?;
+
+            Ok(job)
+        }
+    }
+
+    /// Add `child` to the job; processes it starts later join the job too
+    pub fn assign(&self, child: &Child) -> Result<()> {
+        unsafe {
+            AssignProcessToJobObject(self.handle, HANDLE(child.as_raw_handle()))
+                .context("Failed to add process to job object")
+        }
+    }
+
+    /// Kill every process in the job now
+    pub fn terminate(&self) {
+        unsafe {
+            let _ = TerminateJobObject(self.handle, 1);
+        }
+    }
+}
+
+impl Drop for ProcessJob {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.handle);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Spaces requests evenly at up to `requests_per_second`, and holds every caller back
+/// while the server has asked us to back off
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// `requests_per_second` of 0 (or less) disables pacing; `pause` still applies
+    pub fn new(requests_per_second: f64) -> Self {
+        let interval = if requests_per_second > 0.0 {
+            Duration::from_secs_f64(1.0 / requests_per_second)
+        } else {
+            Duration::ZERO
+        };
+        Self {
+            interval,
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for the next free slot and claim it
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    /// Hand out no slots for `duration`, e.g. after a 429 with `Retry-After`
+    pub async fn pause(&self, duration: Duration) {
+        let mut next_slot = self.next_slot.lock().await;
+        *next_slot = (*next_slot).max(Instant::now() + duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_are_paced() {
+        let limiter = RateLimiter::new(20.0);
+        let started = Instant::now();
+
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+
+        // The first slot is free, the other four wait 50ms each
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_pause_delays_next_request() {
+        let limiter = RateLimiter::new(0.0);
+        limiter.pause(Duration::from_millis(100)).await;
+        let started = Instant::now();
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(90));
+        assert!(elapsed < Duration::from_millis(190));
+    }
+}
@@ -1,3 +1,6 @@
+use crate::config::DurationRounding;
+use crate::format::format_hm;
+use crate::http;
 use crate::screenpipe::Activity;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
@@ -19,6 +22,34 @@ pub struct SalesforceLoginResponse {
     pub instance_url: String,
 }
 
+/// Maximum number of records accepted by the sObject Collections API per call
+const MAX_COLLECTION_SIZE: usize = 200;
+
+/// Production login endpoint, used unless `with_login_url` says otherwise
+const DEFAULT_LOGIN_URL: &str = "https://login.salesforce.com";
+
+/// Custom object that time entries are written to
+pub const TIME_ENTRY_OBJECT: &str = "TimeEntry__c";
+
+/// Fields of `TIME_ENTRY_OBJECT` that `TimeEntry` maps to
+pub const TIME_ENTRY_FIELDS: &[&str] = &[
+    "Name",
+    "StartTime__c",
+    "DurationMinutes__c",
+    "Description__c",
+    "ExternalKey__c",
+];
+
+#[derive(Debug, Deserialize)]
+struct DescribeResponse {
+    fields: Vec<DescribeField>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DescribeField {
+    name: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct TimeEntry {
     #[serde(rename = "Name")]
@@ -29,10 +60,108 @@ pub struct TimeEntry {
     pub duration_minutes: f64,
     #[serde(rename = "Description__c")]
     pub description: String,
+    #[serde(rename = "ExternalKey__c", skip_serializing_if = "Option::is_none")]
+    pub external_key: Option<String>,
+}
+
+impl TimeEntry {
+    fn new(activity: &Activity, duration_minutes: f64) -> Self {
+        Self {
+            name: format!("Auto-tracked: {}", activity.app_name),
+            start_time: activity.timestamp.to_rfc3339(),
+            duration_minutes,
+            description: format!("{} - {}", activity.app_name, activity.window_title),
+            external_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordAttributes {
+    #[serde(rename = "type")]
+    pub object_type: String,
+}
+
+/// A time entry wrapped with the sObject type, as required by the Collections API
+#[derive(Debug, Serialize)]
+pub struct TimeEntryRecord {
+    pub attributes: RecordAttributes,
+    #[serde(flatten)]
+    pub entry: TimeEntry,
+}
+
+/// Request body for `/composite/sobjects` upserts
+#[derive(Debug, Serialize)]
+pub struct CollectionRequest {
+    #[serde(rename = "allOrNone")]
+    pub all_or_none: bool,
+    pub records: Vec<TimeEntryRecord>,
+}
+
+/// Per-record outcome returned by the Collections API
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct RecordResult {
+    pub id: Option<String>,
+    pub success: bool,
+    #[serde(default)]
+    pub created: Option<bool>,
+    #[serde(default)]
+    pub errors: Vec<RecordError>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct RecordError {
+    #[serde(rename = "statusCode")]
+    pub status_code: String,
+    pub message: String,
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+/// Stable external id for an activity so retried inserts upsert instead of duplicating
+fn external_key(activity: &Activity) -> String {
+    // FNV-1a over app and window title; stable across runs and Rust versions
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in activity
+        .app_name
+        .bytes()
+        .chain(std::iter::once(0))
+        .chain(activity.window_title.bytes())
+    {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    format!("wtje-{}-{:016x}", activity.timestamp.timestamp(), hash)
+}
+
+/// Build the upsert body for a batch of activities and their durations in minutes
+fn build_collection_request(entries: &[(&Activity, f64)]) -> CollectionRequest {
+    CollectionRequest {
+        all_or_none: false,
+        records: entries
+            .iter()
+            .map(|&(activity, minutes)| {
+                let mut entry = TimeEntry::new(activity, minutes);
+                entry.external_key = Some(external_key(activity));
+                TimeEntryRecord {
+                    attributes: RecordAttributes {
+                        object_type: TIME_ENTRY_OBJECT.to_string(),
+                    },
+                    entry,
+                }
+            })
+            .collect(),
+    }
 }
 
 pub struct SalesforceClient {
+    /// Org URL for data API calls
     instance_url: String,
+    /// Where the OAuth token is requested, e.g. test.salesforce.com for sandboxes
+    login_url: String,
     username: String,
     password: String,
     security_token: String,
@@ -40,6 +169,9 @@ pub struct SalesforceClient {
     client_secret: String,
     client: reqwest::Client,
     access_token: Option<String>,
+    dry_run: bool,
+    rounding: DurationRounding,
+    min_duration_minutes: f64,
 }
 
 impl SalesforceClient {
@@ -53,18 +185,58 @@ impl SalesforceClient {
     ) -> Self {
         Self {
             instance_url,
+            login_url: DEFAULT_LOGIN_URL.to_string(),
             username,
             password,
             security_token,
             client_id,
             client_secret,
-            client: reqwest::Client::new(),
+            client: http::default_client(),
             access_token: None,
+            dry_run: false,
+            rounding: DurationRounding::Exact,
+            min_duration_minutes: 0.0,
         }
     }
 
+    /// Use a shared client (see `http::build_client`) instead of a private one
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Log in through `login_url` (e.g. "https://test.salesforce.com" for a sandbox) while
+    /// data calls keep going to the instance URL
+    pub fn with_login_url(mut self, login_url: String) -> Self {
+        self.login_url = login_url;
+        self
+    }
+
+    /// Log would-be time entries instead of sending them (no login either)
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Round durations sent as minutes, and raise non-zero ones to at least `min_minutes`
+    pub fn with_duration_rounding(mut self, rounding: DurationRounding, min_minutes: f64) -> Self {
+        self.rounding = rounding;
+        self.min_duration_minutes = min_minutes;
+        self
+    }
+
+    /// Minutes sent for `secs` of work; 0 means the entry is skipped
+    fn duration_minutes(&self, secs: u64) -> f64 {
+        if secs == 0 {
+            return 0.0;
+        }
+        self.rounding
+            .to_minutes(secs)
+            .max(self.min_duration_minutes)
+    }
+
     async fn authenticate(&mut self) -> Result<()> {
-        let url = format!("{}/services/oauth2/token", self.instance_url);
+        let url = format!("{}/services/oauth2/token", self.login_url);
 
         let password_with_token = format!("{}{}", self.password, self.security_token);
 
@@ -101,6 +273,24 @@ impl SalesforceClient {
     }
 
     pub async fn log_time(&mut self, activity: &Activity) -> Result<()> {
+        let minutes = self.duration_minutes(activity.duration_secs);
+        if minutes == 0.0 {
+            log::debug!(
+                "Skipping '{}' for Salesforce: no time to log",
+                activity.window_title
+            );
+            return Ok(());
+        }
+        let time_entry = TimeEntry::new(activity, minutes);
+
+        if self.dry_run {
+            log::info!(
+                "[dry-run] Would log to Salesforce: {}",
+                serde_json::to_string(&time_entry)?
+            );
+            return Ok(());
+        }
+
         // Ensure we have a valid token
         if self.access_token.is_none() {
             self.authenticate().await?;
@@ -115,17 +305,10 @@ impl SalesforceClient {
         // Note: This uses a custom Time Entry object.
         // You may need to adjust this based on your Salesforce setup
         let url = format!(
-            "{}/services/data/v58.0/sobjects/TimeEntry__c",
-            self.instance_url
+            "{}/services/data/v58.0/sobjects/{}",
+            self.instance_url, TIME_ENTRY_OBJECT
         );
 
-        let time_entry = TimeEntry {
-            name: format!("Auto-tracked: {}", activity.app_name),
-            start_time: activity.timestamp.to_rfc3339(),
-            duration_minutes: activity.duration_secs as f64 / 60.0,
-            description: format!("{} - {}", activity.app_name, activity.window_title),
-        };
-
         let response = self
             .client
             .post(&url)
@@ -169,21 +352,173 @@ impl SalesforceClient {
                     );
                 }
 
-                log::info!(
-                    "Logged {} minutes to Salesforce",
-                    activity.duration_secs / 60
-                );
+                log::info!("Logged {} to Salesforce", format_hm(activity.duration_secs));
                 return Ok(());
             }
 
             anyhow::bail!("Salesforce API error ({}): {}", status, text);
         }
 
+        log::info!("Logged {} to Salesforce", format_hm(activity.duration_secs));
+        Ok(())
+    }
+
+    /// Upsert a batch of time entries via the sObject Collections API.
+    /// Records are keyed on `ExternalKey__c` so retries don't create duplicates.
+    /// Activities that round to zero minutes are skipped; the others are returned with
+    /// their result, in input order.
+    #[allow(dead_code)] // not called by the tracker yet
+    pub async fn log_time_batch<'a>(
+        &mut self,
+        activities: &'a [Activity],
+    ) -> Result<Vec<(&'a Activity, RecordResult)>> {
+        let entries: Vec<(&Activity, f64)> = activities
+            .iter()
+            .map(|activity| (activity, self.duration_minutes(activity.duration_secs)))
+            .filter(|&(_, minutes)| minutes > 0.0)
+            .collect();
+        if entries.len() < activities.len() {
+            log::debug!(
+                "Skipping {} Salesforce entries with no time to log",
+                activities.len() - entries.len()
+            );
+        }
+
+        if self.dry_run {
+            for chunk in entries.chunks(MAX_COLLECTION_SIZE) {
+                log::info!(
+                    "[dry-run] Would upsert to Salesforce: {}",
+                    serde_json::to_string(&build_collection_request(chunk))?
+                );
+            }
+            return Ok(entries
+                .iter()
+                .map(|&(activity, _)| {
+                    let result = RecordResult {
+                        id: None,
+                        success: true,
+                        created: None,
+                        errors: Vec::new(),
+                    };
+                    (activity, result)
+                })
+                .collect());
+        }
+
+        if self.access_token.is_none() {
+            self.authenticate().await?;
+        }
+
+        let url = format!(
+            "{}/services/data/v58.0/composite/sobjects/{}/ExternalKey__c",
+            self.instance_url, TIME_ENTRY_OBJECT
+        );
+
+        let mut results = Vec::with_capacity(entries.len());
+
+        for chunk in entries.chunks(MAX_COLLECTION_SIZE) {
+            let body = build_collection_request(chunk);
+
+            let token = self
+                .access_token
+                .as_ref()
+                .context("No access token available")?
+                .clone();
+
+            let mut response = self
+                .client
+                .patch(&url)
+                .bearer_auth(&token)
+                .json(&body)
+                .send()
+                .await
+                .context("Failed to upsert time entries to Salesforce")?;
+
+            // If unauthorized, try to re-authenticate once
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+                log::warn!("Salesforce token expired, re-authenticating...");
+                self.access_token = None;
+                self.authenticate().await?;
+
+                let new_token = self
+                    .access_token
+                    .as_ref()
+                    .context("No access token after re-authentication")?;
+
+                response = self
+                    .client
+                    .patch(&url)
+                    .bearer_auth(new_token)
+                    .json(&body)
+                    .send()
+                    .await
+                    .context("Failed to upsert time entries to Salesforce after re-auth")?;
+            }
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                anyhow::bail!("Salesforce API error ({}): {}", status, text);
+            }
+
+            let chunk_results: Vec<RecordResult> = response
+                .json()
+                .await
+                .context("Failed to parse Salesforce collection response")?;
+
+            results.extend(chunk_results);
+        }
+
+        let failed = results.iter().filter(|r| !r.success).count();
         log::info!(
-            "Logged {} minutes to Salesforce",
-            activity.duration_secs / 60
+            "Upserted {} time entries to Salesforce ({} failed)",
+            results.len() - failed,
+            failed
         );
-        Ok(())
+
+        Ok(entries
+            .into_iter()
+            .map(|(activity, _)| activity)
+            .zip(results)
+            .collect())
+    }
+
+    /// List the field names of an sObject. Requires a prior successful authentication.
+    pub async fn describe_object(&self, object: &str) -> Result<Vec<String>> {
+        let token = self
+            .access_token
+            .as_ref()
+            .context("Not authenticated with Salesforce")?;
+
+        let url = format!(
+            "{}/services/data/v58.0/sobjects/{}/describe",
+            self.instance_url, object
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .context("Failed to describe Salesforce object")?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            anyhow::bail!("Salesforce object {} does not exist", object);
+        }
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Salesforce describe error ({}): {}", status, text);
+        }
+
+        let describe: DescribeResponse = response
+            .json()
+            .await
+            .context("Failed to parse Salesforce describe response")?;
+
+        Ok(describe.fields.into_iter().map(|f| f.name).collect())
     }
 
     pub async fn health_check(&mut self) -> Result<bool> {
@@ -197,3 +532,187 @@ impl SalesforceClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn activity(secs: u64, title: &str) -> Activity {
+        Activity {
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap(),
+            duration_secs: secs,
+            window_title: title.to_string(),
+            app_name: "VS Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_sends_nothing() {
+        use axum::{routing::any, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = Router::new().fallback(any(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { "{}" }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let mut client = SalesforceClient::new(
+            format!("http://{}", addr),
+            "user".to_string(),
+            "password".to_string(),
+            "token".to_string(),
+            "client-id".to_string(),
+            "client-secret".to_string(),
+        )
+        .with_dry_run(true);
+
+        let entries = [activity(600, "PROJ-1"), activity(300, "PROJ-2")];
+        let results = client.log_time_batch(&entries).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, r)| r.success));
+        client.log_time(&entries[0]).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_login_and_data_go_to_their_own_hosts() {
+        use axum::{http::HeaderMap, routing::post, Json, Router};
+        use std::sync::{Arc, Mutex};
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let serve = |app: Router| async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            url
+        };
+
+        let login_calls = calls.clone();
+        let login_url = serve(Router::new().route(
+            "/services/oauth2/token",
+            post(move || async move {
+                login_calls.lock().unwrap().push("login");
+                Json(serde_json::json!({
+                    "access_token": "sandbox-token",
+                    "instance_url": "https://acme--uat.sandbox.my.salesforce.com"
+                }))
+            }),
+        ))
+        .await;
+        let data_calls = calls.clone();
+        let instance_url = serve(Router::new().route(
+            "/services/data/v58.0/sobjects/TimeEntry__c",
+            post(move |headers: HeaderMap| async move {
+                assert_eq!(headers["authorization"], "Bearer sandbox-token");
+                data_calls.lock().unwrap().push("data");
+                Json(serde_json::json!({ "id": "a01", "success": true }))
+            }),
+        ))
+        .await;
+
+        let mut client = SalesforceClient::new(
+            instance_url,
+            "user".to_string(),
+            "password".to_string(),
+            "token".to_string(),
+            "client-id".to_string(),
+            "client-secret".to_string(),
+        )
+        .with_login_url(login_url);
+        client.log_time(&activity(600, "PROJ-1")).await.unwrap();
+
+        // Each server only answers its own path, so a mix-up would fail the call above
+        assert_eq!(*calls.lock().unwrap(), vec!["login", "data"]);
+    }
+
+    #[test]
+    fn test_collection_request_body() {
+        let entries = [
+            activity(600, "PROJ-1 main.rs"),
+            activity(1200, "PROJ-2 lib.rs"),
+            activity(90, "PROJ-1 main.rs"),
+        ];
+
+        let entries: Vec<(&Activity, f64)> = entries
+            .iter()
+            .map(|activity| (activity, activity.duration_secs as f64 / 60.0))
+            .collect();
+        let body = serde_json::to_value(build_collection_request(&entries)).unwrap();
+
+        assert_eq!(body["allOrNone"], false);
+        let records = body["records"].as_array().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["attributes"]["type"], "TimeEntry__c");
+        assert_eq!(records[1]["DurationMinutes__c"], 20.0);
+        assert_eq!(records[1]["Description__c"], "VS Code - PROJ-2 lib.rs");
+
+        // Same app/title/start yields the same key so retries upsert the same record
+        let keys: Vec<&str> = records
+            .iter()
+            .map(|r| r["ExternalKey__c"].as_str().unwrap())
+            .collect();
+        assert_eq!(keys[0], keys[2]);
+        assert_ne!(keys[0], keys[1]);
+    }
+
+    fn client() -> SalesforceClient {
+        SalesforceClient::new(
+            "http://127.0.0.1:9".to_string(),
+            "user".to_string(),
+            "password".to_string(),
+            "token".to_string(),
+            "client-id".to_string(),
+            "client-secret".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_fractional_minutes_are_rounded() {
+        // 259s is 4.3167 minutes
+        assert_eq!(DurationRounding::Exact.to_minutes(259), 259.0 / 60.0);
+        assert_eq!(DurationRounding::Quarter.to_minutes(259), 4.25);
+        assert_eq!(DurationRounding::Quarter.to_minutes(270), 4.5);
+        assert_eq!(DurationRounding::Whole.to_minutes(259), 4.0);
+        assert_eq!(DurationRounding::Whole.to_minutes(270), 5.0);
+
+        let whole = client().with_duration_rounding(DurationRounding::Whole, 0.0);
+        assert_eq!(whole.duration_minutes(259), 4.0);
+        assert_eq!(whole.duration_minutes(20), 0.0);
+
+        let with_minimum = client().with_duration_rounding(DurationRounding::Quarter, 1.0);
+        assert_eq!(with_minimum.duration_minutes(20), 1.0);
+        assert_eq!(with_minimum.duration_minutes(259), 4.25);
+        assert_eq!(with_minimum.duration_minutes(0), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_zero_minute_entries_are_skipped() {
+        let mut client = client()
+            .with_dry_run(true)
+            .with_duration_rounding(DurationRounding::Whole, 0.0);
+
+        let entries = [
+            activity(600, "PROJ-1"),
+            activity(0, "PROJ-2"),
+            activity(20, "PROJ-3"),
+        ];
+        let results = client.log_time_batch(&entries).await.unwrap();
+
+        let sent: Vec<&str> = results
+            .iter()
+            .map(|(activity, _)| activity.window_title.as_str())
+            .collect();
+        assert_eq!(sent, vec!["PROJ-1"]);
+    }
+}
@@ -1,8 +1,9 @@
+use crate::http;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use log::debug;
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Activity {
@@ -11,11 +12,47 @@ pub struct Activity {
     pub window_title: String,
     pub app_name: String,
     pub description: String,
+    #[serde(default)]
+    pub browser_url: Option<String>,
+}
+
+/// Upper bound on the description kept when merging captures of the same window
+const MAX_MERGED_DESCRIPTION_CHARS: usize = 2000;
+
+impl Activity {
+    /// Fold another capture of the same window into this one: durations add up, the earliest
+    /// timestamp is kept, and the longer (more informative) description wins
+    pub fn merge(&mut self, other: &Activity) {
+        self.duration_secs += other.duration_secs;
+        self.timestamp = self.timestamp.min(other.timestamp);
+
+        if other.description.chars().count() > self.description.chars().count() {
+            self.description = other.description.clone();
+        }
+        // Also caps a first capture that was already longer than any merged into it
+        if self
+            .description
+            .chars()
+            .nth(MAX_MERGED_DESCRIPTION_CHARS)
+            .is_some()
+        {
+            self.description = self
+                .description
+                .chars()
+                .take(MAX_MERGED_DESCRIPTION_CHARS)
+                .collect();
+        }
+
+        if self.browser_url.is_none() {
+            self.browser_url = other.browser_url.clone();
+        }
+    }
 }
 
+/// Top-level search response; entries are kept raw so one bad entry can't fail the whole batch
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScreenpipeResponse {
-    pub data: Vec<ScreenpipeSearchEntry>,
+    pub data: Vec<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,27 +72,174 @@ pub struct ScreenpipeContent {
     pub browser_url: Option<String>,
 }
 
+/// Parse a Screenpipe search response, skipping entries that don't match the expected schema.
+/// Returns the parsed activities and the number of raw entries in the response.
+fn parse_activities(body: &str) -> Result<(Vec<Activity>, usize)> {
+    let response: ScreenpipeResponse = serde_json::from_str(body)
+        .with_context(|| format!("Failed to parse Screenpipe response: {}", body))?;
+
+    let total = response.data.len();
+    let activities: Vec<Activity> = response
+        .data
+        .into_iter()
+        .filter_map(
+            |raw| match serde_json::from_value::<ScreenpipeSearchEntry>(raw) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    debug!("Skipping malformed Screenpipe entry: {}", e);
+                    None
+                }
+            },
+        )
+        .map(|entry| {
+            let timestamp = entry
+                .content
+                .timestamp
+                .as_deref()
+                .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(Utc::now);
+
+            Activity {
+                timestamp,
+                duration_secs: 60,
+                window_title: entry.content.window_name.unwrap_or_default(),
+                app_name: entry.content.app_name.unwrap_or_default(),
+                description: entry.content.text.unwrap_or_default(),
+                browser_url: entry.content.browser_url.filter(|url| !url.is_empty()),
+            }
+        })
+        .collect();
+
+    let skipped = total - activities.len();
+    if skipped > 0 {
+        warn!(
+            "Skipped {} of {} malformed Screenpipe entries",
+            skipped, total
+        );
+    }
+
+    Ok((activities, total))
+}
+
+/// Number of entries requested per search page
+const PAGE_SIZE: usize = 100;
+
+/// Tries per page before a fetch fails, e.g. while the embedded server restarts
+const FETCH_ATTEMPTS: u32 = 3;
+
 pub struct ScreenpipeClient {
     base_url: String,
     client: reqwest::Client,
+    max_entries: usize,
+    /// Wait before the first retry of a failed page; doubled for each further retry
+    retry_delay: Duration,
 }
 
 impl ScreenpipeClient {
     pub fn new(base_url: String) -> Self {
         Self {
             base_url,
-            client: reqwest::Client::new(),
+            client: http::default_client(),
+            max_entries: 1000,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Use a shared client (see `http::build_client`) instead of a private one
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Cap the number of entries fetched per poll across all pages
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Activities captured in `since..until`. The fixed end keeps later pages from shifting
+    /// while we paginate, and lets callers start the next window exactly where this one ended.
+    pub async fn get_activities_between(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Activity>> {
+        let mut activities = Vec::new();
+        let mut offset = 0;
+
+        while offset < self.max_entries {
+            let limit = PAGE_SIZE.min(self.max_entries - offset);
+            let (page, entries) = self
+                .fetch_page_with_retry(since, until, offset, limit)
+                .await?;
+            activities.extend(page);
+
+            if entries < limit {
+                break;
+            }
+            offset += entries;
+        }
+
+        if offset >= self.max_entries {
+            warn!(
+                "Reached max_entries_per_poll ({}), some activities may be missing",
+                self.max_entries
+            );
+        }
+
+        Ok(activities)
+    }
+
+    /// `fetch_page`, retried with exponential backoff so a brief outage doesn't lose the window
+    async fn fetch_page_with_retry(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<Activity>, usize)> {
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+        loop {
+            match self.fetch_page(since, until, offset, limit).await {
+                Ok(page) => return Ok(page),
+                Err(e) if attempt < FETCH_ATTEMPTS => {
+                    warn!(
+                        "Screenpipe fetch failed (attempt {}/{}), retrying in {:?}: {:#}",
+                        attempt, FETCH_ATTEMPTS, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
         }
     }
 
-    pub async fn get_recent_activities(&self, since: DateTime<Utc>) -> Result<Vec<Activity>> {
+    /// Fetch one page of search results, returning the parsed activities and the raw entry count
+    async fn fetch_page(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<Activity>, usize)> {
         let url = format!("{}/search", self.base_url);
 
         // Screenpipe API parameters
         let params: HashMap<&str, String> = [
             ("start_timestamp", since.timestamp().to_string()),
-            ("end_timestamp", Utc::now().timestamp().to_string()),
-            ("limit", "100".to_string()),
+            ("end_timestamp", until.timestamp().to_string()),
+            ("limit", limit.to_string()),
+            ("offset", offset.to_string()),
         ]
         .iter()
         .cloned()
@@ -82,32 +266,7 @@ impl ScreenpipeClient {
 
         debug!("Screenpipe response payload: {}", body);
 
-        let screenpipe_response: ScreenpipeResponse = serde_json::from_str(&body)
-            .with_context(|| format!("Failed to parse Screenpipe response: {}", body))?;
-
-        let activities = screenpipe_response
-            .data
-            .into_iter()
-            .filter_map(|entry| {
-                let timestamp = entry
-                    .content
-                    .timestamp
-                    .as_deref()
-                    .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(Utc::now);
-
-                Some(Activity {
-                    timestamp,
-                    duration_secs: 60,
-                    window_title: entry.content.window_name.unwrap_or_default(),
-                    app_name: entry.content.app_name.unwrap_or_default(),
-                    description: entry.content.text.unwrap_or_default(),
-                })
-            })
-            .collect();
-
-        Ok(activities)
+        parse_activities(&body)
     }
 
     pub async fn health_check(&self) -> Result<bool> {
@@ -119,3 +278,150 @@ impl ScreenpipeClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_skips_malformed_entries() {
+        let body = r#"{
+            "data": [
+                {"type": "OCR", "content": {"frame_id": 1, "text": "fn main()", "timestamp": "2024-05-01T09:00:00Z", "app_name": "Code", "window_name": "PROJ-1 main.rs"}},
+                {"type": "OCR", "content": "not an object"},
+                {"content": {"app_name": "missing type"}},
+                {"type": "OCR", "content": {"frame_id": "not a number"}},
+                {"type": "OCR", "content": {"app_name": "Firefox", "window_name": "PROJ-2", "browser_url": "https://example.com"}}
+            ]
+        }"#;
+
+        let (activities, total) = parse_activities(body).unwrap();
+        assert_eq!(total, 5);
+        assert_eq!(activities.len(), 2);
+        assert_eq!(activities[0].window_title, "PROJ-1 main.rs");
+        assert_eq!(activities[0].description, "fn main()");
+        assert_eq!(activities[1].app_name, "Firefox");
+        assert_eq!(
+            activities[1].browser_url.as_deref(),
+            Some("https://example.com")
+        );
+    }
+
+    fn capture(minutes_ago: i64, description: &str) -> Activity {
+        Activity {
+            timestamp: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            duration_secs: 60,
+            window_title: "PROJ-1 main.rs".to_string(),
+            app_name: "Code".to_string(),
+            description: description.to_string(),
+            browser_url: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_keeps_earliest_timestamp_and_longest_description() {
+        let mut merged = capture(5, "fn main()");
+        let earlier = capture(10, "short");
+        let later = capture(1, "fn main() { println!(\"hello\"); }");
+
+        merged.merge(&earlier);
+        merged.merge(&later);
+
+        assert_eq!(merged.timestamp, earlier.timestamp);
+        assert_eq!(merged.duration_secs, 180);
+        assert_eq!(merged.description, later.description);
+    }
+
+    #[test]
+    fn test_merge_bounds_description_growth() {
+        let mut merged = capture(0, "");
+        for _ in 0..50 {
+            merged.merge(&capture(0, &"x".repeat(MAX_MERGED_DESCRIPTION_CHARS * 2)));
+        }
+        assert_eq!(
+            merged.description.chars().count(),
+            MAX_MERGED_DESCRIPTION_CHARS
+        );
+
+        // A long first capture is capped too, even when the merged one is shorter
+        let mut merged = capture(0, &"x".repeat(MAX_MERGED_DESCRIPTION_CHARS * 2));
+        merged.merge(&capture(0, "short"));
+        assert_eq!(
+            merged.description.chars().count(),
+            MAX_MERGED_DESCRIPTION_CHARS
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_envelope() {
+        assert!(parse_activities("not json").is_err());
+    }
+
+    /// Serve `total` OCR entries from /search honoring limit/offset, counting requests
+    async fn serve_entries(
+        total: usize,
+    ) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use axum::{extract::Query, routing::get, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let requests = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        let app = Router::new().route(
+            "/search",
+            get(move |Query(params): Query<HashMap<String, usize>>| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let offset = params.get("offset").copied().unwrap_or(0);
+                    let limit = params.get("limit").copied().unwrap_or(PAGE_SIZE);
+                    let data: Vec<serde_json::Value> = (offset..total.min(offset + limit))
+                        .map(|i| {
+                            serde_json::json!({
+                                "type": "OCR",
+                                "content": {"app_name": "Code", "window_name": format!("entry {}", i)}
+                            })
+                        })
+                        .collect();
+                    Json(serde_json::json!({ "data": data }))
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        (format!("http://{}", addr), requests)
+    }
+
+    #[tokio::test]
+    async fn test_pagination_stitches_pages() {
+        let (url, requests) = serve_entries(130).await;
+        let client = ScreenpipeClient::new(url);
+
+        let activities = client
+            .get_activities_between(Utc::now(), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(activities.len(), 130);
+        assert_eq!(activities[0].window_title, "entry 0");
+        assert_eq!(activities[129].window_title, "entry 129");
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pagination_respects_max_entries() {
+        let (url, requests) = serve_entries(1000).await;
+        let client = ScreenpipeClient::new(url).with_max_entries(250);
+
+        let activities = client
+            .get_activities_between(Utc::now(), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(activities.len(), 250);
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+}
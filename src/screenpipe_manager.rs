@@ -1,13 +1,58 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::Duration;
 use tracing::{debug, info, warn};
 
+/// How many ports after the requested one to try when it is already taken
+const PORT_SEARCH_RANGE: u16 = 20;
+
+/// Delay between health probes while waiting for the server to come up
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where users can install Screenpipe by hand
+pub(crate) const SCREENPIPE_INSTALL_URL: &str = "https://github.com/mediar-ai/screenpipe";
+
+/// File name of the captured Screenpipe output inside its data directory
+const LOG_FILE_NAME: &str = "screenpipe.log";
+
+/// Number of rotated `screenpipe.log.N` files kept next to the live log
+const LOG_ROTATIONS: usize = 3;
+
+/// Path of the captured Screenpipe output for a given data directory
+pub fn log_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(LOG_FILE_NAME)
+}
+
+/// Screenpipe's binary is missing and was not installed, because installing was declined or
+/// failed; find it with `err.downcast_ref::<ScreenpipeNotInstalled>()`
+#[derive(Debug, thiserror::Error)]
+#[error("{reason}")]
+pub struct ScreenpipeNotInstalled {
+    pub reason: String,
+}
+
 /// Manages the embedded Screenpipe server lifecycle as a subprocess
 pub struct ScreenpipeManager {
     process: Option<Child>,
     data_dir: PathBuf,
+    port: u16,
+    startup_timeout: Duration,
+    /// Rotation size for `screenpipe.log`; `None` discards the output
+    log_max_bytes: Option<u64>,
+    /// Threads copying the child's stdout/stderr into the log file
+    log_pumps: Vec<JoinHandle<()>>,
+    /// Run the install script without prompting when the binary is missing
+    auto_install: bool,
+    /// Whether the server on `port` was already running and is owned by someone else
+    externally_managed: bool,
+    /// Kills the server (and anything it spawned) if this process dies without stopping it
+    #[cfg(windows)]
+    job: Option<crate::process_job::ProcessJob>,
 }
 
 impl ScreenpipeManager {
@@ -15,11 +60,69 @@ impl ScreenpipeManager {
         Self {
             process: None,
             data_dir: PathBuf::new(),
+            port: 0,
+            startup_timeout: Duration::from_secs(30),
+            log_max_bytes: None,
+            log_pumps: Vec::new(),
+            auto_install: false,
+            externally_managed: false,
+            #[cfg(windows)]
+            job: None,
         }
     }
 
-    /// Start the embedded Screenpipe server as a subprocess
-    pub async fn start(&mut self, data_dir: PathBuf, port: u16) -> Result<()> {
+    /// A manager for a Screenpipe someone else runs on `port`: nothing is checked or started,
+    /// and `stop` leaves it running
+    pub fn external(port: u16) -> Self {
+        let mut manager = Self::new();
+        manager.port = port;
+        manager.externally_managed = true;
+        manager
+    }
+
+    /// Install Screenpipe without asking for confirmation if it can't be found
+    pub fn with_auto_install(mut self, auto_install: bool) -> Self {
+        self.auto_install = auto_install;
+        self
+    }
+
+    /// Capture the server's stdout/stderr to `screenpipe.log`, rotating at `max_bytes`
+    pub fn with_log_file(mut self, enabled: bool, max_bytes: u64) -> Self {
+        self.log_max_bytes = enabled.then_some(max_bytes);
+        self
+    }
+
+    /// How long `start` waits for the server to report healthy before giving up
+    pub fn with_startup_timeout(mut self, startup_timeout_secs: u64) -> Self {
+        self.startup_timeout = Duration::from_secs(startup_timeout_secs);
+        self
+    }
+
+    /// Port the server was started on, which may differ from the requested one
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Whether an already-running Screenpipe was reused instead of spawning our own
+    pub fn is_external(&self) -> bool {
+        self.externally_managed
+    }
+
+    /// Start the embedded Screenpipe server as a subprocess.
+    /// A healthy Screenpipe already listening on `port` is reused as-is; otherwise, if `port`
+    /// is in use, the next free port is used. Returns the port the server is reachable on.
+    pub async fn start(&mut self, data_dir: PathBuf, port: u16) -> Result<u16> {
+        if is_healthy(&format!("http://localhost:{}/health", port)).await {
+            info!("Screenpipe is already running on port {}, reusing it", port);
+            self.process = None;
+            self.externally_managed = true;
+            self.port = port;
+            return Ok(port);
+        }
+        self.externally_managed = false;
+
+        let port = find_free_port(port)?;
+        self.port = port;
         info!("Starting embedded Screenpipe server on port {}", port);
 
         self.data_dir = data_dir.clone();
@@ -32,95 +135,96 @@ impl ScreenpipeManager {
 
         info!("Found Screenpipe binary at: {:?}", screenpipe_path);
 
+        let log = match self.log_max_bytes {
+            Some(max_bytes) => {
+                let path = log_path(&data_dir);
+                info!("Writing Screenpipe output to {}", path.display());
+                Some(Arc::new(Mutex::new(RotatingLog::open(path, max_bytes)?)))
+            }
+            None => None,
+        };
+        let output = || {
+            if log.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            }
+        };
+
         // Start screenpipe process
-        let process = Command::new(screenpipe_path)
+        let mut process = Command::new(screenpipe_path)
             .arg("--port")
             .arg(port.to_string())
             .arg("--data-dir")
             .arg(data_dir.to_string_lossy().to_string())
             .arg("--disable-audio") // Simplify by disabling audio initially
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+            .stdout(output())
+            .stderr(output())
             .spawn()
             .context("Failed to start Screenpipe process")?;
 
+        if let Some(log) = log {
+            if let Some(stdout) = process.stdout.take() {
+                self.log_pumps.push(spawn_log_pump(stdout, log.clone()));
+            }
+            if let Some(stderr) = process.stderr.take() {
+                self.log_pumps.push(spawn_log_pump(stderr, log));
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            let job = crate::process_job::ProcessJob::new()
+                .and_then(|job| job.assign(&process).map(|_| job));
+            match job {
+                Ok(job) => self.job = Some(job),
+                Err(e) => warn!("Screenpipe may outlive this process: {:#}", e),
+            }
+        }
+
         self.process = Some(process);
 
         // Verify the server is running, allowing extra time for startup
-        let client = reqwest::Client::new();
         let health_url = format!("http://localhost:{}/health", port);
-        let startup_timeout = Duration::from_secs(30);
-        let poll_interval = Duration::from_secs(1);
-        let start_time = std::time::Instant::now();
-
-        loop {
-            match client.get(&health_url).send().await {
-                Ok(resp) if resp.status().is_success() => {
-                    info!("Screenpipe server started successfully and is healthy");
-                    return Ok(());
-                }
-                Ok(resp) => {
-                    if start_time.elapsed() >= startup_timeout {
-                        self.stop().await?;
-                        return Err(anyhow::anyhow!(
-                            "Screenpipe server health check failed with status: {}",
-                            resp.status()
-                        ));
-                    }
-                    debug!(
-                        "Waiting for Screenpipe to become ready: health endpoint returned {}",
-                        resp.status()
-                    );
-                }
-                Err(e) => {
-                    if start_time.elapsed() >= startup_timeout {
-                        self.stop().await?;
-                        return Err(anyhow::anyhow!(
-                            "Failed to connect to Screenpipe server: {}",
-                            e
-                        ));
-                    }
-                    debug!("Waiting for Screenpipe to become ready: {}", e);
-                }
-            }
-
-            tokio::time::sleep(poll_interval).await;
+        if let Err(e) =
+            wait_until_healthy(&health_url, self.startup_timeout, HEALTH_POLL_INTERVAL).await
+        {
+            self.stop().await?;
+            return Err(e);
         }
+
+        info!("Screenpipe server started successfully and is healthy");
+        Ok(port)
     }
 
-    /// Find the Screenpipe binary in various locations
+    /// Find the Screenpipe binary, offering to install it if it is missing
     fn find_screenpipe_binary(&self) -> Result<PathBuf> {
-        // Try multiple locations where screenpipe might be installed
-        let possible_paths = vec![
-            // In system PATH
-            which::which("screenpipe").ok(),
-            // Common installation locations
-            Some(PathBuf::from("/usr/local/bin/screenpipe")),
-            Some(PathBuf::from("/usr/bin/screenpipe")),
-            // In user's home directory
-            dirs::home_dir().map(|h| h.join(".cargo/bin/screenpipe")),
-            dirs::home_dir().map(|h| h.join(".local/bin/screenpipe")),
-            // Windows locations
-            dirs::home_dir().map(|h| h.join("AppData/Local/screenpipe/screenpipe.exe")),
-            // macOS locations
-            Some(PathBuf::from(
-                "/Applications/screenpipe.app/Contents/MacOS/screenpipe",
-            )),
-        ];
-
-        for path in possible_paths.into_iter().flatten() {
-            if path.exists() {
-                return Ok(path);
-            }
+        if let Some(path) = find_installed_binary() {
+            return Ok(path);
         }
 
-        // If not found, try to install it
-        self.install_screenpipe()
+        // If not found, offer to install it
+        let auto_install = self.auto_install;
+        self.install_screenpipe(|| auto_install || confirm_install())
+            .map_err(|e| {
+                ScreenpipeNotInstalled {
+                    reason: format!("{:#}", e),
+                }
+                .into()
+            })
     }
 
-    /// Install Screenpipe using the install script
-    fn install_screenpipe(&self) -> Result<PathBuf> {
-        info!("Screenpipe not found, attempting to install...");
+    /// Install Screenpipe using the install script, once `confirm` agrees to it
+    fn install_screenpipe(&self, confirm: impl FnOnce() -> bool) -> Result<PathBuf> {
+        if !confirm() {
+            return Err(anyhow::anyhow!(
+                "Screenpipe is not installed. Install it manually from {}, \
+                 or rerun with --yes (or set screenpipe.auto_install_screenpipe = true) to install it automatically",
+                SCREENPIPE_INSTALL_URL
+            ));
+        }
+
+        info!("Screenpipe not found, running the install script...");
 
         #[cfg(unix)]
         {
@@ -133,15 +237,11 @@ impl ScreenpipeManager {
 
             if !output.status.success() {
                 return Err(anyhow::anyhow!(
-                    "Screenpipe installation failed: {}",
+                    "Screenpipe installation failed, install it manually from {}: {}",
+                    SCREENPIPE_INSTALL_URL,
                     String::from_utf8_lossy(&output.stderr)
                 ));
             }
-
-            // Try to find it again
-            if let Ok(path) = which::which("screenpipe") {
-                return Ok(path);
-            }
         }
 
         #[cfg(windows)]
@@ -157,19 +257,29 @@ impl ScreenpipeManager {
 
             if !output.status.success() {
                 return Err(anyhow::anyhow!(
-                    "Screenpipe installation failed: {}",
+                    "Screenpipe installation failed, install it manually from {}: {}",
+                    SCREENPIPE_INSTALL_URL,
                     String::from_utf8_lossy(&output.stderr)
                 ));
             }
         }
 
-        Err(anyhow::anyhow!(
-            "Failed to install Screenpipe automatically. Please install it manually from https://github.com/mediar-ai/screenpipe"
-        ))
+        // Look again, including install locations that may not be on PATH yet
+        find_installed_binary().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Screenpipe install script finished but the binary was not found. Please install it manually from {}",
+                SCREENPIPE_INSTALL_URL
+            )
+        })
     }
 
     /// Stop the embedded Screenpipe server
     pub async fn stop(&mut self) -> Result<()> {
+        if self.externally_managed {
+            info!("Leaving externally managed Screenpipe running");
+            return Ok(());
+        }
+
         info!("Stopping embedded Screenpipe server");
 
         if let Some(mut process) = self.process.take() {
@@ -185,8 +295,13 @@ impl ScreenpipeManager {
 
             #[cfg(windows)]
             {
-                // On Windows, just kill the process
-                let _ = process.kill();
+                // No SIGTERM on Windows: end the whole job so Screenpipe's own children go too
+                match self.job.take() {
+                    Some(job) => job.terminate(),
+                    None => {
+                        let _ = process.kill();
+                    }
+                }
             }
 
             // Wait for the process to exit
@@ -211,14 +326,389 @@ impl ScreenpipeManager {
             }
         }
 
+        // The pumps finish once the child's pipes close; joining them drops the last log handle
+        let pumps = std::mem::take(&mut self.log_pumps);
+        if !pumps.is_empty() {
+            let join = tokio::task::spawn_blocking(move || {
+                for pump in pumps {
+                    let _ = pump.join();
+                }
+            });
+            if tokio::time::timeout(Duration::from_secs(2), join)
+                .await
+                .is_err()
+            {
+                warn!("Screenpipe log file was not closed: output pipes still open");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Append-only log file that is rotated to `<name>.1`, `<name>.2`, ... once it exceeds `max_bytes`
+struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    file: File,
+    written: u64,
+}
+
+impl RotatingLog {
+    fn open(path: PathBuf, max_bytes: u64) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open Screenpipe log file {}", path.display()))?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+
+        Ok(Self {
+            path,
+            max_bytes,
+            file,
+            written,
+        })
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+
+        for n in (1..LOG_ROTATIONS).rev() {
+            let from = rotated(n);
+            if from.exists() {
+                std::fs::rename(&from, rotated(n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated(1))?;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.written = 0;
         Ok(())
     }
 }
 
+/// Copy lines from a child output stream into the shared log until the stream closes
+fn spawn_log_pump<R: Read + Send + 'static>(
+    stream: R,
+    log: Arc<Mutex<RotatingLog>>,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let mut log = log.lock().unwrap_or_else(|e| e.into_inner());
+                    if let Err(e) = log.write_line(&line) {
+                        warn!("Failed to write Screenpipe log: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Read the last `lines` lines of a log file
+pub fn tail_log(path: &Path, lines: usize) -> Result<Vec<String>> {
+    let content = std::fs::read(path)
+        .with_context(|| format!("Failed to read Screenpipe log file {}", path.display()))?;
+    let content = String::from_utf8_lossy(&content);
+    let all: Vec<&str> = content.lines().collect();
+
+    Ok(all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// Find an installed Screenpipe binary in various locations
+pub(crate) fn find_installed_binary() -> Option<PathBuf> {
+    // Try multiple locations where screenpipe might be installed
+    let possible_paths = vec![
+        // In system PATH
+        which::which("screenpipe").ok(),
+        // Common installation locations
+        Some(PathBuf::from("/usr/local/bin/screenpipe")),
+        Some(PathBuf::from("/usr/bin/screenpipe")),
+        // In user's home directory
+        dirs::home_dir().map(|h| h.join(".cargo/bin/screenpipe")),
+        dirs::home_dir().map(|h| h.join(".local/bin/screenpipe")),
+        // Windows locations
+        dirs::home_dir().map(|h| h.join("AppData/Local/screenpipe/screenpipe.exe")),
+        // macOS locations
+        Some(PathBuf::from(
+            "/Applications/screenpipe.app/Contents/MacOS/screenpipe",
+        )),
+    ];
+
+    possible_paths
+        .into_iter()
+        .flatten()
+        .find(|path| path.exists())
+}
+
+/// Ask on the terminal whether to run the Screenpipe installer; declines when not interactive
+fn confirm_install() -> bool {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        warn!("Screenpipe is not installed and no terminal is available to confirm installation");
+        return false;
+    }
+
+    print!(
+        "Screenpipe was not found. Download and run its install script from {}? [y/N] ",
+        SCREENPIPE_INSTALL_URL
+    );
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Probe `health_url` once
+async fn is_healthy(health_url: &str) -> bool {
+    let Ok(client) = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+    else {
+        return false;
+    };
+
+    matches!(client.get(health_url).send().await, Ok(resp) if resp.status().is_success())
+}
+
+/// Poll `health_url` until it returns success or `timeout` elapses
+async fn wait_until_healthy(
+    health_url: &str,
+    timeout: Duration,
+    poll_interval: Duration,
+) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(poll_interval.max(Duration::from_secs(2)))
+        .build()
+        .context("Failed to create HTTP client")?;
+    let start_time = std::time::Instant::now();
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        let last_error = match client.get(health_url).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                debug!("Screenpipe healthy after {} attempt(s)", attempts);
+                return Ok(());
+            }
+            Ok(resp) => format!("health endpoint returned {}", resp.status()),
+            Err(e) => e.to_string(),
+        };
+
+        let elapsed = start_time.elapsed();
+        if elapsed >= timeout {
+            return Err(anyhow::anyhow!(
+                "Screenpipe server did not become healthy within {}s: {}",
+                timeout.as_secs(),
+                last_error
+            ));
+        }
+
+        // Log progress every few seconds rather than on every probe
+        if attempts % 10 == 0 {
+            info!(
+                "Still waiting for Screenpipe to become ready ({}s elapsed): {}",
+                elapsed.as_secs(),
+                last_error
+            );
+        } else {
+            debug!("Waiting for Screenpipe to become ready: {}", last_error);
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Find the first port at or after `preferred` that can be bound on localhost
+fn find_free_port(preferred: u16) -> Result<u16> {
+    for port in preferred..=preferred.saturating_add(PORT_SEARCH_RANGE) {
+        if std::net::TcpListener::bind(("127.0.0.1", port)).is_ok() {
+            if port != preferred {
+                warn!(
+                    "Port {} is in use, using port {} for Screenpipe",
+                    preferred, port
+                );
+            }
+            return Ok(port);
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "No free port found for Screenpipe between {} and {}",
+        preferred,
+        preferred.saturating_add(PORT_SEARCH_RANGE)
+    ))
+}
+
 impl Drop for ScreenpipeManager {
     fn drop(&mut self) {
         if let Some(mut process) = self.process.take() {
             let _ = process.kill();
         }
+        // Closing the job kills anything Screenpipe left behind
+        #[cfg(windows)]
+        drop(self.job.take());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declined_install_points_to_manual_install() {
+        let manager = ScreenpipeManager::new();
+        let err = manager
+            .install_screenpipe(|| false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not installed"));
+        assert!(err.contains(SCREENPIPE_INSTALL_URL));
+        assert!(err.contains("--yes"));
+    }
+
+    #[tokio::test]
+    async fn test_external_manager_starts_and_stops_nothing() {
+        let mut manager = ScreenpipeManager::external(3035);
+        assert!(manager.is_external());
+        assert_eq!(manager.port(), 3035);
+        manager.stop().await.unwrap();
+        assert!(manager.process.is_none());
+    }
+
+    #[test]
+    fn test_log_rotates_at_max_bytes() {
+        let dir = std::env::temp_dir().join(format!("screenpipe-log-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = log_path(&dir);
+
+        let mut log = RotatingLog::open(path.clone(), 20).unwrap();
+        for i in 0..10 {
+            log.write_line(format!("line {}\n", i).as_bytes()).unwrap();
+        }
+
+        // 7-byte lines, 2 per file: the live log holds the last two
+        assert_eq!(tail_log(&path, 10).unwrap(), vec!["line 8", "line 9"]);
+        assert_eq!(
+            tail_log(&dir.join("screenpipe.log.1"), 10).unwrap(),
+            vec!["line 6", "line 7"]
+        );
+        assert!(dir
+            .join(format!("screenpipe.log.{}", LOG_ROTATIONS))
+            .exists());
+        assert!(!dir
+            .join(format!("screenpipe.log.{}", LOG_ROTATIONS + 1))
+            .exists());
+
+        assert_eq!(tail_log(&path, 1).unwrap(), vec!["line 9"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_free_port_skips_taken_port() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        let found = find_free_port(port).unwrap();
+        assert_ne!(found, port);
+        assert!(found > port && found <= port + PORT_SEARCH_RANGE);
+    }
+
+    /// Serve /health that fails until it has been probed `failures` times
+    async fn serve_health(failures: usize) -> String {
+        use axum::{http::StatusCode, routing::get, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let probes = Arc::new(AtomicUsize::new(0));
+        let app = Router::new().route(
+            "/health",
+            get(move || {
+                let probes = probes.clone();
+                async move {
+                    if probes.fetch_add(1, Ordering::SeqCst) < failures {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        format!("http://{}/health", addr)
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_late_healthy_server() {
+        let url = serve_health(3).await;
+        let result =
+            wait_until_healthy(&url, Duration::from_secs(5), Duration::from_millis(10)).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reuses_running_instance_and_leaves_it_on_stop() {
+        let url = serve_health(0).await;
+        let port: u16 = url
+            .trim_end_matches("/health")
+            .rsplit(':')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let mut manager = ScreenpipeManager::new();
+        let bound = manager
+            .start(std::env::temp_dir().join("screenpipe-reuse-test"), port)
+            .await
+            .unwrap();
+        assert_eq!(bound, port);
+        assert!(manager.is_external());
+        assert!(manager.process.is_none());
+
+        manager.stop().await.unwrap();
+        assert!(is_healthy(&url).await);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_timeout() {
+        let url = serve_health(usize::MAX).await;
+        let result =
+            wait_until_healthy(&url, Duration::from_millis(100), Duration::from_millis(10)).await;
+        assert!(result.is_err());
     }
 }
@@ -204,6 +204,27 @@ impl Default for StateManager {
     }
 }
 
+/// What the tracker loop last saw, shared with the daemon so clients can tell a quiet
+/// Screenpipe from an idle user
+#[derive(Debug, Clone, Copy)]
+pub struct TrackerStatus {
+    pub state: TrackingState,
+    /// End of the last window fetched from Screenpipe
+    pub last_sync: Option<DateTime<Utc>>,
+    /// End of the latest capture Screenpipe returned
+    pub last_activity_at: Option<DateTime<Utc>>,
+}
+
+impl Default for TrackerStatus {
+    fn default() -> Self {
+        Self {
+            state: TrackingState::Stopped,
+            last_sync: None,
+            last_activity_at: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,16 +1,41 @@
 use crate::{
-    config::Config,
-    database::{ActivityTier, Database},
-    jira::JiraClient,
-    llm::LLMAnalyzer,
-    salesforce::SalesforceClient,
+    config::{
+        Config, ConsolidationMode, LLMConfig, NotificationsConfig, TrackingConfig, WorkingHours,
+    },
+    database::{
+        ActivitySource, ActivityTier, AttributionSource, Database, FlagKind, StoredActivity,
+        StoredWorklog,
+    },
+    detection::{ChainDetector, IssueDetector},
+    events::{self, TrackerEvent},
+    format::{format_hm, format_hms},
+    heartbeat, issue_key,
+    jira::{build_issue_key_regex, detect_issue_key, IssueNotFound, JiraClient},
+    llm::{
+        chunk_activities, merge_analyses, AnalysisResult, AssignedIssue, IssueMatch, LLMAnalyzer,
+    },
+    metrics::{Failure, Metrics},
+    notifications::SummaryNotifier,
+    nudge::NudgeManager,
+    salesforce::{SalesforceClient, TIME_ENTRY_FIELDS, TIME_ENTRY_OBJECT},
     screenpipe::{Activity, ScreenpipeClient},
-    state::{StateManager, TrackingState},
+    state::{StateManager, TrackerStatus, TrackingState},
+    work_type::WorkType,
 };
-use anyhow::{Context, Result};
-use chrono::{DateTime, Duration, Utc};
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
-use tokio::sync::RwLock;
+use anyhow::Result;
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
+use tokio::sync::{broadcast, watch, RwLock};
+
+/// Assigned-issues cache, kept next to the analytics database so restarts don't refetch it
+const ASSIGNED_ISSUES_CACHE_FILE: &str = "assigned_issues_cache.json";
 
 pub struct WorkTracker {
     config: Config,
@@ -18,55 +43,146 @@ pub struct WorkTracker {
     jira: Option<JiraClient>,
     salesforce: Option<SalesforceClient>,
     llm_analyzer: Option<LLMAnalyzer>,
+    /// Finds issues for activities when the LLM batch analysis is off
+    detector: Box<dyn IssueDetector>,
     database: Database,
     pub state_manager: Arc<RwLock<StateManager>>,
     last_sync: DateTime<Utc>,
+    /// Captures fetched recently, so the overlap between polls isn't stored twice
+    seen_captures: HashSet<CaptureKey>,
     last_llm_analysis: DateTime<Utc>,
     issue_override: Arc<RwLock<Option<String>>>,
+    nudges: NudgeManager,
+    summaries: SummaryNotifier,
+    /// Break recorded for an ongoing idle gap, ended when activity resumes
+    idle_break: Option<i64>,
+    /// Break opened while `tracking.break_apps` dominated the captures
+    app_break: Option<i64>,
+    /// Live updates for daemon clients
+    events: broadcast::Sender<TrackerEvent>,
+    /// Tracking settings changed at runtime (e.g. via the daemon API), picked up each poll
+    tracking_settings: Option<Arc<RwLock<TrackingConfig>>>,
+    /// Number of batch analyses started, for diagnostics
+    analyses_started: u64,
+    /// Random delay applied to every scheduled analysis of this tracker
+    llm_jitter_secs: i64,
+    /// Issues that turned out to be deleted or moved during the current analysis
+    missing_issues: MissingIssues,
+    /// Worklogs posted (or, in dry-run mode, that would have been) by the current analysis
+    worklogs_logged: usize,
+    /// State and last sync/activity times, read by the daemon's `/status`
+    status: Arc<RwLock<TrackerStatus>>,
+    /// File rewritten on every loop iteration, if set
+    heartbeat: Option<PathBuf>,
+    /// Counters for the daemon's `/metrics`
+    metrics: Arc<Metrics>,
 }
 
 impl WorkTracker {
     pub fn new(config: Config, issue_override: Arc<RwLock<Option<String>>>) -> Result<Self> {
-        let screenpipe = ScreenpipeClient::new(config.screenpipe.url.clone());
+        let database = Self::open_database(&config)?;
+        Self::with_database(config, issue_override, database)
+    }
+
+    /// Open the analytics database with the tiering, redaction, size limits and category
+    /// rules from `config`
+    pub fn open_database(config: &Config) -> Result<Database> {
+        Database::new(crate::paths::database_path(config)?)?
+            .with_micro_threshold(config.tracking.micro_activity_threshold_secs)
+            .with_redaction_patterns(&config.analytics.redaction_patterns)?
+            .with_max_description_chars(config.analytics.max_description_chars)
+            .with_nonbillable_apps(&config.tracking.nonbillable_apps)
+            .with_category_rules(&config.tracking.category_rules)
+    }
+
+    /// Jira client configured from `config`, or `None` when the Jira integration is off
+    pub fn open_jira(config: &Config, http: reqwest::Client) -> Result<Option<JiraClient>> {
+        if !config.jira.enabled {
+            return Ok(None);
+        }
 
-        let jira = if config.jira.enabled {
-            Some(JiraClient::new(
+        let timezone = jira_timezone(config)?;
+
+        Ok(Some(
+            JiraClient::new(
                 config.jira.url.clone(),
                 config.jira.email.clone(),
                 config.jira.api_token.clone(),
-            ))
-        } else {
-            None
-        };
+            )
+            .with_client(http)
+            .with_project_keys(&config.jira.project_keys)
+            .with_dry_run(config.tracking.dry_run)
+            .with_comment_template(config.jira.worklog_comment_template.clone())
+            .with_timezone(timezone)
+            .with_rate_limit(config.jira.max_requests_per_second)
+            .with_tempo(config.jira.use_tempo)
+            .with_search_api(config.jira.search_api)
+            .with_worklog_attributes(
+                config.jira.worklog_attribute_key.clone(),
+                config.jira.worklog_attributes.clone(),
+            )
+            .with_cache_file(
+                crate::paths::database_path(config)?.with_file_name(ASSIGNED_ISSUES_CACHE_FILE),
+            ),
+        ))
+    }
+
+    /// Create a tracker sharing an existing database pool (e.g. with daemon handlers),
+    /// usually opened with `open_database`
+    pub fn with_database(
+        config: Config,
+        issue_override: Arc<RwLock<Option<String>>>,
+        database: Database,
+    ) -> Result<Self> {
+        let http = crate::http::build_client(&config.network)?;
+        let screenpipe = ScreenpipeClient::new(config.screenpipe.url())
+            .with_client(http.clone())
+            .with_max_entries(config.screenpipe.max_entries_per_poll);
+
+        let jira = Self::open_jira(&config, http.clone())?;
 
         let salesforce = if config.salesforce.enabled {
-            Some(SalesforceClient::new(
-                config.salesforce.instance_url.clone(),
-                config.salesforce.username.clone(),
-                config.salesforce.password.clone(),
-                config.salesforce.security_token.clone(),
-                config.salesforce.client_id.clone(),
-                config.salesforce.client_secret.clone(),
-            ))
+            Some(
+                SalesforceClient::new(
+                    config.salesforce.instance_url.clone(),
+                    config.salesforce.username.clone(),
+                    config.salesforce.password.clone(),
+                    config.salesforce.security_token.clone(),
+                    config.salesforce.client_id.clone(),
+                    config.salesforce.client_secret.clone(),
+                )
+                .with_client(http.clone())
+                .with_login_url(config.salesforce.login_url_or_default())
+                .with_dry_run(config.tracking.dry_run)
+                .with_duration_rounding(
+                    config.salesforce.duration_rounding,
+                    config.salesforce.min_duration_minutes,
+                ),
+            )
         } else {
             None
         };
 
         let llm_analyzer = if config.llm.enabled {
-            Some(LLMAnalyzer::new(
-                config.llm.endpoint.clone(),
-                config.llm.api_key.clone(),
-                config.llm.timeout_secs,
-            )?)
+            Some(
+                LLMAnalyzer::new(
+                    config.llm.endpoint.clone(),
+                    config.llm.api_key.clone(),
+                    config.llm.timeout_secs,
+                )?
+                .with_client(http.clone())
+                .with_rules(config.llm.extra_rules.clone(), config.llm.summary_max_chars)
+                .with_redaction_patterns(&config.analytics.redaction_patterns)?,
+            )
         } else {
             None
         };
 
-        // Initialize database
-        let db_path = Self::get_database_path(&config)?;
-        let database = Database::new(db_path)?;
-
+        let detector = Box::new(ChainDetector::from_config(&config, http)?);
         let state_manager = Arc::new(RwLock::new(StateManager::new()));
+        let nudges = NudgeManager::new(&config.nudging);
+        let llm_jitter_secs = random_jitter_secs(config.tracking.llm_jitter_secs);
+        let summaries = SummaryNotifier::new(Arc::new(RwLock::new(config.notifications.clone())));
 
         Ok(Self {
             config,
@@ -74,28 +190,80 @@ impl WorkTracker {
             jira,
             salesforce,
             llm_analyzer,
+            detector,
             database,
             state_manager,
             last_sync: Utc::now() - Duration::minutes(5),
+            seen_captures: HashSet::new(),
             last_llm_analysis: Utc::now(),
             issue_override,
+            nudges,
+            summaries,
+            idle_break: None,
+            app_break: None,
+            events: events::channel(),
+            tracking_settings: None,
+            analyses_started: 0,
+            llm_jitter_secs,
+            missing_issues: MissingIssues::default(),
+            worklogs_logged: 0,
+            status: Arc::new(RwLock::new(TrackerStatus::default())),
+            heartbeat: None,
+            metrics: Arc::new(Metrics::default()),
         })
     }
 
-    fn get_database_path(config: &Config) -> Result<PathBuf> {
-        let path_str = &config.analytics.database_path;
+    /// Share notification preferences with clients that change them at runtime (e.g. the daemon API)
+    pub fn with_notification_prefs(mut self, prefs: Arc<RwLock<NotificationsConfig>>) -> Self {
+        self.summaries = SummaryNotifier::new(prefs);
+        self
+    }
 
-        // Expand ~ to home directory
-        let expanded = if path_str.starts_with('~') {
-            let home = std::env::var("HOME")
-                .or_else(|_| std::env::var("USERPROFILE"))
-                .context("Could not determine home directory")?;
-            path_str.replacen('~', &home, 1)
-        } else {
-            path_str.clone()
-        };
+    /// Publish tracker events to a channel shared with clients (e.g. the daemon's `/events` stream)
+    pub fn with_events(mut self, events: broadcast::Sender<TrackerEvent>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Share the tracker's status with clients that report it (e.g. the daemon's `/status`)
+    pub fn with_status(mut self, status: Arc<RwLock<TrackerStatus>>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Touch `path` on every loop iteration so a stuck loop can be detected (e.g. by the
+    /// daemon's `/health`)
+    pub fn with_heartbeat(mut self, path: PathBuf) -> Self {
+        self.heartbeat = Some(path);
+        self
+    }
+
+    /// Count stored activities, worklogs, LLM calls and failures in `metrics`, shared with
+    /// the daemon's `/metrics`
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Send an event to subscribers, if any are listening
+    fn publish(&self, event: TrackerEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Publish the current tracking state
+    async fn publish_state(&self) {
+        let state = self.state_manager.read().await;
+        self.status.write().await.state = state.current_state();
+        self.publish(TrackerEvent::StateChanged {
+            state: state.current_state().as_str(),
+            session_id: state.current_session().map(|session| session.id),
+        });
+    }
 
-        Ok(PathBuf::from(expanded))
+    /// Share tracking settings with clients that change them at runtime (e.g. the daemon API)
+    pub fn with_tracking_settings(mut self, settings: Arc<RwLock<TrackingConfig>>) -> Self {
+        self.tracking_settings = Some(settings);
+        self
     }
 
     pub async fn check_health(&mut self) -> Result<()> {
@@ -112,6 +280,34 @@ impl WorkTracker {
         if let Some(salesforce) = &mut self.salesforce {
             let sf_healthy = salesforce.health_check().await?;
             log::info!("Salesforce: {}", if sf_healthy { "✓" } else { "✗" });
+
+            // Catch a missing object or field before the first real log_time
+            if sf_healthy {
+                match salesforce.describe_object(TIME_ENTRY_OBJECT).await {
+                    Ok(fields) => {
+                        let missing: Vec<&str> = TIME_ENTRY_FIELDS
+                            .iter()
+                            .filter(|f| !fields.iter().any(|existing| existing == *f))
+                            .copied()
+                            .collect();
+                        if missing.is_empty() {
+                            log::info!("Salesforce {} fields: ✓", TIME_ENTRY_OBJECT);
+                        } else {
+                            log::warn!(
+                                "Salesforce {} is missing fields: {}",
+                                TIME_ENTRY_OBJECT,
+                                missing.join(", ")
+                            );
+                        }
+                    }
+                    Err(e) => log::warn!("Salesforce {} check failed: {:#}", TIME_ENTRY_OBJECT, e),
+                }
+            }
+        }
+
+        if let Some(llm) = &self.llm_analyzer {
+            let llm_healthy = llm.health_check().await?;
+            log::info!("LLM: {}", if llm_healthy { "✓" } else { "✗" });
         }
 
         Ok(())
@@ -122,35 +318,47 @@ impl WorkTracker {
         let session_id = self.database.create_session()?;
 
         let mut state = self.state_manager.write().await;
-        state.start_tracking(session_id)
+        state
+            .start_tracking(session_id)
             .map_err(|e| anyhow::anyhow!(e))?;
 
+        drop(state);
+
         log::info!("Started tracking session {}", session_id);
+        self.publish_state().await;
         Ok(())
     }
 
     /// Pause tracking - creates break period
     pub async fn pause_tracking(&mut self) -> Result<()> {
         let state = self.state_manager.read().await;
-        let session_id = state.current_session()
+        let session_id = state
+            .current_session()
             .ok_or_else(|| anyhow::anyhow!("No active session"))?
             .id;
         drop(state);
 
+        self.end_idle_break(Utc::now())?;
+        self.end_app_break(Utc::now())?;
         let break_id = self.database.create_break(session_id)?;
 
         let mut state = self.state_manager.write().await;
-        state.pause_tracking(break_id)
+        state
+            .pause_tracking(break_id)
             .map_err(|e| anyhow::anyhow!(e))?;
 
+        drop(state);
+
         log::info!("Paused tracking (break started)");
+        self.publish_state().await;
         Ok(())
     }
 
     /// Resume tracking from pause
     pub async fn resume_tracking(&mut self) -> Result<()> {
         let state = self.state_manager.read().await;
-        let break_id = state.current_break()
+        let break_id = state
+            .current_break()
             .ok_or_else(|| anyhow::anyhow!("No active break"))?
             .id;
         drop(state);
@@ -158,38 +366,68 @@ impl WorkTracker {
         self.database.end_break(break_id)?;
 
         let mut state = self.state_manager.write().await;
-        state.resume_tracking()
-            .map_err(|e| anyhow::anyhow!(e))?;
+        state.resume_tracking().map_err(|e| anyhow::anyhow!(e))?;
+
+        drop(state);
 
         log::info!("Resumed tracking");
+        self.publish_state().await;
         Ok(())
     }
 
     /// Stop tracking - ends session and triggers analysis
     pub async fn stop_tracking(&mut self) -> Result<()> {
         let state = self.state_manager.read().await;
-        let session_id = state.current_session()
+        let session_id = state
+            .current_session()
             .ok_or_else(|| anyhow::anyhow!("No active session"))?
             .id;
         drop(state);
 
+        self.end_idle_break(Utc::now())?;
+        self.end_app_break(Utc::now())?;
         self.database.end_session(session_id)?;
 
         let mut state = self.state_manager.write().await;
-        state.stop_tracking()
-            .map_err(|e| anyhow::anyhow!(e))?;
+        state.stop_tracking().map_err(|e| anyhow::anyhow!(e))?;
+
+        drop(state);
 
         log::info!("Stopped tracking session {}", session_id);
+        self.status.write().await.state = TrackingState::Stopped;
+        self.publish(TrackerEvent::StateChanged {
+            state: TrackingState::Stopped.as_str(),
+            session_id: Some(session_id),
+        });
 
         // Trigger final analysis if configured
-        if self.config.tracking.analyze_on_stop {
-            drop(state);
+        if self.config.tracking.analyze_on_stop && self.long_enough_to_analyze(session_id)? {
             self.analyze_and_log_batch(session_id).await?;
         }
 
         Ok(())
     }
 
+    /// Whether the session has at least `tracking.min_session_secs` of tracked time (excluding
+    /// breaks); shorter sessions are skipped, with a log line saying so
+    fn long_enough_to_analyze(&self, session_id: i64) -> Result<bool> {
+        let stats = self.database.get_session_stats(session_id)?;
+        let tracked_secs = stats
+            .total_duration_secs
+            .saturating_sub(stats.break_duration_secs);
+        if tracked_secs >= self.config.tracking.min_session_secs {
+            return Ok(true);
+        }
+
+        log::info!(
+            "Skipping analysis of session {}: {} tracked, less than the {} minimum",
+            session_id,
+            format_hms(tracked_secs),
+            format_hms(self.config.tracking.min_session_secs)
+        );
+        Ok(false)
+    }
+
     /// Sync activities from screenpipe to local database
     /// This runs every 5 minutes when tracking is active
     pub async fn sync(&mut self) -> Result<()> {
@@ -202,272 +440,3282 @@ impl WorkTracker {
             return Ok(());
         }
 
-        let session_id = state.current_session()
+        let session_id = state
+            .current_session()
             .ok_or_else(|| anyhow::anyhow!("No active session during tracking"))?
             .id;
         drop(state);
 
         log::info!("Fetching activities since {}", self.last_sync);
 
-        let activities = self
+        // `last_sync` only moves to `until` once the window is fetched and stored, so a failed
+        // poll (e.g. Screenpipe restarting) is retried in full next cycle. Each window also
+        // reaches back by the overlap, for frames Screenpipe wrote after the previous poll.
+        let until = Utc::now();
+        let overlap = Duration::seconds(self.config.tracking.sync_overlap_secs as i64);
+        let fetched = self
             .screenpipe
-            .get_recent_activities(self.last_sync)
+            .get_activities_between(self.last_sync - overlap, until)
             .await?;
-        log::info!("Found {} activities", activities.len());
+        let activities = self.unseen(fetched);
+        tracing::info!(
+            session_id,
+            count = activities.len(),
+            "Found {} activities",
+            activities.len()
+        );
 
         if activities.is_empty() {
-            self.last_sync = Utc::now();
+            // Nothing captured for the whole window: the screen was idle or locked
+            if self.config.tracking.detect_idle_gaps && self.idle_break.is_none() {
+                let break_id = self.database.create_break_at(session_id, self.last_sync)?;
+                log::info!("No activity since {}, recording idle break", self.last_sync);
+                self.idle_break = Some(break_id);
+            }
+            self.record_sync(until, &activities).await;
+            return Ok(());
+        }
+
+        let window_start = activities
+            .iter()
+            .map(|a| a.timestamp)
+            .min()
+            .unwrap_or(until)
+            .max(self.last_sync);
+        self.end_idle_break(window_start)?;
+
+        // A call or calendar in focus for most of the window is a break, not billable work
+        if break_apps_dominate(&activities, &self.config.tracking.break_apps) {
+            if self.app_break.is_none() {
+                let break_id = self.database.create_break_at(session_id, window_start)?;
+                log::info!("Break app in focus, recording break");
+                self.app_break = Some(break_id);
+            }
+            self.record_sync(until, &activities).await;
             return Ok(());
         }
+        self.end_app_break(window_start)?;
+
+        // Late captures (written after the previous poll read their time) usually continue
+        // a window that poll stored, so they extend it rather than make a fragment of their own
+        let mut extended: HashMap<i64, u64> = HashMap::new();
+        let mut fresh = Vec::new();
+        for activity in &activities {
+            if activity.timestamp < self.last_sync {
+                let stored = self.database.latest_unlogged_activity(
+                    session_id,
+                    &activity.app_name,
+                    &activity.window_title,
+                )?;
+                if let Some(stored) = stored {
+                    *extended.entry(stored.id).or_insert(stored.duration_secs) +=
+                        activity.duration_secs;
+                    continue;
+                }
+            }
+            fresh.push(activity.clone());
+        }
 
         // Consolidate and store activities
-        let consolidated = self.consolidate_activities(&activities);
-        log::info!("Consolidated into {} entries", consolidated.len());
+        let consolidated = self.consolidate_activities(&fresh);
+        tracing::info!(
+            session_id,
+            count = consolidated.len(),
+            "Consolidated into {} entries",
+            consolidated.len()
+        );
+
+        let (consolidated, dropped) = drop_short_activities(
+            consolidated,
+            self.config.tracking.min_activity_duration_secs,
+        );
+        if dropped > 0 {
+            log::debug!(
+                "Dropped {} activities shorter than {}s",
+                dropped,
+                self.config.tracking.min_activity_duration_secs
+            );
+        }
 
+        let extended: Vec<(i64, u64)> = extended.into_iter().collect();
+        self.database
+            .store_activities(session_id, &consolidated, &extended)?;
+        if !extended.is_empty() {
+            log::debug!("Extended {} activities with late captures", extended.len());
+        }
         for activity in &consolidated {
-            self.database.store_activity(session_id, activity)?;
             log::debug!(
-                "Stored: {} - {} ({}s, tier: {:?})",
+                "Stored: {} - {} ({}, tier: {:?})",
                 activity.app_name,
                 activity.window_title,
-                activity.duration_secs,
-                ActivityTier::from_duration(activity.duration_secs)
+                format_hms(activity.duration_secs),
+                self.database.tier_for(activity.duration_secs)
             );
         }
 
-        self.last_sync = Utc::now();
+        self.metrics.record_activities_stored(consolidated.len());
+        if !consolidated.is_empty() {
+            self.publish(TrackerEvent::ActivitiesStored {
+                session_id,
+                count: consolidated.len(),
+            });
+        }
+
+        if let Err(e) = self.nudge_unassigned_issues(&consolidated).await {
+            log::warn!("Failed to check for unassigned issues: {:#}", e);
+        }
+
+        self.record_sync(until, &activities).await;
+        Ok(())
+    }
+
+    /// Move `last_sync` past a window that was fetched and stored, remembering its captures,
+    /// and report it along with the end of its latest capture
+    async fn record_sync(&mut self, until: DateTime<Utc>, activities: &[Activity]) {
+        let overlap = Duration::seconds(self.config.tracking.sync_overlap_secs as i64);
+        self.remember_seen(activities, until - overlap);
+        self.last_sync = until;
+
+        let mut status = self.status.write().await;
+        status.last_sync = Some(until);
+        let latest = activities
+            .iter()
+            .map(|a| a.timestamp + Duration::seconds(a.duration_secs as i64))
+            .max();
+        if latest.is_some() {
+            status.last_activity_at = status.last_activity_at.max(latest);
+        }
+    }
+
+    /// Drop captures already returned (and stored) by an earlier poll
+    fn unseen(&self, fetched: Vec<Activity>) -> Vec<Activity> {
+        let mut fetched_keys = HashSet::new();
+        fetched
+            .into_iter()
+            .filter(|activity| {
+                let key = CaptureKey::of(activity);
+                !self.seen_captures.contains(&key) && fetched_keys.insert(key)
+            })
+            .collect()
+    }
+
+    /// Remember stored captures so the next poll's overlap skips them. Captures before
+    /// `keep_from` can't be fetched again, so they are forgotten.
+    fn remember_seen(&mut self, activities: &[Activity], keep_from: DateTime<Utc>) {
+        self.seen_captures
+            .extend(activities.iter().map(CaptureKey::of));
+        self.seen_captures.retain(|key| key.timestamp >= keep_from);
+    }
+
+    /// End the idle break, if one is open, at `at`
+    fn end_idle_break(&mut self, at: DateTime<Utc>) -> Result<()> {
+        if let Some(break_id) = self.idle_break.take() {
+            self.database.end_break_at(break_id, at)?;
+            log::info!("Activity resumed, idle break ended");
+        }
+        Ok(())
+    }
+
+    /// End the break opened for a break app, if one is open, at `at`
+    fn end_app_break(&mut self, at: DateTime<Utc>) -> Result<()> {
+        if let Some(break_id) = self.app_break.take() {
+            self.database.end_break_at(break_id, at)?;
+            log::info!("Break app no longer in focus, break ended");
+        }
+        Ok(())
+    }
+
+    /// The issue override, normalized so a key set as `proj-42 ` is logged to `PROJ-42`.
+    /// An override that isn't shaped like an issue key is ignored with a warning.
+    async fn current_override(&self) -> Option<String> {
+        let raw = self.issue_override.read().await.clone()?;
+        match issue_key::normalize(&raw) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                log::warn!("Ignoring issue override: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Nudge the user about issue keys seen in their activities that aren't assigned to them
+    async fn nudge_unassigned_issues(&mut self, activities: &[Activity]) -> Result<()> {
+        if !self.config.nudging.enabled || !self.config.nudging.detect_assigned_issues_in_titles {
+            return Ok(());
+        }
+
+        // An explicit override already tells us where the time goes
+        if self.current_override().await.is_some() {
+            return Ok(());
+        }
+
+        let Some(jira) = &self.jira else {
+            return Ok(());
+        };
+
+        let mut detected: Vec<(String, &Activity)> = Vec::new();
+        for activity in activities {
+            if let Some(issue_key) = jira.find_issue_from_activity(activity).await? {
+                if !detected.iter().any(|(key, _)| *key == issue_key) {
+                    detected.push((issue_key, activity));
+                }
+            }
+        }
+
+        if detected.is_empty() {
+            return Ok(());
+        }
+
+        let keys: Vec<String> = detected.iter().map(|(key, _)| key.clone()).collect();
+        let assigned = jira.filter_assigned(&keys).await?;
+        for (issue_key, activity) in detected {
+            if !assigned.contains(&issue_key) {
+                self.nudges
+                    .nudge_unassigned(&issue_key, &activity.window_title);
+            }
+        }
+
         Ok(())
     }
 
     /// Analyze buffered activities using LLM and log to Jira
     /// This runs every 3 hours or when tracking stops
     pub async fn analyze_and_log_batch(&mut self, session_id: i64) -> Result<()> {
-        log::info!("Starting LLM batch analysis for session {}", session_id);
+        self.analyses_started += 1;
+        self.missing_issues = MissingIssues::default();
+        self.worklogs_logged = 0;
+        tracing::info!(
+            session_id,
+            "Starting LLM batch analysis for session {}",
+            session_id
+        );
 
         // Get session statistics
         let stats = self.database.get_session_stats(session_id)?;
         log::info!(
-            "Session stats: {} total activities ({} billable, {} micro)",
+            "Session stats: {} total activities ({} billable, {} micro, {} non-billable)",
             stats.total_activities,
             stats.billable_activities,
-            stats.micro_activities
+            stats.micro_activities,
+            stats.nonbillable_activities
         );
 
         // Get activities by tier
-        let billable = self.database.get_session_activities(session_id, Some(ActivityTier::Billable))?;
-        let micro = self.database.get_session_activities(session_id, Some(ActivityTier::Micro))?;
+        // Only what earlier runs haven't logged yet
+        let mut billable = self
+            .database
+            .get_unlogged_activities(session_id, Some(ActivityTier::Billable))?;
+        let mut micro = self
+            .database
+            .get_unlogged_activities(session_id, Some(ActivityTier::Micro))?;
+
+        if !self.config.tracking.log_nonbillable_time {
+            billable.retain(|a| a.billable);
+            micro.retain(|a| a.billable);
+        }
+
+        if let Some(hours) = &self.config.tracking.working_hours {
+            let (inside, outside) = clip_to_working_hours(
+                billable.into_iter().chain(micro).collect(),
+                hours,
+                jira_timezone(&self.config)?,
+            );
+            self.flag_off_hours(session_id, &inside, &outside)?;
+            (billable, micro) = inside
+                .into_iter()
+                .partition(|a| a.tier == ActivityTier::Billable);
+        }
 
         if billable.is_empty() && micro.is_empty() {
             log::info!("No activities to analyze");
             return Ok(());
         }
 
-        // If LLM is enabled, use it for analysis
-        if let (Some(llm), Some(jira)) = (&self.llm_analyzer, &self.jira) {
+        // Activities entered or corrected for a specific issue skip matching and are logged as-is
+        let entered_for_issue = |a: &StoredActivity| a.issue_key.is_some();
+        let (manual, billable): (Vec<_>, Vec<_>) =
+            billable.into_iter().partition(entered_for_issue);
+        let (manual_micro, micro): (Vec<_>, Vec<_>) =
+            micro.into_iter().partition(entered_for_issue);
+        let manual: Vec<_> = manual.into_iter().chain(manual_micro).collect();
+        self.log_manual_activities(session_id, &manual).await?;
+
+        // The issues the LLM may match against, when LLM matching is on
+        let assigned_issues = match (&self.llm_analyzer, &self.jira) {
+            (Some(_), Some(jira)) if !billable.is_empty() || !micro.is_empty() => {
+                let issues = jira.get_assigned_issues().await?;
+                log::info!("Fetched {} assigned issues", issues.len());
+                Some(issues)
+            }
+            _ => None,
+        };
+
+        if billable.is_empty() && micro.is_empty() {
+            log::info!(
+                "No activities left to match after {} manual entries",
+                manual.len()
+            );
+        } else if assigned_issues.as_ref().is_some_and(Vec::is_empty) {
+            let unmatched: Vec<_> = billable.into_iter().chain(micro).collect();
+            self.log_without_assigned_issues(session_id, &unmatched)
+                .await?;
+        } else if let (Some(llm), Some(jira), Some(assigned_issues)) =
+            (&self.llm_analyzer, &self.jira, assigned_issues)
+        {
+            // If LLM is enabled, use it for analysis
             log::info!("Using LLM for batch analysis");
 
-            // Get assigned issues
-            let assigned_issues = jira.get_assigned_issues().await?;
-            log::info!("Fetched {} assigned issues", assigned_issues.len());
+            // The activities by id, to split matches by day and to find worklogs the user
+            // made by hand for the same time
+            let by_id: HashMap<i64, StoredActivity> = billable
+                .iter()
+                .chain(&micro)
+                .map(|a| (a.id, a.clone()))
+                .collect();
+            let timezone = jira_timezone(&self.config)?;
+            let account_id = match jira.get_current_user().await {
+                Ok(user) => Some(user.account_id),
+                Err(e) => {
+                    log::warn!(
+                        "Cannot check for worklogs made outside the tracker: {:#}",
+                        e
+                    );
+                    None
+                }
+            };
 
-            if assigned_issues.is_empty() {
-                log::warn!("No assigned issues found - cannot match activities");
-                return Ok(());
+            // Split large sessions into chunks so each request stays within the LLM context limits
+            let chunks = chunk_activities(billable, micro, self.config.llm.batch_size);
+            if chunks.len() > 1 {
+                log::info!("Splitting session into {} LLM batches", chunks.len());
             }
 
-            // Prepare LLM request
-            let analysis_result = llm.analyze_batch(
-                self.config.jira.email.clone(),
-                self.config.company.name.clone(),
-                assigned_issues,
-                stats.start_time,
-                stats.end_time.unwrap_or_else(Utc::now),
-                stats.total_duration_secs,
-                stats.break_duration_secs,
-                billable,
-                micro,
-            ).await?;
+            let mut requests = Vec::with_capacity(chunks.len());
+            let mut partial_results = Vec::with_capacity(chunks.len());
+            for (chunk_billable, chunk_micro) in chunks {
+                self.metrics.record_llm_call();
+                let batch = llm
+                    .analyze_batch(
+                        self.config.jira.email.clone(),
+                        self.config.company.name.clone(),
+                        assigned_issues.clone(),
+                        stats.start_time,
+                        stats.end_time.unwrap_or_else(Utc::now),
+                        stats.total_duration_secs,
+                        stats.break_duration_secs,
+                        chunk_billable,
+                        chunk_micro,
+                    )
+                    .await;
+                let (request, partial) =
+                    batch.inspect_err(|_| self.metrics.record_failure(Failure::Llm))?;
+                requests.push(request.without_ocr());
+                partial_results.push(partial);
+            }
 
-            log::info!(
+            let mut analysis_result = merge_analyses(partial_results);
+            salvage_unmatched(
+                llm,
+                &mut analysis_result.analysis,
+                &by_id,
+                &assigned_issues,
+                &self.config.llm,
+                &self.metrics,
+            )
+            .await;
+
+            tracing::info!(
+                session_id,
+                issues = analysis_result.analysis.issues.len(),
+                confidence = analysis_result.analysis.confidence,
                 "LLM analysis complete: {} issues matched, confidence: {:.2}",
                 analysis_result.analysis.issues.len(),
                 analysis_result.analysis.confidence
             );
 
-            // Store analysis result
+            // Store analysis result with the requests behind it (one per batch)
+            let request_json = serde_json::to_string(&requests)?;
             let analysis_json = serde_json::to_string(&analysis_result)?;
             self.database.store_analysis(
                 session_id,
+                request_json,
                 analysis_json,
                 analysis_result.analysis.confidence,
             )?;
 
             // Log to Jira based on LLM results
             for issue_match in &analysis_result.analysis.issues {
-                if issue_match.confidence < self.config.llm.confidence_threshold {
+                let threshold = self.config.llm.confidence_threshold_for(&issue_match.key);
+                if issue_match.confidence < threshold {
                     log::warn!(
-                        "Skipping {} - confidence too low: {:.2}",
+                        "Skipping {} - confidence too low: {:.2} (needs {:.2})",
                         issue_match.key,
-                        issue_match.confidence
+                        issue_match.confidence,
+                        threshold
                     );
                     continue;
                 }
 
-                // Create worklog entry with LLM-generated summary
-                let activity = Activity {
-                    timestamp: stats.start_time,
-                    duration_secs: issue_match.total_time_secs,
-                    window_title: issue_match.summary.clone(),
-                    app_name: self.config.company.name.clone(),
-                    description: format!("Work type: {}", issue_match.work_type),
+                // Subtract time the user already logged to the issue themselves, so it isn't billed twice
+                let mut time_secs = issue_match.total_time_secs;
+                let included: Vec<StoredActivity> = issue_match
+                    .activities_included
+                    .iter()
+                    .filter_map(|id| by_id.get(id).cloned())
+                    .collect();
+                let window =
+                    included
+                        .iter()
+                        .map(|a| {
+                            (
+                                a.timestamp,
+                                a.timestamp + Duration::seconds(a.duration_secs as i64),
+                            )
+                        })
+                        .fold(
+                            None,
+                            |window: Option<(DateTime<Utc>, DateTime<Utc>)>, (start, end)| {
+                                Some(window.map_or((start, end), |(from, to)| {
+                                    (from.min(start), to.max(end))
+                                }))
+                            },
+                        );
+                if let (Some(account_id), Some((from, to))) = (&account_id, window) {
+                    match externally_logged_secs(
+                        jira,
+                        &self.database,
+                        account_id,
+                        &issue_match.key,
+                        from,
+                        to,
+                    )
+                    .await
+                    {
+                        Ok(0) => {}
+                        Ok(external_secs) => {
+                            time_secs = time_secs.saturating_sub(external_secs);
+                            log::info!(
+                                "{} already has {} logged outside the tracker for this period; {} left",
+                                issue_match.key,
+                                format_hm(external_secs),
+                                format_hm(time_secs)
+                            );
+                            if time_secs < self.config.tracking.min_activity_duration_secs {
+                                log::info!(
+                                    "Skipping {} - already covered by existing worklogs",
+                                    issue_match.key
+                                );
+                                if !self.config.tracking.dry_run {
+                                    self.database
+                                        .mark_activities_logged(&issue_match.activities_included)?;
+                                    self.database.record_attribution(
+                                        &issue_match.activities_included,
+                                        &issue_match.key,
+                                        AttributionSource::Llm,
+                                    )?;
+                                }
+                                continue;
+                            }
+                        }
+                        Err(e) => log::warn!(
+                            "Failed to check existing worklogs on {}: {:#}",
+                            issue_match.key,
+                            e
+                        ),
+                    }
+                }
+
+                // One worklog per day, so overnight work lands on the right dates
+                let day_worklogs = if included.is_empty() {
+                    vec![DayWorklog {
+                        started: stats.start_time,
+                        time_secs,
+                        activity_ids: issue_match.activities_included.clone(),
+                    }]
+                } else {
+                    share_by_day(split_by_day(&included, timezone), time_secs)
                 };
+                if day_worklogs.len() > 1 {
+                    log::info!(
+                        "{} spans {} days, logging a worklog for each",
+                        issue_match.key,
+                        day_worklogs.len()
+                    );
+                }
 
-                match jira.log_work(&issue_match.key, &activity).await {
-                    Ok(_) => {
-                        log::info!(
-                            "Logged {} to {} ({} mins)",
-                            issue_match.key,
-                            issue_match.summary,
-                            issue_match.total_time_secs / 60
-                        );
+                for DayWorklog {
+                    started,
+                    time_secs,
+                    activity_ids,
+                } in day_worklogs
+                {
+                    if let Some(max_hours) = self.config.jira.max_daily_hours_per_issue {
+                        let date = match timezone {
+                            Some(tz) => started.with_timezone(&tz).date_naive(),
+                            None => started.with_timezone(&Local).date_naive(),
+                        };
+                        // Count what the day already has on the issue, from the tracker
+                        // and from worklogs entered by hand
+                        let mut day_secs = 0;
+                        if let Some((day_start, day_end)) = local_day(date, timezone) {
+                            day_secs += self.database.logged_secs_between(
+                                &issue_match.key,
+                                day_start,
+                                day_end,
+                            )?;
+                            if let Some(account_id) = &account_id {
+                                match externally_logged_secs(
+                                    jira,
+                                    &self.database,
+                                    account_id,
+                                    &issue_match.key,
+                                    day_start,
+                                    day_end - Duration::seconds(1),
+                                )
+                                .await
+                                {
+                                    Ok(external_secs) => day_secs += external_secs,
+                                    Err(e) => log::warn!(
+                                        "Failed to check the day's worklogs on {}: {:#}",
+                                        issue_match.key,
+                                        e
+                                    ),
+                                }
+                            }
+                        }
 
-                        // Mark activities as logged
-                        self.database.mark_activities_logged(&issue_match.activities_included)?;
+                        if (day_secs + time_secs) as f64 > max_hours * 3600.0 {
+                            let message = format!(
+                                "{} on {} for {} would bring the day to {}, over the {}h daily limit, and was not logged",
+                                format_hm(time_secs),
+                                date,
+                                issue_match.key,
+                                format_hm(day_secs + time_secs),
+                                max_hours
+                            );
+                            log::warn!("{}", message);
+                            // Held-back activities come back every analysis; flag them once
+                            if activity_ids.is_empty()
+                                || !self
+                                    .database
+                                    .newly_flagged(FlagKind::OverDailyLimit, &activity_ids)?
+                                    .is_empty()
+                            {
+                                self.database.store_flag(
+                                    session_id,
+                                    FlagKind::OverDailyLimit,
+                                    &message,
+                                    time_secs,
+                                )?;
+                            }
+                            continue;
+                        }
                     }
-                    Err(e) => {
-                        log::error!("Failed to log to Jira {}: {}", issue_match.key, e);
+
+                    // Create worklog entry with LLM-generated summary
+                    let activity = Activity {
+                        timestamp: started,
+                        duration_secs: time_secs,
+                        window_title: issue_match.summary.clone(),
+                        app_name: self.config.company.name.clone(),
+                        description: format!("Work type: {}", issue_match.work_type),
+                        browser_url: None,
+                    };
+
+                    // Prefer the LLM's summary; fall back to the configured template
+                    let work_type = WorkType::normalize(&issue_match.work_type);
+                    let comment = if issue_match.summary.trim().is_empty() {
+                        let work_type_name =
+                            work_type.map_or(issue_match.work_type.as_str(), |t| t.as_str());
+                        jira.render_comment(&issue_match.key, &activity, Some(work_type_name))
+                    } else {
+                        issue_match.summary.clone()
+                    };
+
+                    match jira
+                        .log_work_with_comment(&issue_match.key, &activity, comment, work_type)
+                        .await
+                    {
+                        // Leave the activities unlogged in dry-run mode
+                        Ok(_) if self.config.tracking.dry_run => self.worklogs_logged += 1,
+                        Ok(worklog_id) => {
+                            tracing::info!(
+                                session_id,
+                                issue_key = %issue_match.key,
+                                duration_secs = time_secs,
+                                worklog_id = %worklog_id,
+                                "Logged {} to {} ({})",
+                                issue_match.key,
+                                issue_match.summary,
+                                format_hm(time_secs)
+                            );
+
+                            // Mark activities as logged and remember the worklog for undo
+                            self.database.mark_activities_logged(&activity_ids)?;
+                            self.database.record_attribution(
+                                &activity_ids,
+                                &issue_match.key,
+                                AttributionSource::Llm,
+                            )?;
+                            self.database.store_worklog(
+                                session_id,
+                                &issue_match.key,
+                                &worklog_id,
+                                time_secs,
+                                &activity_ids,
+                                started,
+                            )?;
+                            self.summaries.record(&issue_match.key, time_secs);
+                            self.metrics.record_worklog_posted();
+                            self.worklogs_logged += 1;
+                        }
+                        Err(e) if e.is::<IssueNotFound>() => {
+                            self.missing_issues.record(
+                                &self.database,
+                                &issue_match.key,
+                                &activity_ids,
+                                time_secs,
+                            )?;
+                        }
+                        Err(e) => {
+                            self.metrics.record_failure(Failure::Worklog);
+                            tracing::error!(
+                                session_id,
+                                issue_key = %issue_match.key,
+                                duration_secs = time_secs,
+                                "Failed to log to Jira {}: {}",
+                                issue_match.key,
+                                e
+                            );
+                        }
                     }
                 }
             }
 
-            // Report unmatched activities
-            if analysis_result.analysis.unmatched.total_time_secs > 0 {
+            // Report unmatched activities and red flags so clients can surface them
+            let unmatched = &analysis_result.analysis.unmatched;
+            if unmatched.total_time_secs > 0 {
                 log::warn!(
-                    "Unmatched time: {} mins ({})",
-                    analysis_result.analysis.unmatched.total_time_secs / 60,
-                    analysis_result.analysis.unmatched.likely_reason
+                    "Unmatched time: {} ({})",
+                    format_hm(unmatched.total_time_secs),
+                    unmatched.likely_reason
                 );
+                self.database.store_flag(
+                    session_id,
+                    FlagKind::Unmatched,
+                    &unmatched.likely_reason,
+                    unmatched.total_time_secs,
+                )?;
+                self.summaries.record_unmatched(unmatched.total_time_secs);
             }
 
+            for red_flag in &analysis_result.analysis.red_flags {
+                log::warn!("Red flag: {}", red_flag);
+                self.database
+                    .store_flag(session_id, FlagKind::RedFlag, red_flag, 0)?;
+            }
         } else {
-            log::info!("LLM disabled, using fallback regex matching");
-            // Fallback to regex-based matching (original behavior)
-            self.fallback_regex_logging(session_id, &billable).await?;
+            log::info!("LLM disabled, using the issue detection pipeline");
+            self.fallback_detection_logging(session_id, &billable, &micro)
+                .await?;
         }
 
+        std::mem::take(&mut self.missing_issues).flag(&self.database, session_id)?;
+        self.summaries.flush().await;
+
         self.last_llm_analysis = Utc::now();
+        self.publish(TrackerEvent::AnalysisComplete {
+            session_id,
+            logged: self.worklogs_logged,
+        });
         Ok(())
     }
 
-    /// Fallback regex-based logging (original behavior)
-    async fn fallback_regex_logging(&mut self, session_id: i64, activities: &[crate::database::StoredActivity]) -> Result<()> {
-        if let Some(jira) = &self.jira {
-            let issue_override = {
-                let guard = self.issue_override.read().await;
-                guard.clone()
-            };
+    /// Analyze sessions other than `current` that have manual entries waiting to be logged,
+    /// so time entered while tracking was stopped, or for an earlier session, reaches Jira
+    async fn analyze_sessions_with_manual_entries(&mut self, current: Option<i64>) -> Result<()> {
+        for session_id in self
+            .database
+            .sessions_with_unlogged_manual_activities(current)?
+        {
+            log::info!("Analyzing session {} for its manual entries", session_id);
+            if let Err(e) = self.analyze_and_log_batch(session_id).await {
+                log::error!("Analysis of session {} failed: {:#}", session_id, e);
+            }
+        }
 
-            for stored_activity in activities {
-                if stored_activity.logged_to_jira {
-                    continue;
-                }
+        Ok(())
+    }
 
-                let activity = Activity {
-                    timestamp: stored_activity.timestamp,
-                    duration_secs: stored_activity.duration_secs,
-                    window_title: stored_activity.window_title.clone(),
-                    app_name: stored_activity.app_name.clone(),
-                    description: stored_activity.description.clone(),
-                };
+    /// Flag time outside working hours. Activities entirely outside are marked unloggable so
+    /// later analyses skip them; those crossing an edge are logged for their inside part.
+    fn flag_off_hours(
+        &self,
+        session_id: i64,
+        inside: &[StoredActivity],
+        outside: &[StoredActivity],
+    ) -> Result<()> {
+        let excluded: Vec<i64> = outside
+            .iter()
+            .map(|a| a.id)
+            .filter(|id| !inside.iter().any(|a| a.id == *id))
+            .collect();
+        if !excluded.is_empty() {
+            self.database.mark_activities_unloggable(&excluded)?;
+        }
 
-                let target_issue = if let Some(issue_key) = &issue_override {
-                    Some(issue_key.clone())
-                } else {
-                    match jira.find_issue_from_activity(&activity).await {
-                        Ok(result) => result,
-                        Err(err) => {
-                            log::error!("Failed to detect Jira issue: {}", err);
-                            None
-                        }
-                    }
-                };
+        // Activities crossing an edge come back every analysis until their working part is
+        // logged; their off-hours part is only flagged the first time
+        let ids: Vec<i64> = outside.iter().map(|a| a.id).collect();
+        let new = self.database.newly_flagged(FlagKind::OffHours, &ids)?;
+        let outside: Vec<&StoredActivity> =
+            outside.iter().filter(|a| new.contains(&a.id)).collect();
+        if outside.is_empty() {
+            return Ok(());
+        }
 
-                if let Some(issue_key) = target_issue {
-                    // Check if assigned to user
-                    match jira.is_assigned_to_me(&issue_key).await {
-                        Ok(true) => {
-                            match jira.log_work(&issue_key, &activity).await {
-                                Ok(_) => {
-                                    log::info!("Logged to Jira: {}", issue_key);
-                                    self.database.mark_activities_logged(&[stored_activity.id])?;
-                                }
-                                Err(e) => log::error!("Failed to log to Jira: {}", e),
-                            }
-                        }
-                        Ok(false) => {
-                            log::warn!("Skipping {} - not assigned to you", issue_key);
-                        }
-                        Err(e) => {
-                            log::error!("Failed to check assignment for {}: {}", issue_key, e);
-                        }
-                    }
-                }
+        let time_secs: u64 = outside.iter().map(|a| a.duration_secs).sum();
+
+        log::warn!(
+            "{} outside working hours will not be logged",
+            format_hm(time_secs)
+        );
+        self.database.store_flag(
+            session_id,
+            FlagKind::OffHours,
+            &format!(
+                "{} of {} activities fell outside working hours and was not logged",
+                format_hm(time_secs),
+                outside.len()
+            ),
+            time_secs,
+        )?;
+        Ok(())
+    }
+
+    /// Log activities to the issue they were entered for or assigned to
+    async fn log_manual_activities(
+        &mut self,
+        session_id: i64,
+        activities: &[StoredActivity],
+    ) -> Result<()> {
+        for stored_activity in activities {
+            if let Some(issue_key) = stored_activity.issue_key.as_deref() {
+                self.log_activities_to_issue(
+                    session_id,
+                    issue_key,
+                    std::slice::from_ref(stored_activity),
+                    AttributionSource::Manual,
+                )
+                .await?;
             }
         }
 
         Ok(())
     }
 
-    fn consolidate_activities(&self, activities: &[Activity]) -> Vec<Activity> {
-        let mut consolidated: HashMap<String, Activity> = HashMap::new();
+    /// With no assigned issues the LLM has nothing to match against. Log the time to the
+    /// issue override if one is set; otherwise flag it and leave the activities unlogged so
+    /// the next analysis picks them up.
+    async fn log_without_assigned_issues(
+        &mut self,
+        session_id: i64,
+        activities: &[StoredActivity],
+    ) -> Result<()> {
+        if let Some(issue_key) = self.current_override().await {
+            log::warn!(
+                "No Jira issues are assigned to you - logging {} activities to the issue override {}",
+                activities.len(),
+                issue_key
+            );
+            return self
+                .log_activities_to_issue(
+                    session_id,
+                    &issue_key,
+                    activities,
+                    AttributionSource::Override,
+                )
+                .await;
+        }
 
-        for activity in activities {
-            let key = format!("{}:{}", activity.app_name, activity.window_title);
+        let total_secs: u64 = activities.iter().map(|a| a.duration_secs).sum();
+        log::warn!(
+            "No Jira issues are assigned to you - {} could not be matched. Assign yourself \
+             the issues you worked on or set an issue override; the time will be logged by the \
+             next analysis",
+            format_hm(total_secs)
+        );
 
-            consolidated
-                .entry(key)
-                .and_modify(|existing| {
-                    existing.duration_secs += activity.duration_secs;
-                })
-                .or_insert_with(|| activity.clone());
+        // The same activities come back every analysis until they are logged; flag and
+        // report only the ones not flagged before
+        let ids: Vec<i64> = activities.iter().map(|a| a.id).collect();
+        let new = self.database.newly_flagged(FlagKind::Unmatched, &ids)?;
+        if new.is_empty() {
+            return Ok(());
         }
+        let new_secs: u64 = activities
+            .iter()
+            .filter(|a| new.contains(&a.id))
+            .map(|a| a.duration_secs)
+            .sum();
+        self.database.store_flag(
+            session_id,
+            FlagKind::Unmatched,
+            "No Jira issues are assigned to you",
+            new_secs,
+        )?;
+        self.summaries.record_unmatched(new_secs);
 
-        consolidated.into_values().collect()
+        Ok(())
     }
 
-    /// Main run loop with state-aware polling
-    pub async fn run(&mut self, interval_secs: u64) -> Result<()> {
-        log::info!(
-            "Starting work tracker (polling every {} seconds)...",
-            interval_secs
-        );
+    /// Log each activity to `issue_key` as its own worklog
+    async fn log_activities_to_issue(
+        &mut self,
+        session_id: i64,
+        issue_key: &str,
+        activities: &[StoredActivity],
+        attribution: AttributionSource,
+    ) -> Result<()> {
+        let Some(jira) = &self.jira else {
+            return Ok(());
+        };
 
-        let llm_interval_secs = self.config.tracking.llm_batch_interval_secs;
+        for stored_activity in activities {
+            // Manual entries have no window; their description stands in for it in the comment
+            let window_title = match stored_activity.source {
+                ActivitySource::Manual if stored_activity.window_title.is_empty() => {
+                    stored_activity.description.clone()
+                }
+                _ => stored_activity.window_title.clone(),
+            };
+            let activity = Activity {
+                timestamp: stored_activity.timestamp,
+                duration_secs: stored_activity.duration_secs,
+                window_title,
+                app_name: stored_activity.app_name.clone(),
+                description: stored_activity.description.clone(),
+                browser_url: None,
+            };
 
-        loop {
-            // Screenpipe sync (every 5 min)
-            match self.sync().await {
-                Ok(_) => log::debug!("Sync completed successfully"),
-                Err(e) => log::error!("Sync failed: {:#}", e),
+            match jira.log_work(issue_key, &activity).await {
+                Ok(_) if self.config.tracking.dry_run => self.worklogs_logged += 1,
+                Ok(worklog_id) => {
+                    tracing::info!(
+                        session_id,
+                        issue_key,
+                        duration_secs = stored_activity.duration_secs,
+                        worklog_id = %worklog_id,
+                        source = attribution.as_str(),
+                        "Logged to Jira: {}",
+                        issue_key
+                    );
+                    self.database
+                        .mark_activities_logged(&[stored_activity.id])?;
+                    self.database.record_attribution(
+                        &[stored_activity.id],
+                        issue_key,
+                        attribution,
+                    )?;
+                    self.database.store_worklog(
+                        session_id,
+                        issue_key,
+                        &worklog_id,
+                        stored_activity.duration_secs,
+                        &[stored_activity.id],
+                        stored_activity.timestamp,
+                    )?;
+                    self.summaries
+                        .record(issue_key, stored_activity.duration_secs);
+                    self.metrics.record_worklog_posted();
+                    self.worklogs_logged += 1;
+                }
+                Err(e) if e.is::<IssueNotFound>() => {
+                    self.missing_issues.record(
+                        &self.database,
+                        issue_key,
+                        &[stored_activity.id],
+                        stored_activity.duration_secs,
+                    )?;
+                }
+                Err(e) => {
+                    self.metrics.record_failure(Failure::Worklog);
+                    tracing::error!(
+                        session_id,
+                        issue_key,
+                        duration_secs = stored_activity.duration_secs,
+                        "Failed to log to Jira: {}",
+                        e
+                    )
+                }
             }
+        }
 
-            // Check if it's time for LLM analysis (every 3 hours)
-            let since_last_analysis = Utc::now() - self.last_llm_analysis;
-            if since_last_analysis.num_seconds() >= llm_interval_secs as i64 {
-                let state = self.state_manager.read().await;
-                if let Some(session) = state.current_session() {
-                    let session_id = session.id;
-                    drop(state);
-
-                    log::info!("Triggering scheduled LLM analysis");
-                    match self.analyze_and_log_batch(session_id).await {
-                        Ok(_) => log::info!("Scheduled analysis completed"),
-                        Err(e) => log::error!("Scheduled analysis failed: {:#}", e),
-                    }
+        Ok(())
+    }
+
+    /// Fallback logging when the LLM batch analysis is off: the issue override, or else the
+    /// configured detection pipeline, picks the issue for each of the (unlogged) activities.
+    /// Micro activities are only logged with `tracking.log_micro_aggregate`, summed per issue.
+    async fn fallback_detection_logging(
+        &mut self,
+        session_id: i64,
+        activities: &[StoredActivity],
+        micro: &[StoredActivity],
+    ) -> Result<()> {
+        let Some(jira) = &self.jira else {
+            return Ok(());
+        };
+        // The override is checked once for the whole batch; unassigned, nothing is logged
+        let issue_override = match self.current_override().await {
+            Some(issue_key) => match jira.filter_assigned(std::slice::from_ref(&issue_key)).await {
+                Ok(assigned) if assigned.contains(&issue_key) => Some(issue_key),
+                Ok(_) => {
+                    log::warn!("Skipping {} - not assigned to you", issue_key);
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::error!("Failed to check assignment for {}: {}", issue_key, e);
+                    return Ok(());
+                }
+            },
+            None => None,
+        };
+        let assigned = match issue_override {
+            Some(_) => Vec::new(),
+            None => jira.get_assigned_issues().await?,
+        };
+
+        for stored_activity in activities {
+            let activity = Activity {
+                timestamp: stored_activity.timestamp,
+                duration_secs: stored_activity.duration_secs,
+                window_title: stored_activity.window_title.clone(),
+                app_name: stored_activity.app_name.clone(),
+                description: stored_activity.description.clone(),
+                browser_url: stored_activity.browser_url.clone(),
+            };
+
+            if let Some((issue_key, attribution)) = self
+                .fallback_target_issue(issue_override.as_deref(), &assigned, stored_activity)
+                .await
+            {
+                self.log_fallback_worklog(
+                    session_id,
+                    &issue_key,
+                    attribution,
+                    &activity,
+                    &[stored_activity.id],
+                )
+                .await?;
+            }
+        }
+
+        if self.config.tracking.log_micro_aggregate {
+            let mut by_issue: BTreeMap<String, (AttributionSource, Vec<&StoredActivity>)> =
+                BTreeMap::new();
+            for stored_activity in micro {
+                if let Some((issue_key, attribution)) = self
+                    .fallback_target_issue(issue_override.as_deref(), &assigned, stored_activity)
+                    .await
+                {
+                    by_issue
+                        .entry(issue_key)
+                        .or_insert_with(|| (attribution, Vec::new()))
+                        .1
+                        .push(stored_activity);
                 }
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(interval_secs)).await;
+            for (issue_key, (attribution, group)) in by_issue {
+                let activity = aggregate_activities(&group);
+                let activity_ids: Vec<i64> = group.iter().map(|a| a.id).collect();
+                log::info!(
+                    "Logging {} micro activities to {} as one worklog ({})",
+                    group.len(),
+                    issue_key,
+                    format_hm(activity.duration_secs)
+                );
+                self.log_fallback_worklog(
+                    session_id,
+                    &issue_key,
+                    attribution,
+                    &activity,
+                    &activity_ids,
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The issue an activity is logged to without the LLM: the override (already checked to
+    /// be assigned to you), otherwise the first assigned issue the detection pipeline finds
+    async fn fallback_target_issue(
+        &self,
+        issue_override: Option<&str>,
+        assigned: &[AssignedIssue],
+        stored_activity: &StoredActivity,
+    ) -> Option<(String, AttributionSource)> {
+        if let Some(issue_key) = issue_override {
+            Some((issue_key.to_string(), AttributionSource::Override))
+        } else {
+            // The pipeline only returns issues assigned to the user
+            match self.detector.detect(stored_activity, assigned).await {
+                Ok(result) => result.map(|d| (d.issue_key, d.source)),
+                Err(err) => {
+                    log::error!("Failed to detect Jira issue: {}", err);
+                    None
+                }
+            }
         }
     }
+
+    /// Post one worklog covering `activity_ids` and record it; failures are logged, not returned
+    async fn log_fallback_worklog(
+        &mut self,
+        session_id: i64,
+        issue_key: &str,
+        attribution: AttributionSource,
+        activity: &Activity,
+        activity_ids: &[i64],
+    ) -> Result<()> {
+        let Some(jira) = &self.jira else {
+            return Ok(());
+        };
+
+        match jira.log_work(issue_key, activity).await {
+            Ok(_) if self.config.tracking.dry_run => {}
+            Ok(worklog_id) => {
+                tracing::info!(
+                    session_id,
+                    issue_key = %issue_key,
+                    duration_secs = activity.duration_secs,
+                    worklog_id = %worklog_id,
+                    "Logged to Jira: {}",
+                    issue_key
+                );
+                self.database.mark_activities_logged(activity_ids)?;
+                self.database
+                    .record_attribution(activity_ids, issue_key, attribution)?;
+                self.database.store_worklog(
+                    session_id,
+                    issue_key,
+                    &worklog_id,
+                    activity.duration_secs,
+                    activity_ids,
+                    activity.timestamp,
+                )?;
+                self.summaries.record(issue_key, activity.duration_secs);
+                self.metrics.record_worklog_posted();
+            }
+            Err(e) if e.is::<IssueNotFound>() => {
+                self.missing_issues.record(
+                    &self.database,
+                    issue_key,
+                    activity_ids,
+                    activity.duration_secs,
+                )?;
+            }
+            Err(e) => {
+                self.metrics.record_failure(Failure::Worklog);
+                tracing::error!(
+                    session_id,
+                    issue_key = %issue_key,
+                    duration_secs = activity.duration_secs,
+                    "Failed to log to Jira: {}",
+                    e
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delete the most recent session's worklogs from Jira and mark their activities unlogged.
+    /// Worklogs already deleted in Jira are rolled back too; ones Jira refuses to delete are
+    /// kept and reported with the reason.
+    pub async fn undo_last_worklogs(&mut self) -> Result<UndoOutcome> {
+        let jira = self
+            .jira
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Jira integration is disabled"))?;
+
+        let worklogs = self.database.get_last_session_worklogs()?;
+        let mut outcome = UndoOutcome::default();
+
+        for worklog in worklogs {
+            match jira
+                .delete_worklog(&worklog.issue_key, &worklog.worklog_id)
+                .await
+            {
+                Ok(_) => {
+                    self.database.rollback_worklog(&worklog)?;
+                    outcome.undone.push(worklog);
+                }
+                Err(e) => {
+                    log::error!(
+                        "Failed to delete worklog {} on {}: {:#}",
+                        worklog.worklog_id,
+                        worklog.issue_key,
+                        e
+                    );
+                    outcome.failed.push((worklog, format!("{:#}", e)));
+                }
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    /// Merge captures of the same window; with `consolidate_by = "issue_key"` the issue key
+    /// in the title (or else the URL) is part of what makes a window the same
+    fn consolidate_activities(&self, activities: &[Activity]) -> Vec<Activity> {
+        let mut consolidated: HashMap<String, Activity> = HashMap::new();
+        let issue_key_regex = match self.config.tracking.consolidate_by {
+            ConsolidationMode::Window => None,
+            ConsolidationMode::IssueKey => {
+                Some(build_issue_key_regex(&self.config.jira.project_keys))
+            }
+        };
+        let use_denylist = self.config.jira.project_keys.is_empty();
+
+        for activity in activities {
+            let mut key = format!("{}:{}", activity.app_name, activity.window_title);
+            if let Some(regex) = &issue_key_regex {
+                let issue_key = detect_issue_key(regex, &activity.window_title, use_denylist)
+                    .or_else(|| {
+                        activity
+                            .browser_url
+                            .as_deref()
+                            .and_then(|url| detect_issue_key(regex, url, use_denylist))
+                    });
+                if let Some(issue_key) = issue_key {
+                    key = format!("{}:{}", key, issue_key);
+                }
+            }
+
+            consolidated
+                .entry(key)
+                .and_modify(|existing| existing.merge(activity))
+                .or_insert_with(|| activity.clone());
+        }
+
+        consolidated.into_values().collect()
+    }
+
+    /// Main run loop with state-aware polling. Runs until `shutdown` changes (or its sender is
+    /// dropped), then does a final sync and, for an active session, a final analysis.
+    pub async fn run(
+        &mut self,
+        mut interval_secs: u64,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> Result<()> {
+        log::info!(
+            "Starting work tracker (polling every {} seconds)...",
+            interval_secs
+        );
+
+        log::debug!(
+            "Scheduled analyses are jittered by {}s",
+            self.llm_jitter_secs
+        );
+
+        loop {
+            if let Some(settings) = &self.tracking_settings {
+                let tracking = settings.read().await.clone();
+                if tracking.screenpipe_poll_interval_secs != interval_secs {
+                    log::info!(
+                        "Polling every {} seconds",
+                        tracking.screenpipe_poll_interval_secs
+                    );
+                    interval_secs = tracking.screenpipe_poll_interval_secs;
+                }
+                self.config.tracking = tracking;
+            }
+            let llm_interval_secs = self.config.tracking.llm_batch_interval_secs;
+
+            if let Some(path) = &self.heartbeat {
+                if let Err(e) = heartbeat::beat(path, Utc::now()) {
+                    log::warn!("{:#}", e);
+                }
+            }
+
+            // Screenpipe sync (every 5 min)
+            match self.sync().await {
+                Ok(_) => log::debug!("Sync completed successfully"),
+                Err(e) => {
+                    self.metrics.record_failure(Failure::Sync);
+                    log::error!("Sync failed: {:#}", e)
+                }
+            }
+
+            // Check if it's time for LLM analysis (every 3 hours)
+            if analysis_due(
+                Utc::now(),
+                self.last_llm_analysis,
+                llm_interval_secs,
+                self.llm_jitter_secs,
+                self.config.tracking.align_llm_batches,
+            ) {
+                let current = self
+                    .state_manager
+                    .read()
+                    .await
+                    .current_session()
+                    .map(|session| session.id);
+                if let Some(session_id) = current {
+                    match self.long_enough_to_analyze(session_id) {
+                        Ok(true) => {
+                            log::info!("Triggering scheduled LLM analysis");
+                            match self.analyze_and_log_batch(session_id).await {
+                                Ok(_) => log::info!("Scheduled analysis completed"),
+                                Err(e) => log::error!("Scheduled analysis failed: {:#}", e),
+                            }
+                        }
+                        Ok(false) => {}
+                        Err(e) => log::error!("Failed to check session length: {:#}", e),
+                    }
+                }
+
+                if let Err(e) = self.analyze_sessions_with_manual_entries(current).await {
+                    log::error!("Failed to look up manual entries to log: {:#}", e);
+                }
+            }
+
+            // Hourly/daily summaries may come due between analyses
+            self.summaries.flush().await;
+
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(interval_secs)) => {}
+                _ = shutdown.changed() => break,
+            }
+        }
+
+        self.finish().await
+    }
+
+    /// Flush the last window of work: sync once more and stop the active session,
+    /// which runs its final analysis when `analyze_on_stop` is set
+    async fn finish(&mut self) -> Result<()> {
+        log::info!("Tracker shutting down, flushing final activities");
+
+        if let Err(e) = self.sync().await {
+            log::error!("Final sync failed: {:#}", e);
+        }
+
+        let active = !self.state_manager.read().await.current_state().is_stopped();
+        if active {
+            self.stop_tracking().await?;
+        }
+
+        self.summaries.flush().await;
+        Ok(())
+    }
+}
+
+/// Whether a scheduled analysis is due at `now`. The next run is `interval_secs` after
+/// `last_analysis`, or the next UTC multiple of `interval_secs` when `align` is set,
+/// delayed by `jitter_secs`.
+fn analysis_due(
+    now: DateTime<Utc>,
+    last_analysis: DateTime<Utc>,
+    interval_secs: u64,
+    jitter_secs: i64,
+    align: bool,
+) -> bool {
+    let interval = (interval_secs as i64).max(1);
+    let last = last_analysis.timestamp();
+    let next = if align {
+        (last.div_euclid(interval) + 1) * interval
+    } else {
+        last + interval
+    };
+
+    now.timestamp() >= next + jitter_secs
+}
+
+/// Result of `WorkTracker::undo_last_worklogs`
+#[derive(Debug, Default)]
+pub struct UndoOutcome {
+    /// Worklogs deleted from Jira (or found already gone) and rolled back locally
+    pub undone: Vec<StoredWorklog>,
+    /// Worklogs Jira did not delete, with the error; still recorded as logged
+    pub failed: Vec<(StoredWorklog, String)>,
+}
+
+/// Issues found deleted or moved while logging, with the activities and time that could
+/// not be logged to each
+#[derive(Default)]
+struct MissingIssues(BTreeMap<String, (usize, u64)>);
+
+impl MissingIssues {
+    /// Mark the activities unloggable so later analyses don't retry them
+    fn record(
+        &mut self,
+        database: &Database,
+        issue_key: &str,
+        activity_ids: &[i64],
+        time_secs: u64,
+    ) -> Result<()> {
+        log::warn!(
+            "{} was not found in Jira (deleted or moved); {} will not be logged",
+            issue_key,
+            format_hm(time_secs)
+        );
+        database.mark_activities_unloggable(activity_ids)?;
+        let (activities, secs) = self.0.entry(issue_key.to_string()).or_default();
+        *activities += activity_ids.len();
+        *secs += time_secs;
+        Ok(())
+    }
+
+    /// Surface each missing issue as an analysis flag
+    fn flag(self, database: &Database, session_id: i64) -> Result<()> {
+        for (issue_key, (activities, time_secs)) in self.0 {
+            database.store_flag(
+                session_id,
+                FlagKind::Unloggable,
+                &format!(
+                    "{} was not found in Jira (deleted or moved); {} activities were not logged",
+                    issue_key, activities
+                ),
+                time_secs,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Identifies a single Screenpipe capture across overlapping polls
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct CaptureKey {
+    timestamp: DateTime<Utc>,
+    app_name: String,
+    window_title: String,
+}
+
+impl CaptureKey {
+    fn of(activity: &Activity) -> Self {
+        Self {
+            timestamp: activity.timestamp,
+            app_name: activity.app_name.clone(),
+            window_title: activity.window_title.clone(),
+        }
+    }
+}
+
+/// Time `account_id` logged to `issue_key` with worklogs started in `from..=to` that this
+/// tracker didn't post, e.g. entered by hand in Jira
+async fn externally_logged_secs(
+    jira: &JiraClient,
+    database: &Database,
+    account_id: &str,
+    issue_key: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<u64> {
+    let posted_by_tracker = database.get_worklog_ids(issue_key)?;
+    let worklogs = jira.get_worklogs(issue_key, from).await?;
+
+    Ok(worklogs
+        .iter()
+        .filter(|w| {
+            w.author
+                .as_ref()
+                .is_some_and(|author| author.account_id == account_id)
+        })
+        .filter(|w| !posted_by_tracker.contains(&w.id))
+        .filter(|w| {
+            w.started_at()
+                .is_some_and(|started| started >= from && started <= to)
+        })
+        .map(|w| w.time_spent_seconds)
+        .sum())
+}
+
+/// Ask the LLM about activities the batch analysis left unmatched one at a time, longest
+/// first and at most `llm.max_fallback_suggestions` of them. Suggestions confident enough for
+/// their project join the batch's match for that issue, or a new match per issue when the
+/// batch has none that will be logged.
+async fn salvage_unmatched(
+    llm: &LLMAnalyzer,
+    analysis: &mut AnalysisResult,
+    by_id: &HashMap<i64, StoredActivity>,
+    assigned_issues: &[AssignedIssue],
+    config: &LLMConfig,
+    metrics: &Metrics,
+) {
+    let mut candidates: Vec<&StoredActivity> = analysis
+        .unmatched
+        .activities
+        .iter()
+        .filter_map(|id| by_id.get(id))
+        .collect();
+    candidates.sort_by_key(|a| std::cmp::Reverse(a.duration_secs));
+    candidates.truncate(config.max_fallback_suggestions);
+
+    // Per issue: the lowest confidence among its suggestions, and the activities
+    let mut salvaged: BTreeMap<String, (f64, Vec<&StoredActivity>)> = BTreeMap::new();
+    for activity in candidates {
+        metrics.record_llm_call();
+        match llm.suggest_issue(activity, assigned_issues).await {
+            Ok(Some(suggestion)) => {
+                let threshold = config.confidence_threshold_for(&suggestion.key);
+                match suggestion.confidence {
+                    Some(confidence) if confidence >= threshold => {
+                        let (lowest, activities) = salvaged
+                            .entry(suggestion.key)
+                            .or_insert((confidence, Vec::new()));
+                        *lowest = lowest.min(confidence);
+                        activities.push(activity);
+                    }
+                    confidence => log::debug!(
+                        "Not matching activity {} to {}: confidence {:?} (needs {:.2})",
+                        activity.id,
+                        suggestion.key,
+                        confidence,
+                        threshold
+                    ),
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                metrics.record_failure(Failure::Llm);
+                // Most likely the endpoint is down, which would fail every other request too
+                log::warn!("Stopping single-activity suggestions: {:#}", e);
+                break;
+            }
+        }
+    }
+
+    for (key, (confidence, activities)) in salvaged {
+        let ids: Vec<i64> = activities.iter().map(|a| a.id).collect();
+        let time_secs: u64 = activities.iter().map(|a| a.duration_secs).sum();
+        log::info!(
+            "Matched {} unmatched activities to {} one by one ({})",
+            ids.len(),
+            key,
+            format_hm(time_secs)
+        );
+
+        analysis.unmatched.activities.retain(|id| !ids.contains(id));
+        analysis.unmatched.total_time_secs =
+            analysis.unmatched.total_time_secs.saturating_sub(time_secs);
+        analysis.total_productive_time_secs += time_secs;
+
+        // Joining the batch's match keeps the issue to one worklog
+        let threshold = config.confidence_threshold_for(&key);
+        match analysis
+            .issues
+            .iter_mut()
+            .find(|m| m.key == key && m.confidence >= threshold)
+        {
+            Some(existing) => {
+                existing.total_time_secs += time_secs;
+                existing.activities_included.extend(ids);
+            }
+            // Described by its longest activity (activities are sorted longest first)
+            None => analysis.issues.push(IssueMatch {
+                confidence,
+                summary: activities[0].window_title.clone(),
+                work_type: String::new(),
+                key,
+                total_time_secs: time_secs,
+                activities_included: ids,
+            }),
+        }
+    }
+}
+
+/// Pick a delay in `0..=max_secs` that differs between processes
+fn random_jitter_secs(max_secs: u64) -> i64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    if max_secs == 0 {
+        return 0;
+    }
+    // RandomState is seeded randomly per process, which is all the randomness we need
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (random % (max_secs + 1)) as i64
+}
+
+/// `jira.timezone` parsed, or `None` for the system zone
+fn jira_timezone(config: &Config) -> Result<Option<Tz>> {
+    config
+        .jira
+        .timezone
+        .as_deref()
+        .map(|name| {
+            name.parse::<Tz>()
+                .map_err(|e| anyhow::anyhow!("Invalid jira.timezone '{}': {}", name, e))
+        })
+        .transpose()
+}
+
+/// Group activities by the day they started on in `timezone` (the system zone when `None`),
+/// in date order
+fn split_by_day(
+    activities: &[StoredActivity],
+    timezone: Option<Tz>,
+) -> Vec<(NaiveDate, Vec<StoredActivity>)> {
+    let mut days: BTreeMap<NaiveDate, Vec<StoredActivity>> = BTreeMap::new();
+    for activity in activities {
+        let date = match timezone {
+            Some(tz) => activity.timestamp.with_timezone(&tz).date_naive(),
+            None => activity.timestamp.with_timezone(&Local).date_naive(),
+        };
+        days.entry(date).or_default().push(activity.clone());
+    }
+    days.into_iter().collect()
+}
+
+/// Split activities at the edges of `hours` in `timezone` (the system zone when `None`) into
+/// the time inside the window and the time outside it. An activity crossing an edge shows up
+/// in both, with the same id: inside shortened to (and starting at) its working time, outside
+/// holding the rest.
+fn clip_to_working_hours(
+    activities: Vec<StoredActivity>,
+    hours: &WorkingHours,
+    timezone: Option<Tz>,
+) -> (Vec<StoredActivity>, Vec<StoredActivity>) {
+    let mut inside = Vec::new();
+    let mut outside = Vec::new();
+
+    for activity in activities {
+        let start = activity.timestamp;
+        let end = start + Duration::seconds(activity.duration_secs as i64);
+
+        let mut inside_secs = 0;
+        let mut first_inside = None;
+        for (window_start, window_end) in working_windows(start, end, hours, timezone) {
+            let from = window_start.max(start);
+            let to = window_end.min(end);
+            if from < to {
+                inside_secs += (to - from).num_seconds() as u64;
+                first_inside.get_or_insert(from);
+            }
+        }
+
+        let outside_secs = activity.duration_secs.saturating_sub(inside_secs);
+        if outside_secs > 0 {
+            outside.push(StoredActivity {
+                duration_secs: outside_secs,
+                ..activity.clone()
+            });
+        }
+        if let Some(from) = first_inside {
+            inside.push(StoredActivity {
+                timestamp: from,
+                duration_secs: inside_secs,
+                ..activity
+            });
+        }
+    }
+
+    (inside, outside)
+}
+
+/// Longest stretch of local time a DST change skips (a few zones jump by two hours)
+const MAX_DST_GAP_MINUTES: i64 = 3 * 60;
+
+/// `local` in `timezone` (the system zone when `None`) as UTC. An ambiguous time takes its
+/// earlier reading; a time skipped by DST moves to the end of the gap, so the day isn't lost
+fn local_to_utc(local: NaiveDateTime, timezone: Option<Tz>) -> Option<DateTime<Utc>> {
+    let resolve = |local: NaiveDateTime| match timezone {
+        Some(tz) => tz
+            .from_local_datetime(&local)
+            .earliest()
+            .map(|at| at.with_timezone(&Utc)),
+        None => Local
+            .from_local_datetime(&local)
+            .earliest()
+            .map(|at| at.with_timezone(&Utc)),
+    };
+    (0..=MAX_DST_GAP_MINUTES).find_map(|minutes| resolve(local + Duration::minutes(minutes)))
+}
+
+/// Start and end of `date` in `timezone` (the system zone when `None`), in UTC
+fn local_day(date: NaiveDate, timezone: Option<Tz>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = local_to_utc(date.and_time(NaiveTime::MIN), timezone)?;
+    let end = local_to_utc(date.succ_opt()?.and_time(NaiveTime::MIN), timezone)?;
+    Some((start, end))
+}
+
+/// The working-hours window of each working day from `start` to `end`, in UTC
+fn working_windows(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    hours: &WorkingHours,
+    timezone: Option<Tz>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let local_date = |at: DateTime<Utc>| match timezone {
+        Some(tz) => at.with_timezone(&tz).date_naive(),
+        None => at.with_timezone(&Local).date_naive(),
+    };
+
+    let mut windows = Vec::new();
+    let last = local_date(end);
+    let mut day = local_date(start);
+    while day <= last {
+        if hours.days.contains(&day.weekday()) {
+            if let (Some(from), Some(to)) = (
+                local_to_utc(day.and_time(hours.start), timezone),
+                local_to_utc(day.and_time(hours.end), timezone),
+            ) {
+                windows.push((from, to));
+            }
+        }
+        match day.succ_opt() {
+            Some(next) => day = next,
+            None => break,
+        }
+    }
+    windows
+}
+
+/// A worklog for one day's share of an issue match
+#[derive(Debug, PartialEq)]
+struct DayWorklog {
+    started: DateTime<Utc>,
+    time_secs: u64,
+    activity_ids: Vec<i64>,
+}
+
+/// Divide `time_secs` between the days in proportion to each day's activity time. Each
+/// worklog starts with that day's first activity; the last day takes any rounding remainder.
+fn share_by_day(days: Vec<(NaiveDate, Vec<StoredActivity>)>, time_secs: u64) -> Vec<DayWorklog> {
+    let total_activity_secs: u64 = days
+        .iter()
+        .flat_map(|(_, activities)| activities)
+        .map(|a| a.duration_secs)
+        .sum();
+    let day_count = days.len();
+    let mut remaining = time_secs;
+
+    days.into_iter()
+        .enumerate()
+        .map(|(index, (_, activities))| {
+            let day_secs = activities.iter().map(|a| a.duration_secs).sum::<u64>();
+            let share = if index + 1 == day_count {
+                remaining
+            } else {
+                (time_secs * day_secs)
+                    .checked_div(total_activity_secs)
+                    .unwrap_or(time_secs / day_count as u64)
+            };
+            remaining -= share;
+
+            DayWorklog {
+                started: activities
+                    .iter()
+                    .map(|a| a.timestamp)
+                    .min()
+                    .expect("days are never empty"),
+                time_secs: share,
+                activity_ids: activities.iter().map(|a| a.id).collect(),
+            }
+        })
+        .collect()
+}
+
+/// One activity standing for `activities`: their total time from the earliest start, under
+/// the app and title of the longest
+fn aggregate_activities(activities: &[&StoredActivity]) -> Activity {
+    let longest = activities
+        .iter()
+        .max_by_key(|a| a.duration_secs)
+        .expect("aggregate of no activities");
+    Activity {
+        timestamp: activities
+            .iter()
+            .map(|a| a.timestamp)
+            .min()
+            .unwrap_or(longest.timestamp),
+        duration_secs: activities.iter().map(|a| a.duration_secs).sum(),
+        window_title: longest.window_title.clone(),
+        app_name: longest.app_name.clone(),
+        description: longest.description.clone(),
+        browser_url: longest.browser_url.clone(),
+    }
+}
+
+/// Drop consolidated activities shorter than `min_duration_secs`, returning the rest and how many were dropped
+fn drop_short_activities(
+    activities: Vec<Activity>,
+    min_duration_secs: u64,
+) -> (Vec<Activity>, usize) {
+    let total = activities.len();
+    let kept: Vec<Activity> = activities
+        .into_iter()
+        .filter(|activity| activity.duration_secs >= min_duration_secs)
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+/// Whether captures in `break_apps` (by app name or window title) make up more than half
+/// of the captured time
+fn break_apps_dominate(activities: &[Activity], break_apps: &[String]) -> bool {
+    let break_apps: Vec<String> = break_apps
+        .iter()
+        .map(|app| app.trim().to_lowercase())
+        .filter(|app| !app.is_empty())
+        .collect();
+    if break_apps.is_empty() {
+        return false;
+    }
+
+    let is_break_app = |activity: &Activity| {
+        let app_name = activity.app_name.to_lowercase();
+        let window_title = activity.window_title.to_lowercase();
+        break_apps
+            .iter()
+            .any(|app| app_name.contains(app.as_str()) || window_title.contains(app.as_str()))
+    };
+    let total_secs: u64 = activities.iter().map(|a| a.duration_secs).sum();
+    let break_secs: u64 = activities
+        .iter()
+        .filter(|a| is_break_app(a))
+        .map(|a| a.duration_secs)
+        .sum();
+    break_secs * 2 > total_secs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveTime, Weekday};
+    use tempfile::NamedTempFile;
+
+    /// Screenpipe stand-in that never returns any activities
+    async fn serve_empty_screenpipe() -> u16 {
+        use axum::{routing::get, Json, Router};
+
+        let app = Router::new()
+            .route(
+                "/search",
+                get(|| async { Json(serde_json::json!({ "data": [] })) }),
+            )
+            .route("/health", get(|| async { "ok" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        port
+    }
+
+    /// Jira stand-in with `assigned` issues that accepts every worklog
+    async fn serve_jira(assigned: &'static [&'static str]) -> String {
+        serve_jira_with_missing(assigned, &[]).await
+    }
+
+    /// Like `serve_jira`, but worklogs posted to `missing` issues get a 404
+    async fn serve_jira_with_missing(
+        assigned: &'static [&'static str],
+        missing: &'static [&'static str],
+    ) -> String {
+        use axum::{
+            extract::Path,
+            http::StatusCode,
+            routing::{get, post},
+            Json, Router,
+        };
+
+        let app = Router::new()
+            .route(
+                "/rest/api/3/myself",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "accountId": "abc",
+                        "emailAddress": "dev@example.com",
+                        "displayName": "Dev"
+                    }))
+                }),
+            )
+            .route(
+                "/rest/api/3/search",
+                get(move || async move {
+                    let issues: Vec<_> = assigned
+                        .iter()
+                        .map(|key| serde_json::json!({ "key": key, "fields": { "summary": key } }))
+                        .collect();
+                    Json(serde_json::json!({ "issues": issues, "total": assigned.len() }))
+                }),
+            )
+            .route(
+                "/rest/api/3/issue/:key/worklog",
+                post(move |Path(key): Path<String>| async move {
+                    if missing.contains(&key.as_str()) {
+                        return Err(StatusCode::NOT_FOUND);
+                    }
+                    Ok(Json(serde_json::json!({ "id": "10001" })))
+                }),
+            )
+            .route(
+                "/rest/api/3/issue/:key",
+                get(move |Path(key): Path<String>| async move {
+                    if missing.contains(&key.as_str()) {
+                        let error = serde_json::json!({
+                            "errorMessages": ["Issue does not exist or you do not have permission to see it."]
+                        });
+                        return (StatusCode::NOT_FOUND, Json(error));
+                    }
+                    (StatusCode::OK, Json(serde_json::json!({ "key": key })))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    /// LLM stand-in answering every POST with `handler`
+    async fn serve_llm<H, T>(handler: H) -> String
+    where
+        H: axum::handler::Handler<T, ()>,
+        T: 'static,
+    {
+        let app = axum::Router::new().fallback(axum::routing::post(handler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    /// Batch analysis matching each `(key, secs, activity ids)` and leaving `unmatched` over
+    fn llm_analysis(
+        issues: &[(&str, u64, Vec<i64>)],
+        unmatched: &[i64],
+        unmatched_secs: u64,
+    ) -> serde_json::Value {
+        let matched: Vec<_> = issues
+            .iter()
+            .map(|(key, secs, ids)| {
+                serde_json::json!({
+                    "key": key,
+                    "total_time_secs": secs,
+                    "summary": "Work",
+                    "work_type": "development",
+                    "activities_included": ids,
+                    "confidence": 0.9
+                })
+            })
+            .collect();
+        serde_json::json!({ "analysis": {
+            "total_productive_time_secs": issues.iter().map(|(_, secs, _)| secs).sum::<u64>(),
+            "confidence": 0.9,
+            "issues": matched,
+            "unmatched": {
+                "total_time_secs": unmatched_secs,
+                "activities": unmatched,
+                "likely_reason": if unmatched.is_empty() { "" } else { "unclear" }
+            },
+            "micro_activities_merged": false,
+            "red_flags": []
+        }})
+    }
+
+    /// Config for batch analysis against stand-ins, with the database at `db_path`
+    fn analysis_config(db_path: &std::path::Path, jira_url: String, llm_url: String) -> Config {
+        let mut config = Config::default();
+        config.jira.url = jira_url;
+        config.llm.endpoint = llm_url;
+        config.llm.enabled = true;
+        config.salesforce.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.analytics.database_path = db_path.to_string_lossy().into_owned();
+        config
+    }
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn test_analysis_due_after_interval_plus_jitter() {
+        let last = at("2024-05-01T09:17:00Z");
+        assert!(!analysis_due(
+            at("2024-05-01T10:17:00Z"),
+            last,
+            3600,
+            30,
+            false
+        ));
+        assert!(analysis_due(
+            at("2024-05-01T10:17:30Z"),
+            last,
+            3600,
+            30,
+            false
+        ));
+    }
+
+    #[test]
+    fn test_analysis_due_aligned_to_wall_clock() {
+        let last = at("2024-05-01T09:17:00Z");
+        assert!(!analysis_due(
+            at("2024-05-01T09:59:59Z"),
+            last,
+            3600,
+            0,
+            true
+        ));
+        assert!(analysis_due(
+            at("2024-05-01T10:00:00Z"),
+            last,
+            3600,
+            0,
+            true
+        ));
+
+        // Three-hour batches land on 00:00, 03:00, 06:00, ... UTC
+        assert!(!analysis_due(
+            at("2024-05-01T11:59:00Z"),
+            last,
+            10800,
+            20,
+            true
+        ));
+        assert!(analysis_due(
+            at("2024-05-01T12:00:20Z"),
+            last,
+            10800,
+            20,
+            true
+        ));
+    }
+
+    #[test]
+    fn test_random_jitter_is_bounded() {
+        assert_eq!(random_jitter_secs(0), 0);
+        for _ in 0..100 {
+            assert!((0..=5).contains(&random_jitter_secs(5)));
+        }
+    }
+
+    /// Screenpipe stand-in returning nothing on the first search and one fresh entry afterwards
+    async fn serve_idle_then_active_screenpipe() -> u16 {
+        use axum::{routing::get, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let searches = Arc::new(AtomicUsize::new(0));
+        let app = Router::new().route(
+            "/search",
+            get(move || {
+                let searches = searches.clone();
+                async move {
+                    if searches.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return Json(serde_json::json!({ "data": [] }));
+                    }
+                    Json(serde_json::json!({ "data": [{
+                        "type": "OCR",
+                        "content": {
+                            "frame_id": 1,
+                            "text": "",
+                            "timestamp": Utc::now().to_rfc3339(),
+                            "app_name": "Code",
+                            "window_name": "PROJ-1 main.rs"
+                        }
+                    }] }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_empty_poll_is_recorded_as_idle_break() {
+        for detect_idle_gaps in [true, false] {
+            let mut config = Config::default();
+            config.jira.enabled = false;
+            config.nudging.enabled = false;
+            config.notifications.enabled = false;
+            config.tracking.min_activity_duration_secs = 0;
+            config.tracking.detect_idle_gaps = detect_idle_gaps;
+            config.screenpipe.port = serve_idle_then_active_screenpipe().await;
+
+            let temp_file = NamedTempFile::new().unwrap();
+            let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+            let mut tracker =
+                WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                    .unwrap();
+            tracker.start_tracking().await.unwrap();
+            let session_id = database.get_active_session().unwrap().unwrap().id;
+
+            // Empty window covering the last ten minutes
+            tracker.last_sync = Utc::now() - Duration::minutes(10);
+            tracker.sync().await.unwrap();
+            assert_eq!(tracker.idle_break.is_some(), detect_idle_gaps);
+
+            // Activity resumes and closes the break
+            tracker.sync().await.unwrap();
+            assert!(tracker.idle_break.is_none());
+            assert_eq!(
+                database
+                    .get_session_activities(session_id, None)
+                    .unwrap()
+                    .len(),
+                1
+            );
+
+            let break_secs = database.get_session_break_time(session_id).unwrap();
+            if detect_idle_gaps {
+                assert!(
+                    (595..=610).contains(&break_secs),
+                    "break of {}s",
+                    break_secs
+                );
+            } else {
+                assert_eq!(break_secs, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_break_apps_dominate() {
+        let mut call = activity("Weekly sync", 300);
+        call.app_name = "zoom.us".to_string();
+        let calendar = activity("Lunch - Calendar", 60);
+        let code = activity("PROJ-1 main.rs", 120);
+        let break_apps = ["Zoom".to_string(), "calendar".to_string()];
+
+        assert!(break_apps_dominate(
+            &[call.clone(), code.clone()],
+            &break_apps
+        ));
+        assert!(break_apps_dominate(
+            std::slice::from_ref(&calendar),
+            &break_apps
+        ));
+        assert!(!break_apps_dominate(&[calendar, code.clone()], &break_apps));
+        assert!(!break_apps_dominate(&[call, code], &[]));
+    }
+
+    #[tokio::test]
+    async fn test_break_app_pauses_billable_tracking() {
+        use axum::{routing::get, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // On a call for two polls, then back to coding
+        let polls = Arc::new(AtomicUsize::new(0));
+        let app = Router::new().route(
+            "/search",
+            get(move || {
+                let poll = polls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let entry = |seconds_ago: i64, app_name: &str, window_name: &str| {
+                        serde_json::json!({
+                            "type": "OCR",
+                            "content": {
+                                "timestamp": (Utc::now() - Duration::seconds(seconds_ago)).to_rfc3339(),
+                                "app_name": app_name,
+                                "window_name": window_name
+                            }
+                        })
+                    };
+                    let data = match poll {
+                        0 => vec![
+                            entry(20, "zoom.us", "Standup"),
+                            entry(19, "zoom.us", "Standup"),
+                            entry(18, "Code", "PROJ-1 main.rs"),
+                        ],
+                        1 => vec![entry(10, "zoom.us", "Standup")],
+                        _ => vec![entry(2, "Code", "PROJ-1 main.rs")],
+                    };
+                    Json(serde_json::json!({ "data": data }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let mut config = Config::default();
+        config.jira.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.tracking.min_activity_duration_secs = 0;
+        config.tracking.break_apps = vec!["Zoom".to_string()];
+        config.screenpipe.port = port;
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.start_tracking().await.unwrap();
+        let session_id = database.get_active_session().unwrap().unwrap().id;
+        tracker.last_sync = Utc::now() - Duration::seconds(30);
+
+        // Entering the call opens one break and stores nothing
+        tracker.sync().await.unwrap();
+        let break_id = tracker.app_break;
+        assert!(break_id.is_some());
+        tracker.sync().await.unwrap();
+        assert_eq!(tracker.app_break, break_id);
+        assert!(database
+            .get_session_activities(session_id, None)
+            .unwrap()
+            .is_empty());
+
+        // Leaving it closes the break and tracking carries on
+        tracker.sync().await.unwrap();
+        assert!(tracker.app_break.is_none());
+        assert_eq!(
+            database
+                .get_session_activities(session_id, None)
+                .unwrap()
+                .len(),
+            1
+        );
+        let break_secs = database.get_session_break_time(session_id).unwrap();
+        assert!((15..=25).contains(&break_secs), "break of {}s", break_secs);
+    }
+
+    #[tokio::test]
+    async fn test_late_capture_is_picked_up_by_next_poll() {
+        use axum::{extract::Query, routing::get, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let entry = |seconds_ago: i64, window_name: &str| {
+            serde_json::json!({
+                "type": "OCR",
+                "content": {
+                    "timestamp": (Utc::now() - Duration::seconds(seconds_ago)).to_rfc3339(),
+                    "app_name": "Code",
+                    "window_name": window_name
+                }
+            })
+        };
+        let first = entry(10, "PROJ-1 main.rs");
+        let searches = Arc::new(AtomicUsize::new(0));
+        let app = Router::new().route(
+            "/search",
+            get(
+                move |Query(params): Query<HashMap<String, i64>>| async move {
+                    let mut data = vec![first];
+                    if searches.fetch_add(1, Ordering::SeqCst) > 0 {
+                        // Captured before the first poll ended, but only written afterwards
+                        data.push(entry(7, "PROJ-1 main.rs"));
+                        data.push(entry(5, "PROJ-2 lib.rs"));
+                    }
+                    data.retain(|e| {
+                        let timestamp: DateTime<Utc> =
+                            e["content"]["timestamp"].as_str().unwrap().parse().unwrap();
+                        timestamp.timestamp() >= params["start_timestamp"]
+                    });
+                    Json(serde_json::json!({ "data": data }))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let mut config = Config::default();
+        config.jira.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.screenpipe.port = port;
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.start_tracking().await.unwrap();
+        let session_id = database.get_active_session().unwrap().unwrap().id;
+
+        tracker.sync().await.unwrap();
+        tracker.sync().await.unwrap();
+
+        // The late PROJ-1 capture extends the activity stored by the first poll
+        let mut stored: Vec<(String, u64)> = database
+            .get_session_activities(session_id, None)
+            .unwrap()
+            .into_iter()
+            .map(|a| (a.window_title, a.duration_secs))
+            .collect();
+        stored.sort();
+        assert_eq!(
+            stored,
+            vec![
+                ("PROJ-1 main.rs".to_string(), 120),
+                ("PROJ-2 lib.rs".to_string(), 60)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_browser_tabs_for_different_issues_stay_separate() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let mut config = Config::default();
+        config.tracking.consolidate_by = ConsolidationMode::IssueKey;
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database).unwrap();
+        let tab = |url: &str| {
+            let mut tab = activity("Jira - Google Chrome", 60);
+            tab.app_name = "Google Chrome".to_string();
+            tab.browser_url = Some(url.to_string());
+            tab
+        };
+        let captures = vec![
+            tab("https://corp.atlassian.net/browse/PROJ-1"),
+            tab("https://corp.atlassian.net/browse/PROJ-2"),
+            tab("https://corp.atlassian.net/browse/PROJ-1"),
+        ];
+
+        let mut consolidated = tracker.consolidate_activities(&captures);
+        consolidated.sort_by(|a, b| a.browser_url.cmp(&b.browser_url));
+        let merged: Vec<(&str, u64)> = consolidated
+            .iter()
+            .map(|a| (a.browser_url.as_deref().unwrap(), a.duration_secs))
+            .collect();
+        assert_eq!(
+            merged,
+            vec![
+                ("https://corp.atlassian.net/browse/PROJ-1", 120),
+                ("https://corp.atlassian.net/browse/PROJ-2", 60),
+            ]
+        );
+
+        // Grouping by window alone merges both tabs
+        tracker.config.tracking.consolidate_by = ConsolidationMode::Window;
+        assert_eq!(tracker.consolidate_activities(&captures).len(), 1);
+    }
+
+    #[test]
+    fn test_overlap_entries_are_not_stored_twice() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let mut tracker =
+            WorkTracker::with_database(Config::default(), Arc::new(RwLock::new(None)), database)
+                .unwrap();
+        let now = Utc::now();
+        let capture = |seconds_ago: i64, window_title: &str| {
+            let mut capture = activity(window_title, 60);
+            capture.timestamp = now - Duration::seconds(seconds_ago);
+            capture
+        };
+
+        let first = tracker.unseen(vec![
+            capture(40, "PROJ-1 main.rs"),
+            capture(20, "PROJ-1 main.rs"),
+        ]);
+        assert_eq!(first.len(), 2);
+
+        // Until the window is stored, a retry gets the same captures again
+        assert_eq!(tracker.unseen(first.clone()).len(), 2);
+        tracker.remember_seen(&first, now - Duration::seconds(30));
+
+        // The next window re-reads the last 30s: only the new capture gets through
+        let second = tracker.unseen(vec![
+            capture(20, "PROJ-1 main.rs"),
+            capture(10, "PROJ-1 main.rs"),
+        ]);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].timestamp, now - Duration::seconds(10));
+        tracker.remember_seen(&second, now);
+
+        // Captures older than the last window start are forgotten
+        assert!(tracker.seen_captures.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_counts_stored_activities() {
+        use axum::{routing::get, Json, Router};
+
+        let app = Router::new().route(
+            "/search",
+            get(|| async {
+                let entry = |seconds_ago: i64, window_name: &str| {
+                    serde_json::json!({
+                        "type": "OCR",
+                        "content": {
+                            "timestamp": (Utc::now() - Duration::seconds(seconds_ago)).to_rfc3339(),
+                            "app_name": "Code",
+                            "window_name": window_name
+                        }
+                    })
+                };
+                Json(serde_json::json!({
+                    "data": [entry(20, "PROJ-1 main.rs"), entry(10, "PROJ-2 lib.rs")]
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let mut config = Config::default();
+        config.jira.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.tracking.min_activity_duration_secs = 0;
+        config.screenpipe.port = port;
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let metrics = Arc::new(Metrics::default());
+        let mut tracker = WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database)
+            .unwrap()
+            .with_metrics(metrics.clone());
+        tracker.start_tracking().await.unwrap();
+        assert!(metrics
+            .render(0)
+            .contains("\nwtje_activities_stored_total 0\n"));
+
+        tracker.sync().await.unwrap();
+
+        let text = metrics.render(0);
+        assert!(
+            text.contains("\nwtje_activities_stored_total 2\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("\nwtje_worklogs_posted_total 0\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_poll_keeps_last_sync() {
+        use axum::{http::StatusCode, routing::get, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = Router::new().route(
+            "/search",
+            get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                StatusCode::SERVICE_UNAVAILABLE
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let mut config = Config::default();
+        config.jira.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.screenpipe.port = port;
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database).unwrap();
+        tracker.screenpipe = ScreenpipeClient::new(format!("http://127.0.0.1:{}", port))
+            .with_retry_delay(std::time::Duration::from_millis(10));
+        tracker.start_tracking().await.unwrap();
+
+        let last_sync = Utc::now() - Duration::minutes(10);
+        tracker.last_sync = last_sync;
+        assert!(tracker.sync().await.is_err());
+
+        assert_eq!(tracker.last_sync, last_sync);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert!(tracker.idle_break.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_short_session_is_not_analyzed() {
+        let mut config = Config::default();
+        config.jira.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.tracking.analyze_on_stop = true;
+        config.tracking.min_session_secs = 60;
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.start_tracking().await.unwrap();
+        let session_id = database.get_active_session().unwrap().unwrap().id;
+        database
+            .store_activity(session_id, &activity("PROJ-1 main.rs", 30))
+            .unwrap();
+
+        // Stopped right after an accidental start: 30 seconds at most
+        tracker.stop_tracking().await.unwrap();
+
+        assert_eq!(tracker.analyses_started, 0);
+        let stats = database.get_session_stats(session_id).unwrap();
+        assert!(stats.end_time.is_some());
+        assert_eq!(stats.total_activities, 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_runs_one_final_analysis() {
+        let mut config = Config::default();
+        config.jira.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.tracking.min_session_secs = 0;
+        config.screenpipe.port = serve_empty_screenpipe().await;
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let database = Database::new(temp_file.path().to_path_buf()).unwrap();
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.start_tracking().await.unwrap();
+
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        shutdown_tx.send(true).unwrap();
+        tracker.run(3600, shutdown_rx).await.unwrap();
+
+        assert_eq!(tracker.analyses_started, 1);
+        assert!(tracker
+            .state_manager
+            .read()
+            .await
+            .current_state()
+            .is_stopped());
+        assert!(database.get_active_session().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_override_attribution_is_recorded() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let mut config = Config::default();
+        config.jira.url = serve_jira(&["PROJ-9"]).await;
+        config.llm.enabled = false;
+        config.salesforce.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.analytics.database_path = db_path.to_string_lossy().into_owned();
+
+        let database = Database::new(db_path).unwrap();
+        let session_id = database.create_session().unwrap();
+        database
+            .store_activity(session_id, &activity("PROJ-1 main.rs", 1200))
+            .unwrap();
+        database
+            .store_activity(session_id, &activity("PROJ-9 notes", 1200))
+            .unwrap();
+
+        let issue_override = Arc::new(RwLock::new(Some("PROJ-9".to_string())));
+        let mut tracker =
+            WorkTracker::with_database(config, issue_override.clone(), database.clone()).unwrap();
+        let activities = database.get_unlogged_activities(session_id, None).unwrap();
+        tracker
+            .fallback_detection_logging(session_id, &activities, &[])
+            .await
+            .unwrap();
+
+        let stored = database.get_session_activities(session_id, None).unwrap();
+        for activity in &stored {
+            assert!(activity.logged_to_jira);
+            assert_eq!(activity.attributed_issue.as_deref(), Some("PROJ-9"));
+            assert_eq!(
+                activity.attribution_source,
+                Some(AttributionSource::Override)
+            );
+        }
+
+        // Without the override, the key in the title is used
+        *issue_override.write().await = None;
+        let session_id = database.create_session().unwrap();
+        database
+            .store_activity(session_id, &activity("PROJ-9 review", 1200))
+            .unwrap();
+        let activities = database.get_unlogged_activities(session_id, None).unwrap();
+        tracker
+            .fallback_detection_logging(session_id, &activities, &[])
+            .await
+            .unwrap();
+        let stored = database.get_session_activities(session_id, None).unwrap();
+        assert_eq!(stored[0].attribution_source, Some(AttributionSource::Regex));
+    }
+
+    #[tokio::test]
+    async fn test_micro_activities_aggregate_into_one_worklog() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let mut config = Config::default();
+        config.jira.url = serve_jira(&["PROJ-9"]).await;
+        config.llm.enabled = false;
+        config.salesforce.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.tracking.log_micro_aggregate = true;
+        config.analytics.database_path = db_path.to_string_lossy().into_owned();
+
+        let database = Database::new(db_path).unwrap();
+        let session_id = database.create_session().unwrap();
+        for (title, secs) in [
+            ("PROJ-9 main.rs", 300),
+            ("PROJ-9 tests", 240),
+            ("PROJ-9 PR", 120),
+        ] {
+            database
+                .store_activity(session_id, &activity(title, secs))
+                .unwrap();
+        }
+        database
+            .store_activity(session_id, &activity("Slack", 180))
+            .unwrap();
+
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+
+        let worklogs = database.get_last_session_worklogs().unwrap();
+        assert_eq!(worklogs.len(), 1);
+        assert_eq!(worklogs[0].issue_key, "PROJ-9");
+        assert_eq!(worklogs[0].time_spent_secs, 660);
+        assert_eq!(worklogs[0].activity_ids.len(), 3);
+
+        let unlogged = database.get_unlogged_activities(session_id, None).unwrap();
+        assert_eq!(unlogged.len(), 1);
+        assert_eq!(unlogged[0].window_title, "Slack");
+    }
+
+    #[tokio::test]
+    async fn test_missing_issue_does_not_stop_the_batch() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let mut config = Config::default();
+        config.jira.url =
+            serve_jira_with_missing(&["PROJ-1", "PROJ-2", "PROJ-3"], &["PROJ-2"]).await;
+        config.llm.enabled = false;
+        config.salesforce.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.analytics.database_path = db_path.to_string_lossy().into_owned();
+
+        let database = Database::new(db_path).unwrap();
+        let session_id = database.create_session().unwrap();
+        for title in ["PROJ-1 main.rs", "PROJ-2 deleted", "PROJ-3 docs"] {
+            database
+                .store_activity(session_id, &activity(title, 1200))
+                .unwrap();
+        }
+
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+
+        let logged: Vec<String> = database
+            .get_last_session_worklogs()
+            .unwrap()
+            .into_iter()
+            .map(|w| w.issue_key)
+            .collect();
+        assert_eq!(logged, vec!["PROJ-1", "PROJ-3"]);
+
+        // The activity for the missing issue isn't retried, and the analysis flags it
+        assert!(database
+            .get_unlogged_activities(session_id, None)
+            .unwrap()
+            .is_empty());
+        let flags = database.get_flags(session_id).unwrap();
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].kind, FlagKind::Unloggable);
+        assert!(flags[0].message.contains("PROJ-2"));
+        assert_eq!(flags[0].time_secs, 1200);
+    }
+
+    #[tokio::test]
+    async fn test_worklogs_made_by_hand_reduce_logged_time() {
+        use axum::{routing::get, Json, Router};
+        use std::sync::Mutex;
+
+        let started = Utc::now() - Duration::hours(2);
+        let by_hand = (started + Duration::minutes(30))
+            .format("%Y-%m-%dT%H:%M:%S%.3f%z")
+            .to_string();
+        let posted_secs = Arc::new(Mutex::new(Vec::new()));
+        let posted = posted_secs.clone();
+        let jira = Router::new()
+            .route(
+                "/rest/api/3/myself",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "accountId": "abc",
+                        "emailAddress": "dev@example.com",
+                        "displayName": "Dev"
+                    }))
+                }),
+            )
+            .route(
+                "/rest/api/3/search",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "issues": [{ "key": "PROJ-1", "fields": { "summary": "Fix login" } }],
+                        "total": 1
+                    }))
+                }),
+            )
+            .route(
+                "/rest/api/3/issue/:key/worklog",
+                get(move || async move {
+                    // 20 minutes logged by hand, plus a teammate's time that must not count
+                    Json(serde_json::json!({
+                        "total": 2,
+                        "worklogs": [
+                            { "id": "1", "author": { "accountId": "abc" }, "started": by_hand, "timeSpentSeconds": 1200 },
+                            { "id": "2", "author": { "accountId": "xyz" }, "started": by_hand, "timeSpentSeconds": 1800 }
+                        ]
+                    }))
+                })
+                .post(move |Json(body): Json<serde_json::Value>| async move {
+                    posted.lock().unwrap().push(body["timeSpentSeconds"].as_u64().unwrap());
+                    Json(serde_json::json!({ "id": "10001" }))
+                }),
+            );
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let database = Database::new(db_path.clone()).unwrap();
+        let session_id = database.create_session().unwrap();
+        let mut work = activity("PROJ-1 login.rs", 3600);
+        work.timestamp = started;
+        let activity_id = database.store_activity(session_id, &work).unwrap();
+
+        let llm_url = serve_llm(move || async move {
+            Json(llm_analysis(&[("PROJ-1", 3600, vec![activity_id])], &[], 0))
+        })
+        .await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let jira_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, jira).await.unwrap() });
+        let config = analysis_config(&db_path, jira_url, llm_url);
+
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+
+        assert_eq!(*posted_secs.lock().unwrap(), vec![2400]);
+        let worklogs = database.get_last_session_worklogs().unwrap();
+        assert_eq!(worklogs[0].time_spent_secs, 2400);
+    }
+
+    #[tokio::test]
+    async fn test_worklog_over_daily_limit_is_held_back() {
+        use axum::Json;
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let database = Database::new(db_path.clone()).unwrap();
+        let session_id = database.create_session().unwrap();
+        let mut long_day = activity("PROJ-1 login.rs", 12 * 3600);
+        long_day.timestamp = at("2024-05-01T07:00:00Z");
+        let long_id = database.store_activity(session_id, &long_day).unwrap();
+        let mut short = activity("PROJ-2 docs", 3600);
+        short.timestamp = at("2024-05-01T08:00:00Z");
+        let short_id = database.store_activity(session_id, &short).unwrap();
+        // PROJ-3 already has 7h30m logged that day, so another hour goes over
+        let mut earlier = activity("PROJ-3 api.rs", 7 * 3600 + 1800);
+        earlier.timestamp = at("2024-05-01T00:30:00Z");
+        let earlier_id = database.store_activity(session_id, &earlier).unwrap();
+        database.mark_activities_logged(&[earlier_id]).unwrap();
+        database
+            .store_worklog(
+                session_id,
+                "PROJ-3",
+                "9001",
+                earlier.duration_secs,
+                &[earlier_id],
+                earlier.timestamp,
+            )
+            .unwrap();
+        let mut more = activity("PROJ-3 api.rs", 3600);
+        more.timestamp = at("2024-05-01T20:00:00Z");
+        let more_id = database.store_activity(session_id, &more).unwrap();
+
+        // Matches only the activities sent, so a second analysis stays valid
+        let llm_url = serve_llm(move |Json(request): Json<serde_json::Value>| async move {
+            let sent = request["activities"]["billable"].to_string();
+            let issues: Vec<_> = [
+                ("PROJ-1", 12 * 3600, vec![long_id]),
+                ("PROJ-2", 3600, vec![short_id]),
+                ("PROJ-3", 3600, vec![more_id]),
+            ]
+            .into_iter()
+            .filter(|(_, _, ids)| sent.contains(&format!("\"id\":{},", ids[0])))
+            .collect();
+            Json(llm_analysis(&issues, &[], 0))
+        })
+        .await;
+        let jira_url = serve_jira(&["PROJ-1", "PROJ-2", "PROJ-3"]).await;
+        let mut config = analysis_config(&db_path, jira_url, llm_url);
+        config.jira.timezone = Some("UTC".to_string());
+        config.jira.max_daily_hours_per_issue = Some(8.0);
+
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+        // Held-back activities are analyzed again but not flagged again
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+
+        let worklogs = database.get_last_session_worklogs().unwrap();
+        assert_eq!(
+            worklogs
+                .iter()
+                .map(|w| w.issue_key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["PROJ-3", "PROJ-2"]
+        );
+
+        // The 12 hours and the extra PROJ-3 hour stay unlogged for review
+        let unlogged = database.get_unlogged_activities(session_id, None).unwrap();
+        let mut unlogged_ids: Vec<i64> = unlogged.iter().map(|a| a.id).collect();
+        unlogged_ids.sort();
+        assert_eq!(unlogged_ids, vec![long_id, more_id]);
+        let flags = database.get_flags(session_id).unwrap();
+        assert_eq!(flags.len(), 2);
+        assert!(flags.iter().all(|f| f.kind == FlagKind::OverDailyLimit));
+        assert_eq!(flags[0].time_secs, 12 * 3600);
+        assert!(flags[0].message.contains("PROJ-1"), "{}", flags[0].message);
+        assert!(
+            flags[0].message.contains("2024-05-01"),
+            "{}",
+            flags[0].message
+        );
+        assert_eq!(flags[1].time_secs, 3600);
+        assert!(flags[1].message.contains("PROJ-3"), "{}", flags[1].message);
+        assert!(flags[1].message.contains("8h 30m"), "{}", flags[1].message);
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_activity_is_salvaged_by_suggestion() {
+        use axum::Json;
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let database = Database::new(db_path.clone()).unwrap();
+        let session_id = database.create_session().unwrap();
+        let matched_id = database
+            .store_activity(session_id, &activity("PROJ-1 login.rs", 1800))
+            .unwrap();
+        let salvaged_id = database
+            .store_activity(session_id, &activity("checkout flow", 1200))
+            .unwrap();
+        let joining_id = database
+            .store_activity(session_id, &activity("login tests", 600))
+            .unwrap();
+        let strict_id = database
+            .store_activity(session_id, &activity("ops dashboard", 900))
+            .unwrap();
+        let unknown_id = database
+            .store_activity(session_id, &activity("news", 600))
+            .unwrap();
+
+        // Batch requests leave four activities unmatched; single-activity requests (which
+        // carry one "activity") place all but the news
+        let llm_url = serve_llm(move |Json(request): Json<serde_json::Value>| async move {
+            if let Some(activity) = request.get("activity") {
+                let (key, confidence) = match activity["id"].as_i64().unwrap() {
+                    id if id == salvaged_id => (Some("PROJ-2"), 0.9),
+                    id if id == joining_id => (Some("PROJ-1"), 0.9),
+                    id if id == strict_id => (Some("OPS-1"), 0.8),
+                    _ => (None, 0.0),
+                };
+                return Json(serde_json::json!({ "issue_key": key, "confidence": confidence }));
+            }
+            Json(llm_analysis(
+                &[("PROJ-1", 1800, vec![matched_id])],
+                &[salvaged_id, joining_id, strict_id, unknown_id],
+                3300,
+            ))
+        })
+        .await;
+        let jira_url = serve_jira(&["PROJ-1", "PROJ-2", "OPS-1"]).await;
+        let mut config = analysis_config(&db_path, jira_url, llm_url);
+        // OPS work needs more certainty than the suggestion has
+        config
+            .llm
+            .confidence_overrides
+            .insert("OPS".to_string(), 0.95);
+
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+
+        // The login tests join the batch's PROJ-1 worklog instead of getting a second one
+        let mut logged: Vec<_> = database
+            .get_last_session_worklogs()
+            .unwrap()
+            .into_iter()
+            .map(|w| (w.issue_key, w.time_spent_secs))
+            .collect();
+        logged.sort();
+        assert_eq!(
+            logged,
+            vec![("PROJ-1".to_string(), 2400), ("PROJ-2".to_string(), 1200)]
+        );
+
+        // Activities no suggestion placed confidently enough are reported as unmatched
+        let unlogged = database.get_unlogged_activities(session_id, None).unwrap();
+        assert_eq!(
+            unlogged.iter().map(|a| a.id).collect::<Vec<_>>(),
+            vec![strict_id, unknown_id]
+        );
+        let flags = database.get_flags(session_id).unwrap();
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].kind, FlagKind::Unmatched);
+        assert_eq!(flags[0].time_secs, 1500);
+    }
+
+    #[tokio::test]
+    async fn test_no_assigned_issues_logs_to_override() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let mut config = Config::default();
+        config.jira.url = serve_jira(&[]).await;
+        config.llm.enabled = true;
+        config.llm.endpoint = "http://127.0.0.1:9".to_string();
+        config.salesforce.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.analytics.database_path = db_path.to_string_lossy().into_owned();
+
+        let database = Database::new(db_path).unwrap();
+        let session_id = database.create_session().unwrap();
+        database
+            .store_activity(session_id, &activity("main.rs", 1200))
+            .unwrap();
+
+        // Without an override the time is flagged and left for the next analysis
+        let issue_override = Arc::new(RwLock::new(None));
+        let mut tracker =
+            WorkTracker::with_database(config, issue_override.clone(), database.clone()).unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+
+        let flags = database.get_flags(session_id).unwrap();
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].kind, FlagKind::Unmatched);
+        assert_eq!(flags[0].time_secs, 1200);
+        assert_eq!(
+            database
+                .get_unlogged_activities(session_id, None)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Later analyses only flag time that wasn't flagged before
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+        assert_eq!(database.get_flags(session_id).unwrap().len(), 1);
+        database
+            .store_activity(session_id, &activity("lib.rs", 600))
+            .unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+        let flags = database.get_flags(session_id).unwrap();
+        assert_eq!(flags.len(), 2);
+        assert_eq!(flags[1].time_secs, 600);
+
+        *issue_override.write().await = Some("OPS-3".to_string());
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+
+        let stored = database.get_session_activities(session_id, None).unwrap();
+        assert!(stored[0].logged_to_jira);
+        assert_eq!(stored[0].attributed_issue.as_deref(), Some("OPS-3"));
+        assert_eq!(
+            stored[0].attribution_source,
+            Some(AttributionSource::Override)
+        );
+        assert_eq!(
+            database.get_last_session_worklogs().unwrap()[0].issue_key,
+            "OPS-3"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_activity_crossing_working_hours_is_flagged_once() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let mut config = Config::default();
+        config.jira.url = serve_jira(&[]).await;
+        config.jira.timezone = Some("UTC".to_string());
+        config.llm.enabled = false;
+        config.salesforce.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.tracking.working_hours = Some(WorkingHours {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            days: vec![Weekday::Thu],
+        });
+        config.analytics.database_path = db_path.to_string_lossy().into_owned();
+
+        let database = Database::new(db_path).unwrap();
+        let session_id = database.create_session().unwrap();
+        // Thursday 16:30 to 17:30, with no issue to log its working half to
+        let mut late = activity("Notes", 3600);
+        late.timestamp = at("2024-05-02T16:30:00Z");
+        database.store_activity(session_id, &late).unwrap();
+
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+        tracker.analyze_and_log_batch(session_id).await.unwrap();
+
+        let flags = database.get_flags(session_id).unwrap();
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].kind, FlagKind::OffHours);
+        assert_eq!(flags[0].time_secs, 1800);
+        assert_eq!(
+            database
+                .get_unlogged_activities(session_id, None)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manual_entry_outside_a_session_is_logged() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let mut config = Config::default();
+        config.jira.url = serve_jira(&["PROJ-7"]).await;
+        config.llm.enabled = false;
+        config.salesforce.enabled = false;
+        config.nudging.enabled = false;
+        config.notifications.enabled = false;
+        config.analytics.database_path = db_path.to_string_lossy().into_owned();
+
+        // Entered while tracking was stopped, so it gets a session of its own
+        let database = Database::new(db_path).unwrap();
+        let start = at("2024-05-02T14:00:00Z");
+        let session_id = database
+            .create_closed_session(start, start + Duration::hours(1))
+            .unwrap();
+        let id = database
+            .add_manual_activity(
+                session_id,
+                Some("PROJ-7"),
+                start,
+                3600,
+                "Design review call",
+            )
+            .unwrap();
+
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        tracker
+            .analyze_sessions_with_manual_entries(None)
+            .await
+            .unwrap();
+
+        let worklogs = database.get_last_session_worklogs().unwrap();
+        assert_eq!(worklogs.len(), 1);
+        assert_eq!(worklogs[0].issue_key, "PROJ-7");
+        assert_eq!(worklogs[0].time_spent_secs, 3600);
+        assert_eq!(worklogs[0].activity_ids, vec![id]);
+        let stored = database.get_activity(id).unwrap().unwrap();
+        assert!(stored.logged_to_jira);
+        assert_eq!(stored.attribution_source, Some(AttributionSource::Manual));
+        assert!(database
+            .sessions_with_unlogged_manual_activities(None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_undo_rolls_back_worklogs_already_gone() {
+        use axum::{
+            extract::Path,
+            http::StatusCode,
+            routing::{delete, get},
+            Json, Router,
+        };
+
+        let app =
+            Router::new()
+                .route(
+                    "/rest/api/3/issue/:key/worklog/:id",
+                    delete(|Path((_, id)): Path<(String, String)>| async move {
+                        match id.as_str() {
+                            "10001" => StatusCode::NO_CONTENT,
+                            "10002" => StatusCode::NOT_FOUND,
+                            _ => StatusCode::FORBIDDEN,
+                        }
+                    }),
+                )
+                .route(
+                    "/rest/api/3/issue/:key",
+                    get(|Path(key): Path<String>| async move {
+                        Json(serde_json::json!({ "key": key }))
+                    }),
+                );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let db_path = data_dir.path().join("analytics.db");
+        let mut config = Config::default();
+        config.jira.url = format!("http://{}", addr);
+        config.analytics.database_path = db_path.to_string_lossy().into_owned();
+
+        let database = Database::new(db_path).unwrap();
+        let session_id = database.create_session().unwrap();
+        for (worklog_id, title) in [("10001", "deleted"), ("10002", "gone"), ("10003", "locked")] {
+            let logged = activity(title, 600);
+            let id = database.store_activity(session_id, &logged).unwrap();
+            database.mark_activities_logged(&[id]).unwrap();
+            database
+                .store_worklog(
+                    session_id,
+                    "PROJ-1",
+                    worklog_id,
+                    600,
+                    &[id],
+                    logged.timestamp,
+                )
+                .unwrap();
+        }
+
+        let mut tracker =
+            WorkTracker::with_database(config, Arc::new(RwLock::new(None)), database.clone())
+                .unwrap();
+        let outcome = tracker.undo_last_worklogs().await.unwrap();
+
+        let undone: Vec<&str> = outcome
+            .undone
+            .iter()
+            .map(|w| w.worklog_id.as_str())
+            .collect();
+        assert_eq!(undone, vec!["10001", "10002"]);
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].0.worklog_id, "10003");
+        assert!(outcome.failed[0].1.contains("403"));
+
+        // The locked one is still recorded, so the next undo tries it again
+        let remaining = database.get_last_session_worklogs().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].worklog_id, "10003");
+        assert_eq!(
+            database
+                .get_unlogged_activities(session_id, None)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    fn activity(window_title: &str, duration_secs: u64) -> Activity {
+        Activity {
+            timestamp: Utc::now(),
+            duration_secs,
+            window_title: window_title.to_string(),
+            app_name: "Code".to_string(),
+            description: String::new(),
+            browser_url: None,
+        }
+    }
+
+    #[test]
+    fn test_split_by_day_at_local_midnight() {
+        let stored = |id: i64, timestamp: &str, duration_secs: u64| {
+            let mut stored = StoredActivity::from(&activity("PROJ-1 main.rs", duration_secs));
+            stored.id = id;
+            stored.timestamp = at(timestamp);
+            stored
+        };
+        // 22:00 to 01:00 in New York (UTC-4 in May)
+        let activities = vec![
+            stored(1, "2024-05-02T02:00:00Z", 3600),
+            stored(2, "2024-05-02T03:00:00Z", 3600),
+            stored(3, "2024-05-02T04:00:00Z", 3600),
+        ];
+        let days = split_by_day(&activities, Some(chrono_tz::America::New_York));
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].0, NaiveDate::from_ymd_opt(2024, 5, 1).unwrap());
+        assert_eq!(days[0].1.len(), 2);
+        assert_eq!(days[1].0, NaiveDate::from_ymd_opt(2024, 5, 2).unwrap());
+        assert_eq!(days[1].1.len(), 1);
+
+        // The LLM's 2.5 hours shared 2:1 between the days
+        let worklogs = share_by_day(days, 9000);
+        assert_eq!(
+            worklogs,
+            vec![
+                DayWorklog {
+                    started: at("2024-05-02T02:00:00Z"),
+                    time_secs: 6000,
+                    activity_ids: vec![1, 2],
+                },
+                DayWorklog {
+                    started: at("2024-05-02T04:00:00Z"),
+                    time_secs: 3000,
+                    activity_ids: vec![3],
+                },
+            ]
+        );
+
+        // The same hours in UTC are a single day
+        assert_eq!(split_by_day(&activities, Some(chrono_tz::UTC)).len(), 1);
+    }
+
+    #[test]
+    fn test_clip_to_working_hours() {
+        let stored = |id: i64, timestamp: &str, duration_secs: u64| {
+            let mut stored = StoredActivity::from(&activity("PROJ-1 main.rs", duration_secs));
+            stored.id = id;
+            stored.timestamp = at(timestamp);
+            stored
+        };
+        let hours = WorkingHours {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+        };
+        // Thursday 10:00, Thursday 16:30 to 17:30, Thursday 08:00, Saturday 10:00
+        let activities = vec![
+            stored(1, "2024-05-02T10:00:00Z", 600),
+            stored(2, "2024-05-02T16:30:00Z", 3600),
+            stored(3, "2024-05-02T08:00:00Z", 1800),
+            stored(4, "2024-05-04T10:00:00Z", 600),
+        ];
+
+        let (inside, outside) =
+            clip_to_working_hours(activities.clone(), &hours, Some(chrono_tz::UTC));
+
+        let summary = |list: &[StoredActivity]| {
+            list.iter()
+                .map(|a| (a.id, a.timestamp, a.duration_secs))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary(&inside),
+            vec![
+                (1, at("2024-05-02T10:00:00Z"), 600),
+                (2, at("2024-05-02T16:30:00Z"), 1800)
+            ]
+        );
+        assert_eq!(
+            summary(&outside),
+            vec![
+                (2, at("2024-05-02T16:30:00Z"), 1800),
+                (3, at("2024-05-02T08:00:00Z"), 1800),
+                (4, at("2024-05-04T10:00:00Z"), 600),
+            ]
+        );
+
+        // In New York (UTC-4) only the session crossing 17:00 UTC falls in working hours
+        let (inside, outside) =
+            clip_to_working_hours(activities, &hours, Some(chrono_tz::America::New_York));
+        assert_eq!(
+            summary(&inside),
+            vec![(2, at("2024-05-02T16:30:00Z"), 3600)]
+        );
+        assert_eq!(
+            outside.iter().map(|a| a.id).collect::<Vec<_>>(),
+            vec![1, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_clip_keeps_working_part_that_starts_late() {
+        let mut early = StoredActivity::from(&activity("PROJ-1 main.rs", 3600));
+        early.id = 7;
+        early.timestamp = at("2024-05-02T08:30:00Z");
+        let hours = WorkingHours {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            days: vec![Weekday::Thu],
+        };
+
+        let (inside, outside) = clip_to_working_hours(vec![early], &hours, Some(chrono_tz::UTC));
+
+        assert_eq!(inside.len(), 1);
+        assert_eq!(inside[0].timestamp, at("2024-05-02T09:00:00Z"));
+        assert_eq!(inside[0].duration_secs, 1800);
+        assert_eq!(outside[0].id, 7);
+        assert_eq!(outside[0].duration_secs, 1800);
+    }
+
+    #[test]
+    fn test_working_hours_starting_in_dst_gap() {
+        let mut sunday = StoredActivity::from(&activity("PROJ-1 main.rs", 3600));
+        sunday.id = 8;
+        // 10:00 in Berlin on the day clocks skip from 02:00 to 03:00
+        sunday.timestamp = at("2024-03-31T08:00:00Z");
+        let hours = WorkingHours {
+            start: NaiveTime::from_hms_opt(2, 30, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            days: vec![Weekday::Sun],
+        };
+
+        let (inside, outside) =
+            clip_to_working_hours(vec![sunday], &hours, Some(chrono_tz::Europe::Berlin));
+
+        assert_eq!(inside.len(), 1);
+        assert_eq!(inside[0].duration_secs, 3600);
+        assert!(outside.is_empty());
+        let windows = working_windows(
+            at("2024-03-31T08:00:00Z"),
+            at("2024-03-31T09:00:00Z"),
+            &hours,
+            Some(chrono_tz::Europe::Berlin),
+        );
+        // The day starts when the clocks reach 03:00 (01:00 UTC)
+        assert_eq!(windows[0].0, at("2024-03-31T01:00:00Z"));
+    }
+
+    #[test]
+    fn test_drop_short_activities() {
+        let activities = vec![
+            activity("PROJ-1", 600),
+            activity("blip", 5),
+            activity("PROJ-2", 60),
+            activity("notification", 59),
+        ];
+
+        let (kept, dropped) = drop_short_activities(activities, 60);
+        assert_eq!(dropped, 2);
+        let titles: Vec<&str> = kept.iter().map(|a| a.window_title.as_str()).collect();
+        assert_eq!(titles, vec!["PROJ-1", "PROJ-2"]);
+    }
 }
@@ -0,0 +1,94 @@
+//! Log level chosen with `-v`/`-q` instead of `RUST_LOG`, which stays in charge when set.
+//! Has no crate dependencies so the tray binary can include it too.
+
+use log::LevelFilter;
+
+/// Environment variable whose filter wins over the flags
+pub const RUST_LOG_ENV: &str = "RUST_LOG";
+
+/// Info by default, debug for `-v`, trace for `-vv` (or more), warnings only for `-q`
+pub fn level_filter(verbose: u8, quiet: bool) -> LevelFilter {
+    if quiet {
+        return LevelFilter::Warn;
+    }
+    match verbose {
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// The level asked for by `-v`, `-vv`, `--verbose`, `-q` or `--quiet` among `args`, for
+/// binaries that don't parse arguments with clap
+#[allow(dead_code)] // only the tray binary uses it
+pub fn level_from_args(args: impl IntoIterator<Item = String>) -> LevelFilter {
+    let mut verbose = 0u8;
+    let mut quiet = false;
+    for arg in args {
+        match arg.as_str() {
+            "--verbose" => verbose = verbose.saturating_add(1),
+            "--quiet" => quiet = true,
+            short if short.starts_with('-') && !short.starts_with("--") => {
+                for flag in short.chars().skip(1) {
+                    match flag {
+                        'v' => verbose = verbose.saturating_add(1),
+                        'q' => quiet = true,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    level_filter(verbose, quiet)
+}
+
+/// The `RUST_LOG` filter when it is set, otherwise `level` for everything
+pub fn filter(level: LevelFilter) -> String {
+    std::env::var(RUST_LOG_ENV)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| level.as_str().to_lowercase())
+}
+
+/// Start `env_logger` with `filter(level)`, honouring `RUST_LOG_STYLE` as usual
+pub fn init_env_logger(level: LevelFilter) {
+    let mut builder = env_logger::Builder::new();
+    builder.parse_filters(&filter(level));
+    if let Ok(style) = std::env::var("RUST_LOG_STYLE") {
+        builder.parse_write_style(&style);
+    }
+    builder.init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_filter() {
+        assert_eq!(level_filter(0, false), LevelFilter::Info);
+        assert_eq!(level_filter(1, false), LevelFilter::Debug);
+        assert_eq!(level_filter(2, false), LevelFilter::Trace);
+        assert_eq!(level_filter(5, false), LevelFilter::Trace);
+        assert_eq!(level_filter(0, true), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn test_level_from_args() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(level_from_args(args(&["tray"])), LevelFilter::Info);
+        assert_eq!(level_from_args(args(&["tray", "-v"])), LevelFilter::Debug);
+        assert_eq!(level_from_args(args(&["tray", "-vv"])), LevelFilter::Trace);
+        assert_eq!(
+            level_from_args(args(&["tray", "--verbose", "-v"])),
+            LevelFilter::Trace
+        );
+        assert_eq!(
+            level_from_args(args(&["tray", "--quiet"])),
+            LevelFilter::Warn
+        );
+        assert_eq!(level_from_args(args(&["tray", "--"])), LevelFilter::Info);
+    }
+}
@@ -0,0 +1,100 @@
+//! Build and setup details for the `version` command, for pasting into bug reports
+
+use crate::config::Config;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Commit the binary was built from, set by `build.rs`
+const GIT_SHA: &str = env!("WTJE_GIT_SHA");
+/// Build time in seconds since the epoch, set by `build.rs`
+const BUILD_TIMESTAMP: &str = env!("WTJE_BUILD_TIMESTAMP");
+
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub git_sha: Option<&'static str>,
+    pub build_date: Option<DateTime<Utc>>,
+    /// Enabled flags from the config file; `None` when there is none to read
+    pub integrations: Option<Integrations>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Integrations {
+    pub jira: bool,
+    pub salesforce: bool,
+    pub llm: bool,
+}
+
+impl VersionInfo {
+    /// Details of this build, with the integrations enabled in `config`
+    pub fn new(config: Option<&Config>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: Some(GIT_SHA).filter(|sha| !sha.is_empty()),
+            build_date: BUILD_TIMESTAMP
+                .parse()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+            integrations: config.map(|config| Integrations {
+                jira: config.jira.enabled,
+                salesforce: config.salesforce.enabled,
+                llm: config.llm.enabled,
+            }),
+        }
+    }
+
+    pub fn print(&self) {
+        println!("{} {}", env!("CARGO_PKG_NAME"), self.version);
+        println!("commit: {}", self.git_sha.unwrap_or("unknown"));
+        match self.build_date {
+            Some(date) => println!("built: {}", date.format("%Y-%m-%d %H:%M UTC")),
+            None => println!("built: unknown"),
+        }
+        match &self.integrations {
+            Some(integrations) => {
+                let state = |enabled: bool| if enabled { "enabled" } else { "disabled" };
+                println!(
+                    "jira: {}, salesforce: {}, llm: {}",
+                    state(integrations.jira),
+                    state(integrations.salesforce),
+                    state(integrations.llm)
+                );
+            }
+            None => println!("integrations: no config file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_expected_fields() {
+        let mut config = Config::default();
+        config.salesforce.enabled = true;
+        let info = VersionInfo::new(Some(&config));
+
+        let json = serde_json::to_value(&info).unwrap();
+        let mut fields: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec!["build_date", "git_sha", "integrations", "version"]
+        );
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            json["integrations"],
+            serde_json::json!({ "jira": true, "salesforce": true, "llm": false })
+        );
+
+        let without_config = serde_json::to_value(VersionInfo::new(None)).unwrap();
+        assert!(without_config["integrations"].is_null());
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+/// Kinds of work a worklog can be tagged with. The LLM describes work in free text;
+/// `normalize` maps that onto this fixed list so it can drive worklog attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkType {
+    Coding,
+    Review,
+    Meeting,
+    Docs,
+    Ops,
+}
+
+impl WorkType {
+    pub const ALL: [WorkType; 5] = [
+        WorkType::Coding,
+        WorkType::Review,
+        WorkType::Meeting,
+        WorkType::Docs,
+        WorkType::Ops,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkType::Coding => "coding",
+            WorkType::Review => "review",
+            WorkType::Meeting => "meeting",
+            WorkType::Docs => "docs",
+            WorkType::Ops => "ops",
+        }
+    }
+
+    /// Words in free-text descriptions that indicate each type, matched against whole
+    /// words; a trailing `*` also matches longer words starting with it. Review comes before
+    /// coding so "code review" isn't taken for coding.
+    fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            WorkType::Review => &["review*", "pull request*", "pr", "prs", "approv*"],
+            WorkType::Meeting => &[
+                "meeting*",
+                "call",
+                "calls",
+                "standup*",
+                "stand-up*",
+                "sync",
+                "syncs",
+                "planning",
+                "retro*",
+            ],
+            WorkType::Docs => &[
+                "doc",
+                "docs",
+                "document*",
+                "writing",
+                "spec",
+                "specs",
+                "specification*",
+                "wiki*",
+                "confluence",
+            ],
+            WorkType::Ops => &[
+                "ops",
+                "devops",
+                "deploy*",
+                "release*",
+                "infra*",
+                "incident*",
+                "support*",
+                "monitor*",
+                "on-call",
+            ],
+            WorkType::Coding => &[
+                "code",
+                "coding",
+                "develop*",
+                "implement*",
+                "programming",
+                "debug*",
+                "bug*",
+                "fix*",
+                "refactor*",
+                "test*",
+            ],
+        }
+    }
+
+    /// Map an LLM's free-text work type (e.g. "Code review", "bugfixing") onto the taxonomy
+    pub fn normalize(text: &str) -> Option<WorkType> {
+        let text = text.trim().to_lowercase();
+        if let Some(exact) = WorkType::ALL.iter().find(|t| text == t.as_str()) {
+            return Some(*exact);
+        }
+
+        let words: Vec<&str> = text
+            .split(|c: char| !c.is_alphanumeric() && c != '-')
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        [
+            WorkType::Review,
+            WorkType::Meeting,
+            WorkType::Docs,
+            WorkType::Ops,
+            WorkType::Coding,
+        ]
+        .into_iter()
+        .find(|t| {
+            t.keywords()
+                .iter()
+                .any(|keyword| contains_phrase(&words, keyword))
+        })
+    }
+}
+
+/// Whether `words` has the words of `keyword` in a row, each matching as in `keywords`
+fn contains_phrase(words: &[&str], keyword: &str) -> bool {
+    let phrase: Vec<&str> = keyword.split(' ').collect();
+    words.windows(phrase.len()).any(|window| {
+        window
+            .iter()
+            .zip(&phrase)
+            .all(|(word, pattern)| match pattern.strip_suffix('*') {
+                Some(stem) => word.starts_with(stem),
+                None => word == pattern,
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_free_text() {
+        assert_eq!(WorkType::normalize("development"), Some(WorkType::Coding));
+        assert_eq!(WorkType::normalize("Bug fixing"), Some(WorkType::Coding));
+        assert_eq!(WorkType::normalize("Code review"), Some(WorkType::Review));
+        assert_eq!(
+            WorkType::normalize("Daily standup"),
+            Some(WorkType::Meeting)
+        );
+        assert_eq!(WorkType::normalize("Documentation"), Some(WorkType::Docs));
+        assert_eq!(WorkType::normalize("Deployment"), Some(WorkType::Ops));
+        assert_eq!(WorkType::normalize(" OPS "), Some(WorkType::Ops));
+        assert_eq!(WorkType::normalize("lunch"), None);
+        assert_eq!(WorkType::normalize("bugfixing"), Some(WorkType::Coding));
+        assert_eq!(
+            WorkType::normalize("Reviewing a pull request"),
+            Some(WorkType::Review)
+        );
+        assert_eq!(WorkType::normalize("PR feedback"), Some(WorkType::Review));
+        assert_eq!(WorkType::normalize("On-call"), Some(WorkType::Ops));
+    }
+
+    #[test]
+    fn test_normalize_matches_whole_words() {
+        assert_eq!(
+            WorkType::normalize("async debugging"),
+            Some(WorkType::Coding)
+        );
+        assert_eq!(WorkType::normalize("docker"), None);
+        assert_eq!(WorkType::normalize("inspection"), None);
+        assert_eq!(WorkType::normalize("stops"), None);
+        assert_eq!(WorkType::normalize("devops"), Some(WorkType::Ops));
+        assert_eq!(WorkType::normalize("sprint"), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! The command line as scripts, and the tray and desktop apps starting the daemon, use it
+
+use std::process::Command;
+
+fn cli() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_work-to-jira-effort"))
+}
+
+#[test]
+fn test_daemon_subcommand_parses() {
+    let output = cli()
+        .args(["daemon", "--port", "8787", "--help"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let help = String::from_utf8_lossy(&output.stdout);
+    assert!(help.contains("--port"), "{}", help);
+    assert!(help.contains("8787"), "{}", help);
+}
+
+#[test]
+fn test_daemon_rejects_invalid_port() {
+    let output = cli()
+        .args(["daemon", "--port", "not-a-port"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--port"));
+}
+
+/// Building this test builds the binary, so a module missing from `main.rs` fails here
+#[test]
+fn test_binary_builds_and_lists_daemon() {
+    let output = cli().arg("--help").output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("daemon"));
+}
+
+#[test]
+fn test_daemon_needs_an_existing_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("missing.toml");
+
+    let output = cli()
+        .arg("--config")
+        .arg(&config)
+        .arg("daemon")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No config file"));
+    assert!(!config.exists());
+}
+
+#[test]
+fn test_only_init_creates_the_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("missing.toml");
+
+    for args in [
+        &["undo"][..],
+        &["backup"],
+        &["maintenance"],
+        &["projects"],
+        &["export", "1"],
+        &["notifications"],
+    ] {
+        let output = cli()
+            .arg("--config")
+            .arg(&config)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{:?}", args);
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("No config file"),
+            "{:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(!config.exists(), "{:?} created the config", args);
+    }
+}
+
+#[test]
+fn test_check_skips_screenpipe() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    let data_dir = dir.path().join("data");
+    assert!(cli()
+        .arg("--config")
+        .arg(&config)
+        .arg("init")
+        .output()
+        .unwrap()
+        .status
+        .success());
+    // Without integrations there are no credentials to fill in
+    let defaults = std::fs::read_to_string(&config).unwrap();
+    std::fs::write(
+        &config,
+        defaults.replace("enabled = true", "enabled = false"),
+    )
+    .unwrap();
+
+    let output = cli()
+        .arg("--config")
+        .arg(&config)
+        .arg("--data-dir")
+        .arg(&data_dir)
+        .args(["--skip-screenpipe", "check"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--skip-screenpipe"), "{}", stdout);
+    assert!(
+        !stdout.contains("Starting embedded Screenpipe"),
+        "{}",
+        stdout
+    );
+    assert!(!data_dir.join("screenpipe").exists());
+}